@@ -1,12 +1,52 @@
-use berry::parse::parse_lockfile;
+use berry::parse::{parse_lockfile, parse_lockfile_borrowed, parse_lockfile_parallel};
 use berry_test::load_fixture;
 use criterion::{Criterion, criterion_group, criterion_main};
 use memory_stats::memory_stats;
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::fs;
 use std::hint::black_box;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
+/// Wraps the system allocator to count allocations and allocated bytes
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+    unsafe { System.alloc(layout) }
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    unsafe { System.dealloc(ptr, layout) }
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+    unsafe { System.realloc(ptr, layout, new_size) }
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Run `f`, returning how many allocations and bytes it made
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (usize, usize) {
+  let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+  let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+  drop(black_box(f()));
+  (
+    ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+    ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes,
+  )
+}
+
 /// Benchmark parsing with different fixture sizes
 fn benchmark_fixtures(c: &mut Criterion) {
   let mut group = c.benchmark_group("fixture_parsing");
@@ -171,6 +211,80 @@ fn benchmark_zero_allocation(c: &mut Criterion) {
   group.finish();
 }
 
+/// Compare the owned and borrowed parsers, printing how much each allocates
+fn benchmark_borrowed_vs_owned(c: &mut Criterion) {
+  let mut group = c.benchmark_group("borrowed_vs_owned");
+
+  let fixtures = vec![
+    ("minimal-berry.lock", "small"),
+    ("workspaces.yarn.lock", "medium"),
+    ("auxiliary-packages.yarn.lock", "large"),
+    ("berry.lock", "extra-large"),
+    ("resolutions-patches.yarn.lock", "extra-extra-large"),
+  ];
+
+  for (fixture_name, size_label) in fixtures {
+    let fixture = load_fixture(fixture_name);
+
+    let (owned_allocs, owned_bytes) = count_allocations(|| parse_lockfile(&fixture));
+    let (borrowed_allocs, borrowed_bytes) = count_allocations(|| parse_lockfile_borrowed(&fixture));
+    println!(
+      "Allocations for {fixture_name}: owned {owned_allocs} ({owned_bytes} bytes), borrowed {borrowed_allocs} ({borrowed_bytes} bytes)"
+    );
+
+    group.bench_function(format!("owned_{size_label}"), |b| {
+      b.iter(|| {
+        let result = parse_lockfile(black_box(&fixture));
+        assert!(result.is_ok(), "Should parse {fixture_name} successfully");
+        result.unwrap().1
+      });
+    });
+
+    group.bench_function(format!("borrowed_{size_label}"), |b| {
+      b.iter(|| {
+        let result = parse_lockfile_borrowed(black_box(&fixture));
+        assert!(result.is_ok(), "Should parse {fixture_name} successfully");
+        result.unwrap().1
+      });
+    });
+  }
+
+  group.finish();
+}
+
+/// Compare sequential and parallel entry parsing on the larger fixtures
+fn benchmark_parallel(c: &mut Criterion) {
+  let mut group = c.benchmark_group("parallel");
+
+  let fixtures = vec![
+    ("auxiliary-packages.yarn.lock", "large"),
+    ("berry.lock", "extra-large"),
+    ("resolutions-patches.yarn.lock", "extra-extra-large"),
+  ];
+
+  for (fixture_name, size_label) in fixtures {
+    let fixture = load_fixture(fixture_name);
+
+    group.bench_function(format!("sequential_{size_label}"), |b| {
+      b.iter(|| {
+        let result = parse_lockfile(black_box(&fixture));
+        assert!(result.is_ok(), "Should parse {fixture_name} successfully");
+        result.unwrap().1
+      });
+    });
+
+    group.bench_function(format!("parallel_{size_label}"), |b| {
+      b.iter(|| {
+        let result = parse_lockfile_parallel(black_box(&fixture));
+        assert!(result.is_ok(), "Should parse {fixture_name} successfully");
+        result.unwrap().1
+      });
+    });
+  }
+
+  group.finish();
+}
+
 /// Benchmark heap usage after parsing
 fn benchmark_heap_usage(c: &mut Criterion) {
   let mut group = c.benchmark_group("heap_usage");
@@ -319,6 +433,8 @@ criterion_group!(
   benchmark_parsing_speed_vs_size,
   benchmark_memory_usage,
   benchmark_heap_usage,
+  benchmark_borrowed_vs_owned,
+  benchmark_parallel,
   benchmark_zero_allocation,
   benchmark_individual_functions,
   benchmark_input_characteristics,
@@ -0,0 +1,299 @@
+//! Zero-copy views of a lockfile that borrow from the source text
+//!
+//! Every value in a yarn lockfile is a slice of the file (quotes are trimmed
+//! but nothing is unescaped, and unknown scalars are kept exactly as written
+//! until [`BorrowedExtraProperty::into_owned`]), so the parser can hand back `&'a str`s
+//! without allocating for each field. [`BorrowedLockfile`] is what the parser
+//! builds first; [`BorrowedLockfile::into_owned`] turns it into a [`Lockfile`]
+//! that no longer needs the source to stay alive.
+//!
+//! Dependency maps are kept as `Vec`s in file order rather than `HashMap`s,
+//! which keeps the borrowed form cheap to build.
+
+use crate::ident::{Descriptor, Ident};
+use crate::intern::Interner;
+use crate::locator::Locator;
+use crate::lockfile::{Entry, Lockfile, Metadata};
+use crate::metadata::{DependencyMeta, PeerDependencyMeta};
+use crate::package::{ExtraProperty, LanguageName, LinkType, Package};
+use crate::serialize::unquote_string;
+use std::fmt;
+
+/// Borrowed counterpart of [`Ident`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BorrowedIdent<'a> {
+  /// The scope of the package including the `@`, e.g. `@babel`
+  pub scope: Option<&'a str>,
+  /// The name of the package, e.g. `core`
+  pub name: &'a str,
+}
+
+impl<'a> BorrowedIdent<'a> {
+  /// Split a package name like `@babel/core` or `debug` into scope and name
+  pub fn from_name(full_name: &'a str) -> Self {
+    match full_name.strip_prefix('@').and_then(|s| s.split_once('/')) {
+      Some((scope, name)) => Self {
+        scope: Some(&full_name[..=scope.len()]),
+        name,
+      },
+      // Malformed scoped package, treat as simple name
+      None => Self {
+        scope: None,
+        name: full_name,
+      },
+    }
+  }
+
+  /// Intern the scope and name, producing an owned [`Ident`]
+  pub fn into_owned(self, interner: &mut Interner) -> Ident {
+    Ident::new(
+      self.scope.map(|scope| interner.intern(scope)),
+      interner.intern(self.name),
+    )
+  }
+}
+
+impl fmt::Display for BorrowedIdent<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.scope {
+      Some(scope) => write!(f, "{scope}/{}", self.name),
+      None => f.write_str(self.name),
+    }
+  }
+}
+
+/// Borrowed counterpart of [`Descriptor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BorrowedDescriptor<'a> {
+  pub ident: BorrowedIdent<'a>,
+  /// The raw range, e.g. `npm:^1.0.0`
+  pub range: &'a str,
+}
+
+impl BorrowedDescriptor<'_> {
+  pub fn into_owned(self, interner: &mut Interner) -> Descriptor {
+    Descriptor::new(self.ident.into_owned(interner), self.range.to_string())
+  }
+}
+
+impl fmt::Display for BorrowedDescriptor<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}@{}", self.ident, self.range)
+  }
+}
+
+/// Borrowed counterpart of [`Locator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BorrowedLocator<'a> {
+  pub ident: BorrowedIdent<'a>,
+  /// The reference, e.g. `npm:1.0.0`
+  pub reference: &'a str,
+}
+
+impl BorrowedLocator<'_> {
+  pub fn into_owned(self, interner: &mut Interner) -> Locator {
+    Locator::new(self.ident.into_owned(interner), self.reference.to_string())
+  }
+}
+
+/// Borrowed counterpart of [`ExtraProperty`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BorrowedExtraProperty<'a> {
+  /// The value as written, with its quotes and escapes if it has any
+  Scalar(&'a str),
+  Block(&'a str),
+}
+
+impl BorrowedExtraProperty<'_> {
+  pub fn into_owned(self) -> ExtraProperty {
+    match self {
+      Self::Scalar(value) => ExtraProperty::Scalar(unquote_string(value).into_owned()),
+      Self::Block(body) => ExtraProperty::Block(body.to_string()),
+    }
+  }
+}
+
+/// Borrowed counterpart of [`Package`], see the field docs there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorrowedPackage<'a> {
+  pub version: Option<&'a str>,
+  pub resolution: Option<&'a str>,
+  pub resolution_locator: Option<BorrowedLocator<'a>>,
+  pub language_name: &'a str,
+  pub link_type: LinkType,
+  pub checksum: Option<&'a str>,
+  pub conditions: Option<&'a str>,
+  pub dependencies: Vec<BorrowedDescriptor<'a>>,
+  pub dependencies_meta: Vec<(BorrowedIdent<'a>, DependencyMeta)>,
+  pub peer_dependencies: Vec<BorrowedDescriptor<'a>>,
+  pub peer_dependencies_meta: Vec<(BorrowedIdent<'a>, PeerDependencyMeta)>,
+  pub bin: Vec<(&'a str, &'a str)>,
+  pub extra: Vec<(&'a str, BorrowedExtraProperty<'a>)>,
+}
+
+impl Default for BorrowedPackage<'_> {
+  fn default() -> Self {
+    Self {
+      version: None,
+      resolution: None,
+      resolution_locator: None,
+      language_name: "unknown",
+      link_type: LinkType::Hard,
+      checksum: None,
+      conditions: None,
+      dependencies: Vec::new(),
+      dependencies_meta: Vec::new(),
+      peer_dependencies: Vec::new(),
+      peer_dependencies_meta: Vec::new(),
+      bin: Vec::new(),
+      extra: Vec::new(),
+    }
+  }
+}
+
+impl BorrowedPackage<'_> {
+  pub fn into_owned(self, interner: &mut Interner) -> Package {
+    let language_name = LanguageName::new(interner.intern(self.language_name));
+    let mut package = Package::new(language_name, self.link_type);
+    package.version = self.version.map(str::to_string);
+    package.resolution = self.resolution.map(str::to_string);
+    package.resolution_locator = self
+      .resolution_locator
+      .map(|locator| locator.into_owned(interner));
+    package.checksum = self.checksum.map(str::to_string);
+    package.conditions = self.conditions.map(str::to_string);
+    package.dependencies = self
+      .dependencies
+      .into_iter()
+      .map(|d| {
+        let descriptor = d.into_owned(interner);
+        (*descriptor.ident(), descriptor)
+      })
+      .collect();
+    package.dependencies_meta = self
+      .dependencies_meta
+      .into_iter()
+      .map(|(ident, meta)| (ident.into_owned(interner), Some(meta)))
+      .collect();
+    package.peer_dependencies = self
+      .peer_dependencies
+      .into_iter()
+      .map(|d| {
+        let descriptor = d.into_owned(interner);
+        (*descriptor.ident(), descriptor)
+      })
+      .collect();
+    package.peer_dependencies_meta = self
+      .peer_dependencies_meta
+      .into_iter()
+      .map(|(ident, meta)| (ident.into_owned(interner), meta))
+      .collect();
+    package.bin = self
+      .bin
+      .into_iter()
+      .map(|(name, path)| (name.to_string(), path.to_string()))
+      .collect();
+    package.extra = self
+      .extra
+      .into_iter()
+      .map(|(key, value)| (key.to_string(), value.into_owned()))
+      .collect();
+    package
+  }
+}
+
+/// Borrowed counterpart of [`Entry`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorrowedEntry<'a> {
+  pub descriptors: Vec<BorrowedDescriptor<'a>>,
+  pub package: BorrowedPackage<'a>,
+}
+
+impl BorrowedEntry<'_> {
+  pub fn into_owned(self, interner: &mut Interner) -> Entry {
+    Entry::new(
+      self
+        .descriptors
+        .into_iter()
+        .map(|descriptor| descriptor.into_owned(interner))
+        .collect(),
+      self.package.into_owned(interner),
+    )
+  }
+}
+
+/// Borrowed counterpart of [`Metadata`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorrowedMetadata<'a> {
+  pub version: &'a str,
+  pub cache_key: &'a str,
+}
+
+impl BorrowedMetadata<'_> {
+  pub fn into_owned(self) -> Metadata {
+    Metadata::new(self.version.to_string(), self.cache_key.to_string())
+  }
+}
+
+/// A lockfile whose strings all borrow from the text it was parsed from
+///
+/// Build one with [`crate::parse::parse_borrowed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BorrowedLockfile<'a> {
+  pub metadata: BorrowedMetadata<'a>,
+  pub entries: Vec<BorrowedEntry<'a>>,
+  pub resolutions: Option<Vec<(&'a str, &'a str)>>,
+  pub constraints: Option<Vec<(&'a str, &'a str)>>,
+}
+
+impl BorrowedLockfile<'_> {
+  /// Copy every string out of the source, producing an owned [`Lockfile`]
+  ///
+  /// Idents and language names are interned into the new lockfile's [`Interner`].
+  pub fn into_owned(self) -> Lockfile {
+    let mut interner = Interner::new();
+    let entries = self
+      .entries
+      .into_iter()
+      .map(|entry| entry.into_owned(&mut interner))
+      .collect();
+
+    Lockfile {
+      metadata: self.metadata.into_owned(),
+      entries,
+      resolutions: self.resolutions.map(owned_pairs),
+      constraints: self.constraints.map(owned_pairs),
+      interner,
+    }
+  }
+}
+
+pub(crate) fn owned_pairs(pairs: Vec<(&str, &str)>) -> Vec<(String, String)> {
+  pairs
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_ident_from_name() {
+    let scoped = BorrowedIdent::from_name("@babel/core");
+    assert_eq!(scoped.scope, Some("@babel"));
+    assert_eq!(scoped.name, "core");
+    let mut interner = Interner::new();
+    let owned = scoped.into_owned(&mut interner);
+    assert_eq!(owned.display(&interner).to_string(), "@babel/core");
+
+    let plain = BorrowedIdent::from_name("debug");
+    assert_eq!(plain.scope, None);
+    assert_eq!(plain.to_string(), "debug");
+
+    let malformed = BorrowedIdent::from_name("@nope");
+    assert_eq!(malformed.scope, None);
+    assert_eq!(malformed.name, "@nope");
+  }
+}
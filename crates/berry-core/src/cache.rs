@@ -0,0 +1,414 @@
+//! Yarn cache archive names, and checking a cache directory against a lockfile
+//!
+//! Yarn stores each fetched package as `.yarn/cache/<slug>-<suffix>.zip`. The
+//! slug is built from the locator, e.g. `lodash-npm-4.17.21-6382451519`, and
+//! the suffix is the start of the package's checksum, or the cache key when the
+//! checksum was made with a different one.
+
+use crate::checksum::Checksum;
+use crate::ident::Ident;
+use crate::intern::Interner;
+use crate::locator::Locator;
+use crate::lockfile::{Lockfile, Metadata};
+use crate::package::{LinkType, Package};
+use crate::reference::RangeParts;
+use crate::semver::Version;
+use std::collections::{BTreeSet, HashSet};
+use std::io;
+use std::path::Path;
+
+/// How much of the locator hash and checksum yarn keeps in filenames
+const HASH_TRUNCATE: usize = 10;
+
+/// `@scope-name` or `name`, as in yarn's `slugifyIdent`
+pub fn slugify_ident(ident: &Ident, interner: &Interner) -> String {
+  let name = ident.name(interner);
+  ident
+    .scope(interner)
+    .map_or_else(|| name.to_string(), |scope| format!("{scope}-{name}"))
+}
+
+/// The locator part of a cache filename, e.g. `lodash-npm-4.17.21-6382451519`,
+/// as in yarn's `slugifyLocator`
+///
+/// The version only appears when the reference's selector is a plain semver
+/// version, so `patch:` and `workspace:` references just keep their protocol.
+pub fn slugify_locator(locator: &Locator, interner: &Interner) -> String {
+  let parts = RangeParts::parse(locator.reference());
+  let protocol = parts.protocol.unwrap_or("exotic");
+  let reference = Version::parse(parts.selector).map_or_else(
+    |_| protocol.to_string(),
+    |version| {
+      // `semver.valid` normalizes the version and drops build metadata
+      let version = Version {
+        build: Vec::new(),
+        ..version
+      };
+      format!("{protocol}-{version}")
+    },
+  );
+  let hash = locator.locator_hash(interner);
+
+  format!(
+    "{}-{reference}-{}",
+    slugify_ident(locator.ident(), interner),
+    &hash[..HASH_TRUNCATE]
+  )
+}
+
+/// The name of the zip yarn caches a package under, e.g.
+/// `lodash-npm-4.17.21-6382451519-c08619c038.zip`
+///
+/// Returns `None` for packages without a resolution. Follows yarn's
+/// `Cache.getLocatorPath`: the suffix is the start of the checksum when the
+/// checksum was made with the lockfile's `cacheKey`, and the `cacheKey` itself
+/// otherwise.
+pub fn cache_filename(
+  package: &Package,
+  metadata: &Metadata,
+  interner: &Interner,
+) -> Option<String> {
+  let locator = package.resolution_locator.as_ref()?;
+  let slug = slugify_locator(locator, interner);
+
+  let checksum = package
+    .parsed_checksum()
+    .and_then(Result::ok)
+    .filter(|checksum| checksum.is_compatible_with(&metadata.cache_key));
+  Some(checksum.map_or_else(
+    || format!("{slug}-{}.zip", metadata.cache_key),
+    |checksum| format!("{slug}-{}.zip", &checksum.digest[..HASH_TRUNCATE]),
+  ))
+}
+
+/// The result of [`verify_cache_dir`], each list sorted by filename
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheReport {
+  /// Archives the lockfile expects that aren't in the directory
+  pub missing: Vec<String>,
+  /// Zips that don't belong to any entry in the lockfile
+  pub orphaned: Vec<String>,
+  /// Zips for an entry in the lockfile, but named for another checksum or
+  /// cache key, e.g. left over from before a yarn upgrade
+  pub mismatched: Vec<String>,
+}
+
+impl CacheReport {
+  /// Whether the directory holds exactly the archives the lockfile expects
+  pub fn is_clean(&self) -> bool {
+    self.missing.is_empty() && self.orphaned.is_empty() && self.mismatched.is_empty()
+  }
+}
+
+/// Compare the zips in a cache directory like `.yarn/cache` with the archives
+/// the lockfile expects
+///
+/// Only hard-linked packages are cached; workspaces, `link:` and `portal:`
+/// packages aren't. Packages with `conditions`, like `fsevents`, are only
+/// fetched on matching systems, so they are never reported as missing.
+/// Files other than `*.zip` are ignored.
+pub fn verify_cache_dir(lockfile: &Lockfile, path: impl AsRef<Path>) -> io::Result<CacheReport> {
+  let mut expected = BTreeSet::new();
+  let mut optional = HashSet::new();
+  for entry in &lockfile.entries {
+    let package = &entry.package;
+    if package.link_type != LinkType::Hard {
+      continue;
+    }
+    if let Some(filename) = cache_filename(package, &lockfile.metadata, &lockfile.interner) {
+      if package.conditions.is_some() {
+        optional.insert(filename.clone());
+      }
+      expected.insert(filename);
+    }
+  }
+  let slugs: HashSet<_> = expected.iter().filter_map(|name| slug_of(name)).collect();
+
+  let mut found = HashSet::new();
+  let mut report = CacheReport::default();
+  for dir_entry in std::fs::read_dir(path)? {
+    let dir_entry = dir_entry?;
+    let Ok(name) = dir_entry.file_name().into_string() else {
+      continue;
+    };
+    let is_zip = Path::new(&name)
+      .extension()
+      .is_some_and(|extension| extension == "zip");
+    if !is_zip || !dir_entry.file_type()?.is_file() {
+      continue;
+    }
+
+    if expected.contains(&name) {
+      found.insert(name);
+    } else if slug_of(&name).is_some_and(|slug| slugs.contains(slug)) {
+      report.mismatched.push(name);
+    } else {
+      report.orphaned.push(name);
+    }
+  }
+
+  report.missing = expected
+    .into_iter()
+    .filter(|name| !found.contains(name) && !optional.contains(name))
+    .collect();
+  report.orphaned.sort_unstable();
+  report.mismatched.sort_unstable();
+  Ok(report)
+}
+
+/// A cached zip whose contents don't hash to the lockfile's checksum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+  /// The entry's `resolution`, e.g. `lodash@npm:4.17.21`
+  pub resolution: String,
+  pub filename: String,
+  pub expected: Checksum,
+  /// The hex digest of the zip
+  pub actual: String,
+}
+
+/// The result of [`verify_checksums`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChecksumReport {
+  /// Number of zips whose contents matched their checksum
+  pub verified: usize,
+  /// Zips whose contents didn't match, in lockfile order
+  pub mismatched: Vec<ChecksumMismatch>,
+  /// Resolutions of entries whose `checksum` couldn't be parsed
+  pub invalid: Vec<String>,
+}
+
+impl ChecksumReport {
+  pub fn is_clean(&self) -> bool {
+    self.mismatched.is_empty() && self.invalid.is_empty()
+  }
+}
+
+/// Hash every zip in a cache directory like `.yarn/cache` that belongs to a
+/// lockfile entry, and compare it with the entry's `checksum`
+///
+/// Zips that are missing, or whose checksum was made with another cache key
+/// (and so isn't one yarn would trust either), are skipped; see
+/// [`verify_cache_dir`] to find those.
+pub fn verify_checksums(lockfile: &Lockfile, path: impl AsRef<Path>) -> io::Result<ChecksumReport> {
+  let path = path.as_ref();
+  let mut report = ChecksumReport::default();
+
+  for entry in &lockfile.entries {
+    let package = &entry.package;
+    let resolution = || package.resolution.clone().unwrap_or_default();
+    let checksum = match package.parsed_checksum() {
+      Some(Ok(checksum)) if checksum.is_compatible_with(&lockfile.metadata.cache_key) => checksum,
+      Some(Err(_)) => {
+        report.invalid.push(resolution());
+        continue;
+      }
+      _ => continue,
+    };
+    let Some(filename) = cache_filename(package, &lockfile.metadata, &lockfile.interner) else {
+      continue;
+    };
+
+    let actual = match checksum.algorithm.digest_file(path.join(&filename)) {
+      Ok(actual) => actual,
+      Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+      Err(error) => return Err(error),
+    };
+    if checksum.matches_digest(&actual) {
+      report.verified += 1;
+    } else {
+      report.mismatched.push(ChecksumMismatch {
+        resolution: resolution(),
+        filename,
+        expected: checksum,
+        actual,
+      });
+    }
+  }
+
+  Ok(report)
+}
+
+/// The locator slug of a cache filename, without the checksum or cache key suffix
+fn slug_of(filename: &str) -> Option<&str> {
+  let stem = filename.strip_suffix(".zip")?;
+  stem.rsplit_once('-').map(|(slug, _)| slug)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parse;
+
+  const LOCKFILE: &str = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"@babel/helper-string-parser@npm:^7.25.9":
+  version: 7.25.9
+  resolution: "@babel/helper-string-parser@npm:7.25.9"
+  checksum: 10c0/7244b45d8e65f6b4338a6a68a8556f2cb161b782343e97281a5f2b9b93e420cad0d9f5773a59d79f61d0c448913d06f6a2358a87f2e203cf112e3c5b53522ee6
+  languageName: node
+  linkType: hard
+
+"fsevents@npm:^2.3.2":
+  version: 2.3.3
+  resolution: "fsevents@npm:2.3.3"
+  checksum: 10c0/a1f0c44595123ed717febbc478aa952e47adfc28e2092be66b8ab1635147254ca6cfe1df792a8997f22716d4cbafc73309899ff7bfac2ac3ad8cf2e4ecc3ec60
+  conditions: os=darwin
+  languageName: node
+  linkType: hard
+
+"lodash@npm:^4.17.21":
+  version: 4.17.21
+  resolution: "lodash@npm:4.17.21"
+  checksum: 9/eb835a2e51d381e561e508ce932ea50a8e5a68f4ebdd771ea240d3048244a8d13658acbd502cd4829768c56f2e16bdd4340b9ea141297d472517b83868e677f7
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  languageName: unknown
+  linkType: soft
+"#;
+
+  #[test]
+  fn test_cache_filenames() {
+    let lockfile = parse(LOCKFILE).expect("should parse");
+    let filename = |position: usize| {
+      cache_filename(
+        &lockfile.entries[position].package,
+        &lockfile.metadata,
+        &lockfile.interner,
+      )
+    };
+
+    assert_eq!(
+      filename(0).as_deref(),
+      Some("@babel-helper-string-parser-npm-7.25.9-eade578078-7244b45d8e.zip")
+    );
+    // The checksum was made with another cache key, so yarn names the zip after the current one
+    assert_eq!(
+      filename(2).as_deref(),
+      Some("lodash-npm-4.17.21-6382451519-10c0.zip")
+    );
+
+    let root = lockfile.entries[3]
+      .package
+      .resolution_locator
+      .as_ref()
+      .unwrap();
+    assert_eq!(
+      slugify_locator(root, &lockfile.interner),
+      format!(
+        "root-workspace-{}",
+        &root.locator_hash(&lockfile.interner)[..10]
+      )
+    );
+  }
+
+  #[test]
+  fn test_verify_cache_dir() {
+    let lockfile = parse(LOCKFILE).expect("should parse");
+    let dir = std::env::temp_dir().join(format!("berry-cache-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for name in [
+      "@babel-helper-string-parser-npm-7.25.9-eade578078-7244b45d8e.zip",
+      "lodash-npm-4.17.21-6382451519-eb835a2e51.zip",
+      "left-pad-npm-1.3.0-7ef6a1c2a4-4b40e9b2a4.zip",
+      ".gitignore",
+    ] {
+      std::fs::write(dir.join(name), b"").unwrap();
+    }
+
+    let report = verify_cache_dir(&lockfile, &dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let report = report.expect("should read the directory");
+
+    assert_eq!(report.missing, ["lodash-npm-4.17.21-6382451519-10c0.zip"]);
+    assert_eq!(
+      report.orphaned,
+      ["left-pad-npm-1.3.0-7ef6a1c2a4-4b40e9b2a4.zip"]
+    );
+    assert_eq!(
+      report.mismatched,
+      ["lodash-npm-4.17.21-6382451519-eb835a2e51.zip"]
+    );
+    assert!(!report.is_clean());
+  }
+
+  #[test]
+  fn test_verify_checksums() {
+    let good = crate::hash::make_hash(&["good zip"]);
+    let lockfile = parse(&format!(
+      r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"a@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "a@npm:1.0.0"
+  checksum: 10c0/{good}
+  languageName: node
+  linkType: hard
+
+"b@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "b@npm:1.0.0"
+  checksum: 10c0/{good}
+  languageName: node
+  linkType: hard
+
+"c@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "c@npm:1.0.0"
+  checksum: 10c0/{good}
+  languageName: node
+  linkType: hard
+
+"d@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "d@npm:1.0.0"
+  checksum: not-a-checksum
+  languageName: node
+  linkType: hard
+"#
+    ))
+    .expect("should parse");
+    let filename = |position: usize| {
+      cache_filename(
+        &lockfile.entries[position].package,
+        &lockfile.metadata,
+        &lockfile.interner,
+      )
+      .unwrap()
+    };
+
+    let dir = std::env::temp_dir().join(format!("berry-checksum-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(filename(0)), b"good zip").unwrap();
+    std::fs::write(dir.join(filename(1)), b"tampered zip").unwrap();
+    // `c` isn't in the cache, so it's skipped
+
+    let report = verify_checksums(&lockfile, &dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let report = report.expect("should read the cache");
+
+    assert_eq!(report.verified, 1);
+    assert_eq!(report.mismatched.len(), 1);
+    let mismatch = &report.mismatched[0];
+    assert_eq!(mismatch.resolution, "b@npm:1.0.0");
+    assert_eq!(mismatch.filename, filename(1));
+    assert_eq!(mismatch.expected.digest, good);
+    assert_eq!(mismatch.actual, crate::hash::make_hash(&["tampered zip"]));
+    assert_eq!(report.invalid, ["d@npm:1.0.0"]);
+    assert!(!report.is_clean());
+  }
+}
@@ -0,0 +1,168 @@
+//! Package checksums
+//!
+//! Yarn records the sha512 of each cached zip as the entry's `checksum`. Since
+//! yarn 3.3 it's prefixed with the cache key the zip was built with, as in
+//! `10c0/<sha512>`; older lockfiles only have the digest.
+
+use crate::hash::to_hex;
+use sha2::{Digest, Sha512};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Errors from [`Checksum::parse`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChecksumError {
+  /// Not a hex digest of a known length, optionally after a `cacheKey/`
+  Invalid(String),
+}
+
+impl fmt::Display for ChecksumError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Invalid(checksum) => write!(f, "invalid checksum `{checksum}`"),
+    }
+  }
+}
+
+impl std::error::Error for ChecksumError {}
+
+/// The hash function behind a [`Checksum`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgorithm {
+  Sha512,
+}
+
+impl ChecksumAlgorithm {
+  /// Length of a digest in hex characters
+  fn hex_len(self) -> usize {
+    match self {
+      Self::Sha512 => 128,
+    }
+  }
+
+  /// The hex digest of everything `reader` yields
+  pub fn digest_reader(self, mut reader: impl io::Read) -> io::Result<String> {
+    match self {
+      Self::Sha512 => {
+        let mut hasher = Sha512::new();
+        io::copy(&mut reader, &mut hasher)?;
+        Ok(to_hex(&hasher.finalize()))
+      }
+    }
+  }
+
+  /// The hex digest of a file, as yarn's `hashUtils.checksumFile` computes it
+  pub fn digest_file(self, path: impl AsRef<Path>) -> io::Result<String> {
+    self.digest_reader(io::BufReader::new(std::fs::File::open(path)?))
+  }
+}
+
+/// A parsed `checksum`, like `10c0/<sha512>` or a bare `<sha512>`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checksum {
+  /// The cache key the archive was built with, e.g. `10c0`
+  pub cache_key: Option<String>,
+  pub algorithm: ChecksumAlgorithm,
+  /// The lowercase hex digest
+  pub digest: String,
+}
+
+impl Checksum {
+  pub fn parse(input: &str) -> Result<Self, ChecksumError> {
+    let (cache_key, digest) = input
+      .split_once('/')
+      .map_or((None, input), |(key, digest)| (Some(key), digest));
+
+    let algorithm = ChecksumAlgorithm::Sha512;
+    let is_valid = cache_key.is_none_or(|key| !key.is_empty())
+      && digest.len() == algorithm.hex_len()
+      && digest.bytes().all(|byte| byte.is_ascii_hexdigit());
+    if !is_valid {
+      return Err(ChecksumError::Invalid(input.to_string()));
+    }
+
+    Ok(Self {
+      cache_key: cache_key.map(str::to_string),
+      algorithm,
+      digest: digest.to_ascii_lowercase(),
+    })
+  }
+
+  /// Whether yarn would trust this checksum for a cache built with `cache_key`
+  ///
+  /// Checksums without a cache key predate them and are always compatible.
+  pub fn is_compatible_with(&self, cache_key: &str) -> bool {
+    self.cache_key.as_deref().is_none_or(|key| key == cache_key)
+  }
+
+  /// Whether a hex digest, as returned by [`ChecksumAlgorithm::digest_file`], matches
+  pub fn matches_digest(&self, digest: &str) -> bool {
+    self.digest.eq_ignore_ascii_case(digest)
+  }
+}
+
+impl FromStr for Checksum {
+  type Err = ChecksumError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::parse(s)
+  }
+}
+
+impl fmt::Display for Checksum {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if let Some(cache_key) = &self.cache_key {
+      write!(f, "{cache_key}/")?;
+    }
+    f.write_str(&self.digest)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DIGEST: &str = "7244b45d8e65f6b4338a6a68a8556f2cb161b782343e97281a5f2b9b93e420cad0d9f5773a59d79f61d0c448913d06f6a2358a87f2e203cf112e3c5b53522ee6";
+
+  #[test]
+  fn test_parse_checksum() {
+    let prefixed = Checksum::parse(&format!("10c0/{DIGEST}")).unwrap();
+    assert_eq!(prefixed.cache_key.as_deref(), Some("10c0"));
+    assert_eq!(prefixed.algorithm, ChecksumAlgorithm::Sha512);
+    assert_eq!(prefixed.digest, DIGEST);
+    assert_eq!(prefixed.to_string(), format!("10c0/{DIGEST}"));
+    assert!(prefixed.is_compatible_with("10c0"));
+    assert!(!prefixed.is_compatible_with("9"));
+
+    let bare: Checksum = DIGEST.parse().unwrap();
+    assert_eq!(bare.cache_key, None);
+    assert!(bare.is_compatible_with("10c0"));
+    assert_eq!(bare.to_string(), DIGEST);
+
+    for invalid in [
+      "",
+      "10c0/",
+      "abc",
+      &format!("/{DIGEST}"),
+      &format!("{DIGEST}0"),
+    ] {
+      assert_eq!(
+        Checksum::parse(invalid),
+        Err(ChecksumError::Invalid(invalid.to_string()))
+      );
+    }
+  }
+
+  #[test]
+  fn test_digest_reader() {
+    let digest = ChecksumAlgorithm::Sha512
+      .digest_reader(&b"abc"[..])
+      .unwrap();
+    assert_eq!(
+      digest,
+      "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+    );
+  }
+}
@@ -0,0 +1,454 @@
+//! Yarn classic (v1) lockfiles
+//!
+//! Yarn 1 writes a different format from berry: a `# yarn lockfile v1`
+//! header instead of `__metadata`, `key value` pairs instead of `key: value`,
+//! and `resolved`/`integrity` instead of `resolution`/`checksum`:
+//!
+//! ```text
+//! # THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+//! # yarn lockfile v1
+//!
+//!
+//! debug@^4.3.1:
+//!   version "4.3.4"
+//!   resolved "https://registry.yarnpkg.com/debug/-/debug-4.3.4.tgz#1319f6579357f2338d3337d2cdd4914bb5dcc865"
+//!   integrity sha512-PRWFHuSU3eDtQJPvnNY7Jcket1j0t5OuOsFzPPzsekD52Zl8qUfFIPEiswXqIvHWGVHOgX+7G/vCNNhehwxfkQ==
+//!   dependencies:
+//!     ms "2.1.2"
+//! ```
+//!
+//! [`parse_classic`] reads it into a [`ClassicLockfile`] that mirrors the
+//! file, and [`ClassicLockfile::into_lockfile`] converts that to the berry
+//! [`Lockfile`] model so the graph, `why` and diff tooling work on it too.
+
+use crate::error::{ParseError, ParseErrorKind};
+use crate::git::is_git_url;
+use crate::ident::{Descriptor, Ident, split_name_and_range};
+use crate::intern::Interner;
+use crate::locator::Locator;
+use crate::lockfile::{Entry, Lockfile, Metadata};
+use crate::metadata::DependencyMeta;
+use crate::package::{ExtraProperty, LanguageName, LinkType, Package};
+
+/// The header line that marks a yarn classic lockfile
+pub const CLASSIC_HEADER: &str = "# yarn lockfile v1";
+
+/// The `__metadata` version given to converted classic lockfiles
+pub const CLASSIC_METADATA_VERSION: &str = "1";
+
+/// One entry of a classic lockfile, as written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassicEntry {
+  /// The descriptors before the colon, e.g. `debug@^4.3.1`
+  pub descriptors: Vec<String>,
+  pub version: Option<String>,
+  /// The tarball or repository the package was fetched from
+  pub resolved: Option<String>,
+  /// Subresource integrity of the tarball, e.g. `sha512-...`
+  pub integrity: Option<String>,
+  pub uid: Option<String>,
+  /// `name range` pairs, in file order
+  pub dependencies: Vec<(String, String)>,
+  pub optional_dependencies: Vec<(String, String)>,
+}
+
+/// A parsed yarn classic lockfile
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassicLockfile {
+  pub entries: Vec<ClassicEntry>,
+}
+
+/// Whether `contents` starts like a yarn classic lockfile, looking past the
+/// leading comment lines
+pub fn is_classic(contents: &str) -> bool {
+  contents
+    .lines()
+    .take_while(|line| line.starts_with('#'))
+    .any(|line| line.trim_end() == CLASSIC_HEADER)
+}
+
+/// Parse a yarn classic lockfile
+pub fn parse_classic(contents: &str) -> Result<ClassicLockfile, ParseError> {
+  if !is_classic(contents) {
+    return Err(ParseError::new(
+      ParseErrorKind::InvalidHeader,
+      contents,
+      0,
+      None,
+    ));
+  }
+
+  let mut lockfile = ClassicLockfile::default();
+  // The block (`dependencies` or `optionalDependencies`) the current lines belong to
+  let mut block: Option<String> = None;
+  let mut offset = 0;
+  for line in contents.split_inclusive('\n') {
+    let line_offset = offset;
+    offset += line.len();
+    let line = line.trim_end_matches(['\n', '\r']);
+    let text = line.trim_start();
+    if text.is_empty() || text.starts_with('#') {
+      continue;
+    }
+
+    let indent = line.len() - text.len();
+    let error = |kind, lockfile: &ClassicLockfile| {
+      let entry = lockfile
+        .entries
+        .last()
+        .map(|entry| entry.descriptors.join(", "));
+      ParseError::new(kind, contents, line_offset + indent, entry)
+    };
+    let unexpected = |lockfile: &ClassicLockfile| {
+      error(
+        ParseErrorKind::UnexpectedLine {
+          line: text.to_string(),
+        },
+        lockfile,
+      )
+    };
+
+    match indent {
+      0 => {
+        let Some(descriptors) = text.strip_suffix(':').and_then(split_descriptors) else {
+          return Err(error(
+            ParseErrorKind::InvalidDescriptor {
+              line: text.to_string(),
+            },
+            &lockfile,
+          ));
+        };
+        lockfile.entries.push(ClassicEntry {
+          descriptors,
+          ..ClassicEntry::default()
+        });
+        block = None;
+      }
+      2 => {
+        let Some(entry) = lockfile.entries.last_mut() else {
+          return Err(unexpected(&lockfile));
+        };
+        if let Some(key) = text.strip_suffix(':') {
+          block = Some(unquote(key));
+          continue;
+        }
+        block = None;
+        let Some((key, value)) = split_pair(text) else {
+          return Err(unexpected(&lockfile));
+        };
+        let field = match key.as_str() {
+          "version" => &mut entry.version,
+          "resolved" => &mut entry.resolved,
+          "integrity" => &mut entry.integrity,
+          "uid" => &mut entry.uid,
+          _ => return Err(error(ParseErrorKind::UnknownProperty { key }, &lockfile)),
+        };
+        *field = Some(value);
+      }
+      4 => {
+        let (Some(entry), Some(block), Some(pair)) = (
+          lockfile.entries.last_mut(),
+          block.as_deref(),
+          split_pair(text),
+        ) else {
+          return Err(unexpected(&lockfile));
+        };
+        match block {
+          "dependencies" => entry.dependencies.push(pair),
+          "optionalDependencies" => entry.optional_dependencies.push(pair),
+          _ => {
+            let key = block.to_string();
+            return Err(error(ParseErrorKind::UnknownProperty { key }, &lockfile));
+          }
+        }
+      }
+      _ => return Err(unexpected(&lockfile)),
+    }
+  }
+
+  Ok(lockfile)
+}
+
+/// Split a descriptor line like `"@babel/core@^7.0.0", "@babel/core@^7.1.0"`
+fn split_descriptors(line: &str) -> Option<Vec<String>> {
+  let descriptors: Vec<String> = line.split(", ").map(unquote).collect();
+  descriptors
+    .iter()
+    .all(|descriptor| split_name_and_range(descriptor).is_some())
+    .then_some(descriptors)
+}
+
+/// Split a `key value` line, either side of which may be quoted
+fn split_pair(text: &str) -> Option<(String, String)> {
+  let split = text.strip_prefix('"').map_or_else(
+    || text.find(' '),
+    |quoted| quoted.find('"').map(|end| end + 2),
+  )?;
+  let (key, value) = text.split_at(split);
+  let value = value.trim_start();
+  (!value.is_empty()).then(|| (unquote(key), unquote(value)))
+}
+
+/// Remove the JSON-style quotes yarn puts around some keys and values
+fn unquote(text: &str) -> String {
+  text
+    .strip_prefix('"')
+    .and_then(|text| text.strip_suffix('"'))
+    .map_or_else(
+      || text.to_string(),
+      |text| text.replace("\\\"", "\"").replace("\\\\", "\\"),
+    )
+}
+
+/// The berry form of a classic range: registry ranges gain yarn's default
+/// `npm:` protocol, and anything with a protocol, git URL or path is kept
+fn normalize_range(range: &str) -> String {
+  let is_path = ["./", "../", "/", "~/"]
+    .iter()
+    .any(|prefix| range.starts_with(prefix));
+  if range.contains(':') || is_path || is_git_url(range) {
+    range.to_string()
+  } else {
+    format!("npm:{range}")
+  }
+}
+
+impl ClassicLockfile {
+  /// Convert to the berry model
+  ///
+  /// Registry packages get `npm:` descriptors and a `name@npm:version`
+  /// resolution, as `yarn install` writes them when migrating; any other
+  /// package resolves to its first descriptor's range. The `__metadata`
+  /// version is [`CLASSIC_METADATA_VERSION`] with an empty cache key.
+  /// `optionalDependencies` become dependencies marked optional in
+  /// `dependenciesMeta`. There is no berry checksum for a classic entry, so
+  /// `resolved` and `integrity` are kept in [`Package::extra`].
+  ///
+  /// Yarn 1 doesn't lock workspaces, so the result has no workspace entries
+  /// for [`crate::why::why`] to start from.
+  pub fn into_lockfile(self) -> Lockfile {
+    let mut interner = Interner::new();
+    let node = LanguageName::new(interner.intern("node"));
+    let mut entries = Vec::with_capacity(self.entries.len());
+    for classic in self.entries {
+      let descriptors: Vec<Descriptor> = classic
+        .descriptors
+        .iter()
+        .filter_map(|raw| split_name_and_range(raw))
+        .map(|(name, range)| descriptor(&mut interner, name, range))
+        .collect();
+
+      let Some(first) = descriptors.first() else {
+        continue;
+      };
+      let link_type = if first.range().starts_with("link:") || first.range().starts_with("portal:")
+      {
+        LinkType::Soft
+      } else {
+        LinkType::Hard
+      };
+      let mut package = Package::new(node, link_type);
+      package.version.clone_from(&classic.version);
+      let locator = resolution_locator(&descriptors, classic.version.as_deref(), &mut interner);
+      package.resolution = Some(locator.display(&interner).to_string());
+      package.resolution_locator = Some(locator);
+
+      for (name, range) in &classic.dependencies {
+        let dependency = descriptor(&mut interner, name, range);
+        package.dependencies.insert(*dependency.ident(), dependency);
+      }
+      for (name, range) in &classic.optional_dependencies {
+        let dependency = descriptor(&mut interner, name, range);
+        let meta = DependencyMeta {
+          built: None,
+          optional: Some(true),
+          unplugged: None,
+        };
+        package
+          .dependencies_meta
+          .insert(*dependency.ident(), Some(meta));
+        package.dependencies.insert(*dependency.ident(), dependency);
+      }
+      for (key, value) in [
+        ("resolved", classic.resolved),
+        ("integrity", classic.integrity),
+      ] {
+        if let Some(value) = value {
+          package
+            .extra
+            .insert(key.to_string(), ExtraProperty::Scalar(value));
+        }
+      }
+
+      entries.push(Entry::new(descriptors, package));
+    }
+
+    Lockfile {
+      metadata: Metadata::new(CLASSIC_METADATA_VERSION.to_string(), String::new()),
+      entries,
+      resolutions: None,
+      constraints: None,
+      interner,
+    }
+  }
+}
+
+/// `name@npm:version` for registry packages, following an alias like
+/// `npm:string-width@^4.2.0` to the package it names
+fn resolution_locator(
+  descriptors: &[Descriptor],
+  version: Option<&str>,
+  interner: &mut Interner,
+) -> Locator {
+  let first = &descriptors[0];
+  let (Some(selector), Some(version)) = (first.range().strip_prefix("npm:"), version) else {
+    return Locator::new(*first.ident(), first.range().to_string());
+  };
+  let ident: Ident = split_name_and_range(selector)
+    .map_or_else(|| *first.ident(), |(name, _)| interner.intern_ident(name));
+  Locator::new(ident, format!("npm:{version}"))
+}
+
+fn descriptor(interner: &mut Interner, name: &str, range: &str) -> Descriptor {
+  Descriptor::new(interner.intern_ident(name), normalize_range(range))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::graph::DependencyGraph;
+
+  const LOCKFILE: &str = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@types/node@*", "@types/node@^20.0.0":
+  version "20.12.7"
+  resolved "https://registry.yarnpkg.com/@types/node/-/node-20.12.7.tgz#04080362fa3dd6c5822061aa3124f5c152cff384"
+  integrity sha512-wq0cICSkRLVaf3UGLMGItu/PtdY7oaXaI/RVU+xliKVOtRna3PRY57ZDfztpDL0n11vfymMUnXv8QwYCO7L1wg==
+  dependencies:
+    undici-types "~5.26.4"
+
+debug@^4.3.1:
+  version "4.3.4"
+  resolved "https://registry.yarnpkg.com/debug/-/debug-4.3.4.tgz#1319f6579357f2338d3337d2cdd4914bb5dcc865"
+  integrity sha512-PRWFHuSU3eDtQJPvnNY7Jcket1j0t5OuOsFzPPzsekD52Zl8qUfFIPEiswXqIvHWGVHOgX+7G/vCNNhehwxfkQ==
+  dependencies:
+    ms "2.1.2"
+  optionalDependencies:
+    "@types/node" "*"
+
+ms@2.1.2:
+  version "2.1.2"
+  resolved "https://registry.yarnpkg.com/ms/-/ms-2.1.2.tgz#d09d1f357b443f493382a8eb3ccd183872ae6009"
+  integrity sha512-sGkPx+VjMtmA6MX27oA4FBFELFCZZ4S4XqeGOXCv68tT+jb3vk/RyaKWP0PTKyWtmLSM0b+adUTEvbs1PEaH2w==
+
+"string-width-cjs@npm:string-width@^4.2.0":
+  version "4.2.3"
+  resolved "https://registry.yarnpkg.com/string-width/-/string-width-4.2.3.tgz#269c7117d27b05ad2e536830a8ec895ef9c6d010"
+  integrity sha512-wKyQRQpjJ0sIp62ErSZdGsjMJWsap5oRNihHhu6G7JVO/9jIB6UyevL+tXuOqrng8j/cxKTWyWUwvSTriiZz/g==
+
+undici-types@~5.26.4:
+  version "5.26.5"
+  resolved "https://registry.yarnpkg.com/undici-types/-/undici-types-5.26.5.tgz#bcd539893d00b56e964fd2657a4866b221a65617"
+  integrity sha512-JlCMO+ehdEIKqlFxk6IfVoAUVmgz7cU7zD/h9XZ0qzeosSHmUJVOzSQvvYSYWXkFXC+IfLKSIffhv0sVZup6pA==
+"#;
+
+  #[test]
+  fn test_parse_classic() {
+    assert!(is_classic(LOCKFILE));
+    let classic = parse_classic(LOCKFILE).expect("should parse");
+    assert_eq!(classic.entries.len(), 5);
+
+    let types_node = &classic.entries[0];
+    assert_eq!(
+      types_node.descriptors,
+      ["@types/node@*", "@types/node@^20.0.0"]
+    );
+    assert_eq!(types_node.version.as_deref(), Some("20.12.7"));
+    assert!(
+      types_node
+        .integrity
+        .as_deref()
+        .unwrap()
+        .starts_with("sha512-")
+    );
+    assert_eq!(
+      types_node.dependencies,
+      [("undici-types".to_string(), "~5.26.4".to_string())]
+    );
+
+    let debug = &classic.entries[1];
+    assert_eq!(
+      debug.optional_dependencies,
+      [("@types/node".to_string(), "*".to_string())]
+    );
+  }
+
+  #[test]
+  fn test_into_lockfile() {
+    let lockfile = parse_classic(LOCKFILE).unwrap().into_lockfile();
+    let interner = &lockfile.interner;
+    assert_eq!(lockfile.metadata.version, CLASSIC_METADATA_VERSION);
+
+    let resolutions: Vec<_> = lockfile
+      .entries
+      .iter()
+      .map(|entry| entry.package.resolution.as_deref().unwrap())
+      .collect();
+    assert_eq!(
+      resolutions,
+      [
+        "@types/node@npm:20.12.7",
+        "debug@npm:4.3.4",
+        "ms@npm:2.1.2",
+        "string-width@npm:4.2.3",
+        "undici-types@npm:5.26.5",
+      ]
+    );
+    let descriptors: Vec<_> = lockfile.entries[3]
+      .descriptors
+      .iter()
+      .map(|descriptor| descriptor.display(interner).to_string())
+      .collect();
+    assert_eq!(descriptors, ["string-width-cjs@npm:string-width@^4.2.0"]);
+
+    let debug = &lockfile.entries[1].package;
+    let types_node = interner.get_ident("@types/node").unwrap();
+    assert_eq!(debug.dependencies[&types_node].range(), "npm:*");
+    assert_eq!(
+      debug.dependencies_meta[&types_node]
+        .as_ref()
+        .and_then(|meta| meta.optional),
+      Some(true)
+    );
+    assert!(matches!(
+      debug.extra.get("integrity"),
+      Some(ExtraProperty::Scalar(integrity)) if integrity.starts_with("sha512-")
+    ));
+
+    // Every dependency resolves in the converted lockfile
+    let graph = DependencyGraph::new(&lockfile).expect("every dependency should resolve");
+    assert_eq!(graph.dependencies(1).len(), 2);
+  }
+
+  #[test]
+  fn test_parse_classic_errors() {
+    let berry = "# This file is generated by running \"yarn install\" inside your project.\n";
+    assert_eq!(
+      parse_classic(berry).unwrap_err().kind,
+      ParseErrorKind::InvalidHeader
+    );
+
+    let unknown = format!("{CLASSIC_HEADER}\n\nms@2.1.2:\n  version \"2.1.2\"\n  bogus \"x\"\n");
+    let error = parse_classic(&unknown).unwrap_err();
+    assert_eq!(
+      error.kind,
+      ParseErrorKind::UnknownProperty {
+        key: "bogus".to_string()
+      }
+    );
+    assert_eq!((error.line, error.column), (5, 3));
+    assert_eq!(error.entry.as_deref(), Some("ms@2.1.2"));
+  }
+}
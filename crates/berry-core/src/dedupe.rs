@@ -0,0 +1,350 @@
+//! Duplicate package versions and how to collapse them
+//!
+//! Over time a lockfile collects several versions of the same package, often
+//! because each range was resolved when a different version was the latest.
+//! [`find_duplicates`] groups the npm entries of each package, and
+//! [`plan_dedupe`] moves every descriptor onto the highest existing version
+//! that satisfies it, like `yarn dedupe --strategy highest`. Nothing is
+//! resolved against the registry, so a range only moves to a version that is
+//! already locked.
+//!
+//! Like [`crate::diff::LockfileDiff`], the duplicates and the plan use
+//! descriptor and resolution strings, and serialize to JSON with the `serde`
+//! feature.
+
+use crate::graph::DependencyGraph;
+use crate::ident::Ident;
+use crate::lockfile::Lockfile;
+use crate::reference::ReferenceKind;
+use crate::semver::Version;
+use std::collections::HashMap;
+
+/// One locked version of a package and the descriptors resolving to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResolvedVersion {
+  /// Position of the entry in [`Lockfile::entries`]
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub position: usize,
+  /// `None` if the entry's `version` isn't valid semver
+  pub version: Option<Version>,
+  /// The entry's resolution, e.g. `lodash@npm:4.17.21`
+  pub resolution: String,
+  /// e.g. `lodash@npm:^4.17.0`
+  pub descriptors: Vec<String>,
+}
+
+/// A package locked at more than one version
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Duplicate {
+  /// The package name, e.g. `@babel/core`
+  pub name: String,
+  /// Ordered from the lowest version to the highest
+  pub versions: Vec<ResolvedVersion>,
+}
+
+/// Moving one descriptor from the entry it resolves to onto another
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DedupeMove {
+  pub descriptor: String,
+  /// The resolution the descriptor has now
+  pub from: String,
+  /// The resolution it should have
+  pub to: String,
+  /// Position of the entry the descriptor resolves to now
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub from_position: usize,
+  /// Position of the entry it should resolve to
+  #[cfg_attr(feature = "serde", serde(skip))]
+  pub to_position: usize,
+}
+
+/// The descriptors [`plan_dedupe`] would move, see [`DedupePlan::apply`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DedupePlan {
+  pub moves: Vec<DedupeMove>,
+}
+
+/// Every package with more than one npm entry, ordered by name
+///
+/// Only entries resolved from the registry count, so a package and its
+/// `patch:` of the same version aren't reported as duplicates.
+pub fn find_duplicates(lockfile: &Lockfile) -> Vec<Duplicate> {
+  let interner = &lockfile.interner;
+  let mut by_ident: HashMap<Ident, Vec<ResolvedVersion>> = HashMap::new();
+  for (position, entry) in lockfile.entries.iter().enumerate() {
+    let Some(locator) = &entry.package.resolution_locator else {
+      continue;
+    };
+    if !matches!(locator.reference_kind(), ReferenceKind::Npm { .. }) {
+      continue;
+    }
+    let version = entry
+      .package
+      .version
+      .as_deref()
+      .and_then(|version| Version::parse(version).ok());
+    by_ident
+      .entry(*locator.ident())
+      .or_default()
+      .push(ResolvedVersion {
+        position,
+        version,
+        resolution: locator.display(interner).to_string(),
+        descriptors: entry
+          .descriptors
+          .iter()
+          .map(|descriptor| descriptor.display(interner).to_string())
+          .collect(),
+      });
+  }
+
+  let mut duplicates: Vec<_> = by_ident
+    .into_iter()
+    .filter(|(_, versions)| versions.len() > 1)
+    .map(|(ident, mut versions)| {
+      // Entries without a valid version sort first, as `None < Some(_)`
+      versions.sort_by(|a, b| a.version.cmp(&b.version));
+      Duplicate {
+        name: ident.display(interner).to_string(),
+        versions,
+      }
+    })
+    .collect();
+  duplicates.sort_by(|a, b| a.name.cmp(&b.name));
+  duplicates
+}
+
+/// Move each descriptor of a duplicated package onto the highest locked
+/// version its range allows
+///
+/// Descriptors whose range isn't semver, like `npm:latest`, stay where they are.
+pub fn plan_dedupe(lockfile: &Lockfile) -> DedupePlan {
+  let mut plan = DedupePlan::default();
+  for duplicate in find_duplicates(lockfile) {
+    for current in &duplicate.versions {
+      for descriptor in &lockfile.entries[current.position].descriptors {
+        let highest = duplicate.versions.iter().rev().find(|candidate| {
+          candidate
+            .version
+            .as_ref()
+            .is_some_and(|version| descriptor.matches(version) == Some(true))
+        });
+        if let Some(highest) = highest
+          && highest.position != current.position
+        {
+          plan.moves.push(DedupeMove {
+            descriptor: descriptor.display(&lockfile.interner).to_string(),
+            from: current.resolution.clone(),
+            to: highest.resolution.clone(),
+            from_position: current.position,
+            to_position: highest.position,
+          });
+        }
+      }
+    }
+  }
+  plan
+}
+
+impl DedupePlan {
+  pub fn is_empty(&self) -> bool {
+    self.moves.is_empty()
+  }
+
+  /// Rewrite the lockfile the plan was made for
+  ///
+  /// Entries left without descriptors are removed, along with any
+  /// dependencies only they needed. Entries nothing depended on to begin
+  /// with, such as workspaces or targets of `resolutions`, are always kept.
+  /// Entries are re-sorted by their keys, as yarn writes them.
+  pub fn apply(&self, mut lockfile: Lockfile) -> Lockfile {
+    if self.is_empty() {
+      return lockfile;
+    }
+
+    let before = DependencyGraph::new_lenient(&lockfile);
+    for dedupe_move in &self.moves {
+      let interner = &lockfile.interner;
+      let from = &mut lockfile.entries[dedupe_move.from_position].descriptors;
+      let Some(index) = from
+        .iter()
+        .position(|descriptor| descriptor.display(interner).to_string() == dedupe_move.descriptor)
+      else {
+        continue;
+      };
+      let descriptor = from.remove(index);
+      lockfile.entries[dedupe_move.to_position]
+        .descriptors
+        .push(descriptor);
+    }
+    let after = DependencyGraph::new_lenient(&lockfile);
+
+    let mut live = vec![false; lockfile.entries.len()];
+    let mut stack: Vec<usize> = (0..lockfile.entries.len())
+      .filter(|&position| {
+        !lockfile.entries[position].descriptors.is_empty() && before.dependents(position).is_empty()
+      })
+      .collect();
+    while let Some(position) = stack.pop() {
+      if !live[position] {
+        live[position] = true;
+        stack.extend(after.dependencies(position).iter().map(|edge| edge.to));
+      }
+    }
+
+    let interner = &lockfile.interner;
+    let mut entries: Vec<_> = std::mem::take(&mut lockfile.entries)
+      .into_iter()
+      .zip(live)
+      .filter_map(|(entry, live)| live.then_some(entry))
+      .collect();
+    for entry in &mut entries {
+      entry
+        .descriptors
+        .sort_by_cached_key(|descriptor| descriptor.display(interner).to_string());
+    }
+    entries.sort_by_cached_key(|entry| {
+      entry
+        .descriptors
+        .iter()
+        .map(|descriptor| descriptor.display(interner).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+    });
+    lockfile.entries = entries;
+    lockfile
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parse;
+
+  const LOCKFILE: &str = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"dep-old@npm:^1.0.0":
+  version: 1.0.0
+  resolution: "dep-old@npm:1.0.0"
+  languageName: node
+  linkType: hard
+
+"lodash3@npm:lodash@^3.0.0":
+  version: 3.10.1
+  resolution: "lodash@npm:3.10.1"
+  languageName: node
+  linkType: hard
+
+"lodash@npm:^4.0.0":
+  version: 4.17.20
+  resolution: "lodash@npm:4.17.20"
+  dependencies:
+    dep-old: "npm:^1.0.0"
+  languageName: node
+  linkType: hard
+
+"lodash@npm:^4.17.0":
+  version: 4.17.21
+  resolution: "lodash@npm:4.17.21"
+  languageName: node
+  linkType: hard
+
+"pkg-a@npm:1.0.0":
+  version: 1.0.0
+  resolution: "pkg-a@npm:1.0.0"
+  dependencies:
+    lodash: "npm:^4.17.0"
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  dependencies:
+    lodash: "npm:^4.0.0"
+    lodash3: "npm:lodash@^3.0.0"
+    pkg-a: "npm:1.0.0"
+  languageName: unknown
+  linkType: soft
+"#;
+
+  #[test]
+  fn test_find_duplicates() {
+    let lockfile = parse(LOCKFILE).expect("should parse");
+    let duplicates = find_duplicates(&lockfile);
+    assert_eq!(duplicates.len(), 1);
+
+    let lodash = &duplicates[0];
+    assert_eq!(lodash.name, "lodash");
+    let versions: Vec<_> = lodash
+      .versions
+      .iter()
+      .map(|resolved| {
+        (
+          resolved.version.as_ref().unwrap().to_string(),
+          resolved.resolution.as_str(),
+          resolved.descriptors.clone(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      versions,
+      [
+        (
+          "3.10.1".to_string(),
+          "lodash@npm:3.10.1",
+          vec!["lodash3@npm:lodash@^3.0.0".to_string()]
+        ),
+        (
+          "4.17.20".to_string(),
+          "lodash@npm:4.17.20",
+          vec!["lodash@npm:^4.0.0".to_string()]
+        ),
+        (
+          "4.17.21".to_string(),
+          "lodash@npm:4.17.21",
+          vec!["lodash@npm:^4.17.0".to_string()]
+        ),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_plan_and_apply_dedupe() {
+    let lockfile = parse(LOCKFILE).expect("should parse");
+    let plan = plan_dedupe(&lockfile);
+    // `^3.0.0` has nowhere else to go
+    assert_eq!(plan.moves.len(), 1);
+    assert_eq!(
+      plan.moves,
+      [DedupeMove {
+        descriptor: "lodash@npm:^4.0.0".to_string(),
+        from: "lodash@npm:4.17.20".to_string(),
+        to: "lodash@npm:4.17.21".to_string(),
+        from_position: 2,
+        to_position: 3,
+      }]
+    );
+
+    // lodash 4.17.20 goes, and `dep-old` with it
+    let deduped = plan.apply(lockfile);
+    let text = deduped.to_string();
+    assert!(text.contains("\"lodash@npm:^4.0.0, lodash@npm:^4.17.0\":\n  version: 4.17.21\n"));
+    assert!(!text.contains("4.17.20"));
+    assert!(!text.contains("dep-old"));
+    assert_eq!(deduped.entries.len(), 4);
+
+    let reparsed = parse(&text).expect("should parse the rewritten lockfile");
+    assert!(plan_dedupe(&reparsed).is_empty());
+    assert!(DependencyGraph::new(&reparsed).is_ok());
+  }
+}
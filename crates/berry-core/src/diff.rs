@@ -0,0 +1,651 @@
+//! Structural differences between two lockfiles
+//!
+//! A textual diff of a yarn.lock repeats every field of every entry that moved,
+//! so [`diff`] summarises what actually changed: packages added, removed or
+//! moved to another version, checksums and dependencies that changed for the
+//! same resolution, descriptors that appeared or disappeared, and the
+//! `__metadata` block. The two lockfiles each have their own [`Interner`], so
+//! everything is compared and reported as strings.
+//!
+//! [`LockfileDiff`] renders as a human summary with `Display`, and serializes
+//! to JSON with the `serde` feature.
+//!
+//! [`Interner`]: crate::intern::Interner
+
+use crate::ident::Descriptor;
+use crate::locator::Locator;
+use crate::lockfile::Lockfile;
+use crate::package::Package;
+use crate::semver::Version;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A value that differs between the old and new lockfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldChange<T> {
+  pub from: T,
+  pub to: T,
+}
+
+/// A package whose resolution moved, e.g. from `lodash@npm:4.17.20` to
+/// `lodash@npm:4.17.21`
+///
+/// Downgrades are reported the same way, with a lower `to` version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Upgrade {
+  pub name: String,
+  pub version: FieldChange<Option<String>>,
+  pub resolution: FieldChange<String>,
+}
+
+/// A different `checksum` for the same resolution
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChecksumChange {
+  pub resolution: String,
+  pub checksum: FieldChange<Option<String>>,
+}
+
+/// One dependency added, removed or given a new range; `None` on either side
+/// means it wasn't declared
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DependencyChange {
+  pub name: String,
+  pub range: FieldChange<Option<String>>,
+}
+
+/// The changed `dependencies` of one resolution present in both lockfiles
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DependenciesChange {
+  pub resolution: String,
+  pub changes: Vec<DependencyChange>,
+}
+
+/// Everything that changed from one lockfile to another, see [`diff`]
+///
+/// Lists are sorted, so the same two lockfiles always give the same output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LockfileDiff {
+  /// The `__metadata` version
+  pub version: Option<FieldChange<String>>,
+  pub cache_key: Option<FieldChange<String>>,
+  /// Resolutions of packages that weren't in the old lockfile at any version
+  pub added: Vec<String>,
+  /// Resolutions of packages that aren't in the new lockfile at any version
+  pub removed: Vec<String>,
+  pub upgraded: Vec<Upgrade>,
+  pub checksums: Vec<ChecksumChange>,
+  pub added_descriptors: Vec<String>,
+  pub removed_descriptors: Vec<String>,
+  pub dependencies: Vec<DependenciesChange>,
+}
+
+impl LockfileDiff {
+  pub fn is_empty(&self) -> bool {
+    *self == Self::default()
+  }
+}
+
+/// Compare two lockfiles, typically before and after a change
+pub fn diff(old: &Lockfile, new: &Lockfile) -> LockfileDiff {
+  let old_packages = packages_by_resolution(old);
+  let new_packages = packages_by_resolution(new);
+
+  let mut result = LockfileDiff {
+    version: field_change(&old.metadata.version, &new.metadata.version),
+    cache_key: field_change(&old.metadata.cache_key, &new.metadata.cache_key),
+    ..LockfileDiff::default()
+  };
+
+  // Packages only on one side are grouped by name and protocol, so a new
+  // version of `lodash@npm:` pairs with the old one instead of with a patch
+  let mut moved: BTreeMap<(String, &str), Moved<'_>> = BTreeMap::new();
+  for (resolution, package) in &old_packages {
+    if !new_packages.contains_key(resolution) {
+      let resolved = Resolved::new(resolution, package, old);
+      let group = (resolved.name.clone(), resolved.protocol);
+      moved.entry(group).or_default().removed.push(resolved);
+    }
+  }
+  for (resolution, package) in &new_packages {
+    if !old_packages.contains_key(resolution) {
+      let resolved = Resolved::new(resolution, package, new);
+      let group = (resolved.name.clone(), resolved.protocol);
+      moved.entry(group).or_default().added.push(resolved);
+    }
+  }
+  for (
+    (name, _),
+    Moved {
+      mut removed,
+      mut added,
+    },
+  ) in moved
+  {
+    removed.sort_by(|a, b| a.version.cmp(&b.version));
+    added.sort_by(|a, b| a.version.cmp(&b.version));
+    let mut removed_paired = vec![false; removed.len()];
+    let mut added_paired = vec![false; added.len()];
+    for (from, to) in pair_versions(&removed, &added) {
+      removed_paired[from] = true;
+      added_paired[to] = true;
+      let (from, to) = (&removed[from], &added[to]);
+      result.upgraded.push(Upgrade {
+        name: name.clone(),
+        version: FieldChange {
+          from: from.package.version.clone(),
+          to: to.package.version.clone(),
+        },
+        resolution: FieldChange {
+          from: from.resolution.to_string(),
+          to: to.resolution.to_string(),
+        },
+      });
+    }
+    let leftover = |resolved: &[Resolved<'_>], paired: &[bool]| {
+      resolved
+        .iter()
+        .zip(paired)
+        .filter(|(_, paired)| !**paired)
+        .map(|(resolved, _)| resolved.resolution.to_string())
+        .collect::<Vec<_>>()
+    };
+    result.removed.extend(leftover(&removed, &removed_paired));
+    result.added.extend(leftover(&added, &added_paired));
+  }
+  result.added.sort();
+  result.removed.sort();
+
+  for (resolution, old_package) in &old_packages {
+    let Some(new_package) = new_packages.get(resolution) else {
+      continue;
+    };
+    if old_package.checksum != new_package.checksum {
+      result.checksums.push(ChecksumChange {
+        resolution: (*resolution).to_string(),
+        checksum: FieldChange {
+          from: old_package.checksum.clone(),
+          to: new_package.checksum.clone(),
+        },
+      });
+    }
+    let changes = dependency_changes(old_package, old, new_package, new);
+    if !changes.is_empty() {
+      result.dependencies.push(DependenciesChange {
+        resolution: (*resolution).to_string(),
+        changes,
+      });
+    }
+  }
+
+  let old_descriptors = descriptors(old);
+  let new_descriptors = descriptors(new);
+  result.added_descriptors = new_descriptors
+    .difference(&old_descriptors)
+    .cloned()
+    .collect();
+  result.removed_descriptors = old_descriptors
+    .difference(&new_descriptors)
+    .cloned()
+    .collect();
+
+  result
+}
+
+/// Pair each added version of a package with the removed version it most
+/// likely replaces, as `(removed, added)` indexes ordered by the removed one
+///
+/// Both sides are sorted by version. An added version pairs with the highest
+/// removed version of the same major first, so dropping lodash 3 while
+/// upgrading lodash 4 reads as an upgrade of 4 and a removal of 3; whatever
+/// is left pairs from the highest version down.
+fn pair_versions(removed: &[Resolved<'_>], added: &[Resolved<'_>]) -> Vec<(usize, usize)> {
+  let major = |resolved: &Resolved<'_>| resolved.version.as_ref().map(|version| version.major);
+  let mut unpaired_removed: Vec<usize> = (0..removed.len()).collect();
+  let mut unpaired_added = Vec::new();
+  let mut pairs = Vec::new();
+  for to in (0..added.len()).rev() {
+    let same_major = unpaired_removed.iter().rposition(|&from| {
+      major(&removed[from]).is_some() && major(&removed[from]) == major(&added[to])
+    });
+    match same_major {
+      Some(index) => pairs.push((unpaired_removed.remove(index), to)),
+      None => unpaired_added.push(to),
+    }
+  }
+  for to in unpaired_added {
+    let Some(from) = unpaired_removed.pop() else {
+      break;
+    };
+    pairs.push((from, to));
+  }
+  pairs.sort_unstable();
+  pairs
+}
+
+/// The versions of one package that are only in the old or the new lockfile
+#[derive(Default)]
+struct Moved<'a> {
+  removed: Vec<Resolved<'a>>,
+  added: Vec<Resolved<'a>>,
+}
+
+/// A package that is only in one of the lockfiles
+struct Resolved<'a> {
+  resolution: &'a str,
+  package: &'a Package,
+  name: String,
+  /// The protocol of the resolution, e.g. `npm`
+  protocol: &'a str,
+  version: Option<Version>,
+}
+
+impl<'a> Resolved<'a> {
+  fn new(resolution: &'a str, package: &'a Package, lockfile: &Lockfile) -> Self {
+    let locator = package.resolution_locator.as_ref();
+    let name = locator.map_or_else(
+      || resolution.to_string(),
+      |locator| locator.ident().display(&lockfile.interner).to_string(),
+    );
+    // The reference is also the tail of `resolution`, which borrows for `'a`
+    let protocol = locator
+      .map(Locator::reference)
+      .filter(|reference| resolution.ends_with(reference))
+      .map(|reference| &resolution[resolution.len() - reference.len()..])
+      .and_then(|reference| reference.split_once(':'))
+      .map_or("", |(protocol, _)| protocol);
+    let version = package
+      .version
+      .as_deref()
+      .and_then(|version| Version::parse(version).ok());
+    Self {
+      resolution,
+      package,
+      name,
+      protocol,
+      version,
+    }
+  }
+}
+
+fn field_change(from: &str, to: &str) -> Option<FieldChange<String>> {
+  (from != to).then(|| FieldChange {
+    from: from.to_string(),
+    to: to.to_string(),
+  })
+}
+
+fn packages_by_resolution(lockfile: &Lockfile) -> BTreeMap<&str, &Package> {
+  lockfile
+    .entries
+    .iter()
+    .filter_map(|entry| Some((entry.package.resolution.as_deref()?, &entry.package)))
+    .collect()
+}
+
+fn descriptors(lockfile: &Lockfile) -> BTreeSet<String> {
+  lockfile
+    .entries
+    .iter()
+    .flat_map(|entry| &entry.descriptors)
+    .map(|descriptor| descriptor.display(&lockfile.interner).to_string())
+    .collect()
+}
+
+fn dependency_changes<'a>(
+  old_package: &'a Package,
+  old: &Lockfile,
+  new_package: &'a Package,
+  new: &Lockfile,
+) -> Vec<DependencyChange> {
+  // Ranges are compared with yarn's default `npm:` protocol, so `2.1.2` and
+  // `npm:2.1.2` (as written before and after lockfile v8) aren't a change
+  let ranges = |package: &'a Package, lockfile: &Lockfile| -> BTreeMap<String, &'a Descriptor> {
+    package
+      .dependencies
+      .values()
+      .map(|descriptor| {
+        (
+          descriptor.ident().display(&lockfile.interner).to_string(),
+          descriptor,
+        )
+      })
+      .collect()
+  };
+  let mut old_ranges = ranges(old_package, old);
+  let new_ranges = ranges(new_package, new);
+
+  let mut changes = Vec::new();
+  for (name, to) in new_ranges {
+    let from = old_ranges.remove(&name);
+    if from.map(Descriptor::range_with_default_protocol) != Some(to.range_with_default_protocol()) {
+      let from = from.map(|from| from.range().to_string());
+      let to = to.range().to_string();
+      changes.push(DependencyChange {
+        name,
+        range: FieldChange { from, to: Some(to) },
+      });
+    }
+  }
+  for (name, from) in old_ranges {
+    changes.push(DependencyChange {
+      name,
+      range: FieldChange {
+        from: Some(from.range().to_string()),
+        to: None,
+      },
+    });
+  }
+  changes.sort_by(|a, b| a.name.cmp(&b.name));
+  changes
+}
+
+/// A summary for people, one section per kind of change
+impl fmt::Display for LockfileDiff {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.is_empty() {
+      return writeln!(f, "No changes");
+    }
+
+    if let Some(version) = &self.version {
+      writeln!(f, "Lockfile version: {} -> {}", version.from, version.to)?;
+    }
+    if let Some(cache_key) = &self.cache_key {
+      writeln!(f, "Cache key: {} -> {}", cache_key.from, cache_key.to)?;
+    }
+    section(f, "Added", &self.added, |f, resolution| {
+      writeln!(f, "  + {resolution}")
+    })?;
+    section(f, "Removed", &self.removed, |f, resolution| {
+      writeln!(f, "  - {resolution}")
+    })?;
+    section(f, "Upgraded", &self.upgraded, |f, upgrade| {
+      match (&upgrade.version.from, &upgrade.version.to) {
+        (Some(from), Some(to)) if from != to => {
+          writeln!(f, "  {} {from} -> {to}", upgrade.name)
+        }
+        _ => writeln!(
+          f,
+          "  {} -> {}",
+          upgrade.resolution.from, upgrade.resolution.to
+        ),
+      }
+    })?;
+    section(f, "Checksums changed", &self.checksums, |f, change| {
+      writeln!(f, "  {}", change.resolution)
+    })?;
+    section(
+      f,
+      "Descriptors added",
+      &self.added_descriptors,
+      |f, descriptor| writeln!(f, "  + {descriptor}"),
+    )?;
+    section(
+      f,
+      "Descriptors removed",
+      &self.removed_descriptors,
+      |f, descriptor| writeln!(f, "  - {descriptor}"),
+    )?;
+    section(
+      f,
+      "Dependencies changed",
+      &self.dependencies,
+      |f, change| {
+        writeln!(f, "  {}", change.resolution)?;
+        for dependency in &change.changes {
+          match (&dependency.range.from, &dependency.range.to) {
+            (None, Some(to)) => writeln!(f, "    + {}: {to}", dependency.name)?,
+            (Some(from), None) => writeln!(f, "    - {}: {from}", dependency.name)?,
+            (from, to) => writeln!(
+              f,
+              "    {}: {} -> {}",
+              dependency.name,
+              from.as_deref().unwrap_or_default(),
+              to.as_deref().unwrap_or_default()
+            )?,
+          }
+        }
+        Ok(())
+      },
+    )
+  }
+}
+
+fn section<T>(
+  f: &mut fmt::Formatter<'_>,
+  title: &str,
+  items: &[T],
+  mut write_item: impl FnMut(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+  if items.is_empty() {
+    return Ok(());
+  }
+  writeln!(f, "{title} ({}):", items.len())?;
+  for item in items {
+    write_item(f, item)?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parse;
+  use std::fmt::Write;
+
+  const OLD: &str = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 6
+  cacheKey: 8
+
+"debug@npm:^4.3.1":
+  version: 4.3.1
+  resolution: "debug@npm:4.3.1"
+  dependencies:
+    ms: 2.1.2
+  checksum: aaaa
+  languageName: node
+  linkType: hard
+
+"left-pad@npm:^1.3.0":
+  version: 1.3.0
+  resolution: "left-pad@npm:1.3.0"
+  checksum: bbbb
+  languageName: node
+  linkType: hard
+
+"ms@npm:2.1.2":
+  version: 2.1.2
+  resolution: "ms@npm:2.1.2"
+  checksum: cccc
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  dependencies:
+    debug: ^4.3.1
+    left-pad: ^1.3.0
+  languageName: unknown
+  linkType: soft
+"#;
+
+  const NEW: &str = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"debug@npm:^4.3.1, debug@npm:^4.3.4":
+  version: 4.3.4
+  resolution: "debug@npm:4.3.4"
+  dependencies:
+    ms: "npm:2.1.2"
+  checksum: 10c0/dddd
+  languageName: node
+  linkType: hard
+
+"ms@npm:2.1.2":
+  version: 2.1.2
+  resolution: "ms@npm:2.1.2"
+  checksum: 10c0/cccc
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  dependencies:
+    debug: "npm:^4.3.4"
+    typescript: "npm:^5.0.0"
+  languageName: unknown
+  linkType: soft
+
+"typescript@npm:^5.0.0":
+  version: 5.4.5
+  resolution: "typescript@npm:5.4.5"
+  checksum: 10c0/eeee
+  languageName: node
+  linkType: hard
+"#;
+
+  #[test]
+  fn test_diff() {
+    let old = parse(OLD).expect("should parse");
+    let new = parse(NEW).expect("should parse");
+    let diff = diff(&old, &new);
+
+    assert_eq!(
+      diff.cache_key,
+      Some(FieldChange {
+        from: "8".to_string(),
+        to: "10c0".to_string()
+      })
+    );
+    assert_eq!(diff.added, ["typescript@npm:5.4.5"]);
+    assert_eq!(diff.removed, ["left-pad@npm:1.3.0"]);
+    assert_eq!(diff.upgraded.len(), 1);
+    assert_eq!(diff.upgraded[0].name, "debug");
+    assert_eq!(diff.upgraded[0].resolution.to, "debug@npm:4.3.4");
+    // debug's checksum changed too, but it's a new resolution
+    let checksums: Vec<_> = diff
+      .checksums
+      .iter()
+      .map(|c| c.resolution.as_str())
+      .collect();
+    assert_eq!(checksums, ["ms@npm:2.1.2"]);
+    assert_eq!(
+      diff.added_descriptors,
+      ["debug@npm:^4.3.4", "typescript@npm:^5.0.0"]
+    );
+    assert_eq!(diff.removed_descriptors, ["left-pad@npm:^1.3.0"]);
+
+    assert_eq!(diff.dependencies.len(), 1);
+    assert_eq!(diff.dependencies[0].resolution, "root@workspace:.");
+    let ranges: Vec<_> = diff.dependencies[0]
+      .changes
+      .iter()
+      .map(|change| {
+        (
+          change.name.as_str(),
+          change.range.from.as_deref(),
+          change.range.to.as_deref(),
+        )
+      })
+      .collect();
+    assert_eq!(
+      ranges,
+      [
+        ("debug", Some("^4.3.1"), Some("npm:^4.3.4")),
+        ("left-pad", Some("^1.3.0"), None),
+        ("typescript", None, Some("npm:^5.0.0")),
+      ]
+    );
+
+    let summary = diff.to_string();
+    assert!(summary.contains("Cache key: 8 -> 10c0\n"));
+    assert!(summary.contains("Upgraded (1):\n  debug 4.3.1 -> 4.3.4\n"));
+    assert!(summary.contains("    - left-pad: ^1.3.0\n"));
+  }
+
+  /// A v8 lockfile of npm entries, each a descriptor, version and the
+  /// lines of its `dependencies` block
+  fn lockfile(entries: &[(&str, &str, &str)]) -> Lockfile {
+    let header = NEW.split_once("\n\n\"").unwrap().0;
+    let mut text = format!("{header}\n");
+    for (descriptor, version, dependencies) in entries {
+      let name = descriptor.split_once("@npm:").unwrap().0;
+      let _ = write!(
+        text,
+        "\n\"{descriptor}\":\n  version: {version}\n  resolution: \"{name}@npm:{version}\"\n{dependencies}  languageName: node\n  linkType: hard\n"
+      );
+    }
+    parse(&text).expect("should parse")
+  }
+
+  #[test]
+  fn test_diff_pairs_upgrades_by_major() {
+    let old = lockfile(&[
+      ("lodash@npm:^3.0.0", "3.10.1", ""),
+      ("lodash@npm:^4.0.0", "4.17.20", ""),
+    ]);
+    let new = lockfile(&[("lodash@npm:^4.0.0", "4.17.21", "")]);
+    let changes = diff(&old, &new);
+
+    let upgraded: Vec<_> = changes
+      .upgraded
+      .iter()
+      .map(|upgrade| {
+        (
+          upgrade.resolution.from.as_str(),
+          upgrade.resolution.to.as_str(),
+        )
+      })
+      .collect();
+    assert_eq!(upgraded, [("lodash@npm:4.17.20", "lodash@npm:4.17.21")]);
+    assert_eq!(changes.removed, ["lodash@npm:3.10.1"]);
+    assert!(changes.added.is_empty());
+
+    // Without a version of the same major, the highest versions pair up
+    let new = lockfile(&[("lodash@npm:^5.0.0", "5.0.0", "")]);
+    let changes = diff(&old, &new);
+    assert_eq!(changes.upgraded[0].resolution.from, "lodash@npm:4.17.20");
+    assert_eq!(changes.removed, ["lodash@npm:3.10.1"]);
+  }
+
+  #[test]
+  fn test_diff_ignores_default_npm_protocol() {
+    let dependency = |range: &str| format!("  dependencies:\n    ms: {range}\n");
+    let old = lockfile(&[("debug@npm:^4.3.1", "4.3.4", &dependency("2.1.2"))]);
+    let new = lockfile(&[("debug@npm:^4.3.1", "4.3.4", &dependency("\"npm:2.1.2\""))]);
+    assert!(diff(&old, &new).is_empty());
+
+    let new = lockfile(&[("debug@npm:^4.3.1", "4.3.4", &dependency("\"npm:2.1.3\""))]);
+    let changes = diff(&old, &new);
+    assert_eq!(
+      changes.dependencies[0].changes[0].range,
+      FieldChange {
+        from: Some("2.1.2".to_string()),
+        to: Some("npm:2.1.3".to_string()),
+      }
+    );
+  }
+
+  #[test]
+  fn test_diff_identical() {
+    let old = parse(OLD).expect("should parse");
+    let same = parse(OLD).expect("should parse");
+    let diff = diff(&old, &same);
+    assert!(diff.is_empty());
+    assert_eq!(diff.to_string(), "No changes\n");
+  }
+}
@@ -0,0 +1,184 @@
+use std::fmt::{self, Write};
+use std::ops::Range;
+
+/// What went wrong while parsing a lockfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+  /// The `# This file is generated by running "yarn install"...` header is missing or malformed
+  InvalidHeader,
+  /// The `__metadata` block is missing or malformed
+  InvalidMetadata,
+  /// A top-level line could not be parsed as a descriptor line like `"debug@npm:1.0.0":`
+  InvalidDescriptor { line: String },
+  /// A package property that the parser does not know about
+  UnknownProperty { key: String },
+  /// A `linkType` other than `hard` or `soft`
+  InvalidLinkType { value: String },
+  /// A line that doesn't fit anywhere in the surrounding entry
+  UnexpectedLine { line: String },
+  /// The lockfile could not be read, e.g. it isn't valid UTF-8
+  Io { message: String },
+}
+
+impl fmt::Display for ParseErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidHeader => f.write_str("missing or invalid yarn lockfile header"),
+      Self::InvalidMetadata => f.write_str("missing or invalid `__metadata` block"),
+      Self::InvalidDescriptor { line } => write!(f, "invalid descriptor line `{line}`"),
+      Self::UnknownProperty { key } => write!(f, "unknown property `{key}`"),
+      Self::InvalidLinkType { value } => {
+        write!(f, "invalid linkType `{value}`, expected `hard` or `soft`")
+      }
+      Self::UnexpectedLine { line } => write!(f, "unexpected line `{line}`"),
+      Self::Io { message } => write!(f, "failed to read lockfile: {message}"),
+    }
+  }
+}
+
+/// An error produced when a lockfile can't be parsed, pointing at where it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+  /// What went wrong
+  pub kind: ParseErrorKind,
+  /// Byte offset into the source where the error was detected
+  pub offset: usize,
+  /// 1-based line number of `offset`
+  pub line: usize,
+  /// 1-based column (in characters) of `offset`
+  pub column: usize,
+  /// The descriptor line of the entry being parsed, e.g. `debug@npm:1.0.0`
+  pub entry: Option<String>,
+}
+
+impl ParseError {
+  /// Create an error at `offset` into `source`, computing its line and column
+  pub fn new(kind: ParseErrorKind, source: &str, offset: usize, entry: Option<String>) -> Self {
+    let offset = offset.min(source.len());
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+
+    Self {
+      kind,
+      offset,
+      line,
+      column,
+      entry,
+    }
+  }
+
+  /// Render the lines around the error with a caret under the offending column
+  ///
+  /// ```text
+  ///   5 |   version: 1.0.0
+  /// > 6 |   foo:
+  ///     |   ^
+  ///   7 |   languageName: node
+  /// ```
+  pub fn code_frame(&self, source: &str) -> String {
+    const CONTEXT: usize = 2;
+
+    let first = self.line.saturating_sub(CONTEXT).max(1);
+    let last = self.line + CONTEXT;
+    let gutter = last.to_string().len();
+
+    let mut frame = String::new();
+    for (index, text) in source.lines().enumerate() {
+      let number = index + 1;
+      if number < first {
+        continue;
+      }
+      if number > last {
+        break;
+      }
+
+      let marker = if number == self.line { '>' } else { ' ' };
+      let _ = writeln!(frame, "{marker} {number:>gutter$} | {text}");
+      if number == self.line {
+        let _ = writeln!(frame, "  {:gutter$} | {}^", "", " ".repeat(self.column - 1));
+      }
+    }
+    frame
+  }
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.kind)?;
+    if let Some(entry) = &self.entry {
+      write!(f, " in entry `{entry}`")?;
+    }
+    write!(f, " at line {}, column {}", self.line, self.column)
+  }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A region of the lockfile that was skipped while parsing in recovering mode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+  /// Why the region was skipped
+  pub error: ParseError,
+  /// Byte range of the source that was skipped
+  pub skipped: Range<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} (skipped {} bytes)",
+      self.error,
+      self.skipped.end - self.skipped.start
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_line_and_column() {
+    let source = "first\nsecond\n  third\n";
+    let error = ParseError::new(
+      ParseErrorKind::UnexpectedLine {
+        line: "third".to_string(),
+      },
+      source,
+      15,
+      None,
+    );
+    assert_eq!(error.line, 3);
+    assert_eq!(error.column, 3);
+  }
+
+  #[test]
+  fn test_display_with_entry() {
+    let source = "\"debug@npm:1.0.0\":\n  foo:\n";
+    let error = ParseError::new(
+      ParseErrorKind::UnknownProperty {
+        key: "foo".to_string(),
+      },
+      source,
+      21,
+      Some("debug@npm:1.0.0".to_string()),
+    );
+    assert_eq!(
+      error.to_string(),
+      "unknown property `foo` in entry `debug@npm:1.0.0` at line 2, column 3"
+    );
+  }
+
+  #[test]
+  fn test_code_frame() {
+    let source = "a\nb\nc\n  d\ne\nf\ng\n";
+    let error = ParseError::new(ParseErrorKind::InvalidMetadata, source, 8, None);
+    assert_eq!(
+      error.code_frame(source),
+      "  2 | b\n  3 | c\n> 4 |   d\n    |   ^\n  5 | e\n  6 | f\n"
+    );
+  }
+}
@@ -0,0 +1,313 @@
+//! Git repository ranges and references
+//!
+//! Yarn accepts git dependencies in many shapes: full URLs such as
+//! `git+ssh://git@github.com/a/b.git`, host shortcuts such as `github:a/b`, and
+//! the bare `a/b` GitHub shorthand. Any of them can end in a fragment that
+//! picks what to check out, either old-style (`#main`, `#commit:abc123`) or as
+//! query parameters (`#commit=abc123&workspace=pkg`). Resolutions always pin a
+//! `commit=`.
+
+use crate::reference::percent_decode;
+use std::borrow::Cow;
+
+/// A known git host, from a shortcut like `github:` or the domain of a URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GitHost {
+  GitHub,
+  GitLab,
+  Bitbucket,
+}
+
+impl GitHost {
+  /// The domain repositories on this host are cloned from, e.g. `github.com`
+  pub fn domain(self) -> &'static str {
+    match self {
+      Self::GitHub => "github.com",
+      Self::GitLab => "gitlab.com",
+      Self::Bitbucket => "bitbucket.org",
+    }
+  }
+
+  fn from_shortcut(shortcut: &str) -> Option<Self> {
+    match shortcut {
+      "github" => Some(Self::GitHub),
+      "gitlab" => Some(Self::GitLab),
+      "bitbucket" => Some(Self::Bitbucket),
+      _ => None,
+    }
+  }
+
+  fn from_url(url: &str) -> Option<Self> {
+    [Self::GitHub, Self::GitLab, Self::Bitbucket]
+      .into_iter()
+      .find(|host| url.split(['/', '@', ':']).any(|part| part == host.domain()))
+  }
+}
+
+/// What to check out, from the fragment of a git range
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Treeish<'a> {
+  /// `#commit=abc123`, as in every resolution
+  Commit(Cow<'a, str>),
+  /// `#head=main`, a branch
+  Head(Cow<'a, str>),
+  /// `#tag=v1.0.0`
+  Tag(Cow<'a, str>),
+  /// `#semver=^1.0.0`, the highest tag satisfying the range
+  Semver(Cow<'a, str>),
+  /// An old-style `#main` fragment, which yarn tries as a commit, tag and branch
+  Unqualified(&'a str),
+}
+
+/// A decoded git range or reference
+///
+/// Borrows from the range it was parsed from, see [`GitSpec::parse`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GitSpec<'a> {
+  /// The repository as written, without the fragment, e.g. `github:a/b`
+  pub url: &'a str,
+  /// The host, when the range uses a shortcut or a well-known domain
+  pub host: Option<GitHost>,
+  /// `None` when there is no fragment, meaning the default branch
+  pub treeish: Option<Treeish<'a>>,
+  /// The `workspace=` to use from a monorepo
+  pub workspace: Option<Cow<'a, str>>,
+}
+
+impl<'a> GitSpec<'a> {
+  /// Decode a git range like `github:a/b#tag=v1.0.0`, or `None` if it isn't one
+  pub fn parse(range: &'a str) -> Option<Self> {
+    if !is_git_url(range) {
+      return None;
+    }
+
+    let (url, fragment) = range
+      .split_once('#')
+      .map_or((range, None), |(url, fragment)| (url, Some(fragment)));
+    let host = url
+      .split_once(':')
+      .and_then(|(shortcut, _)| GitHost::from_shortcut(shortcut))
+      .or_else(|| GitHost::from_url(url))
+      .or_else(|| is_shorthand(url).then_some(GitHost::GitHub));
+
+    let mut spec = Self {
+      url,
+      host,
+      treeish: None,
+      workspace: None,
+    };
+    match fragment {
+      None | Some("") => {}
+      Some(fragment) if is_query(fragment) => spec.parse_query(fragment),
+      Some(fragment) => spec.treeish = Some(parse_old_style(fragment)),
+    }
+    Some(spec)
+  }
+
+  fn parse_query(&mut self, fragment: &'a str) {
+    for (key, value) in fragment.split('&').filter_map(|pair| pair.split_once('=')) {
+      let value = percent_decode(value);
+      match key {
+        "commit" => self.treeish = Some(Treeish::Commit(value)),
+        "head" => self.treeish = Some(Treeish::Head(value)),
+        "tag" => self.treeish = Some(Treeish::Tag(value)),
+        "semver" => self.treeish = Some(Treeish::Semver(value)),
+        "workspace" => self.workspace = Some(value),
+        _ => {}
+      }
+    }
+  }
+
+  /// The pinned commit hash, from `#commit=` or an old-style full hash
+  pub fn commit(&self) -> Option<&str> {
+    match &self.treeish {
+      Some(Treeish::Commit(commit)) => Some(commit),
+      Some(Treeish::Unqualified(fragment)) if is_commit_hash(fragment) => Some(fragment),
+      _ => None,
+    }
+  }
+
+  /// The URL git would clone, e.g. `https://github.com/a/b.git` for `github:a/b`
+  pub fn repository_url(&self) -> Cow<'a, str> {
+    let path = self
+      .url
+      .split_once(':')
+      .filter(|(shortcut, _)| GitHost::from_shortcut(shortcut).is_some())
+      .map_or(self.url, |(_, path)| path);
+
+    if let Some(host) = self.host
+      && is_shorthand(path)
+    {
+      let path = path.strip_suffix(".git").unwrap_or(path);
+      return Cow::Owned(format!("https://{}/{path}.git", host.domain()));
+    }
+
+    self
+      .url
+      .strip_prefix("git+")
+      .map_or(Cow::Borrowed(self.url), Cow::Borrowed)
+  }
+}
+
+/// Whether a range points at a git repository, following the URL shapes
+/// yarn's git resolver and its GitHub shorthand accept
+pub fn is_git_url(range: &str) -> bool {
+  let url = range.split('#').next().unwrap_or(range);
+  let is_http = url.starts_with("https://") || url.starts_with("http://");
+
+  if url.starts_with("ssh:") || url.starts_with("git:") {
+    return true;
+  }
+  if let Some(rest) = url.strip_prefix("git+") {
+    return rest.contains(':');
+  }
+  if url.starts_with("git@") {
+    return url.contains('/') && has_git_extension(url);
+  }
+  if let Some((shortcut, path)) = url.split_once(':')
+    && GitHost::from_shortcut(shortcut).is_some()
+  {
+    return is_shorthand(path);
+  }
+  if let Some(path) = url.strip_prefix("https://github.com/")
+    && is_shorthand(path)
+  {
+    return true;
+  }
+
+  is_http && has_git_extension(url) || is_shorthand(url)
+}
+
+fn has_git_extension(url: &str) -> bool {
+  std::path::Path::new(url)
+    .extension()
+    .is_some_and(|extension| extension.eq_ignore_ascii_case("git"))
+}
+
+/// `owner/repo`, as in yarn's GitHub shorthand
+fn is_shorthand(path: &str) -> bool {
+  let is_part = |part: &str| {
+    !part.is_empty()
+      && part != "."
+      && part != ".."
+      && part
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || b"._-".contains(&byte))
+  };
+  path
+    .split_once('/')
+    .is_some_and(|(owner, repo)| is_part(owner) && is_part(repo))
+}
+
+fn is_query(fragment: &str) -> bool {
+  fragment
+    .split_once('=')
+    .is_some_and(|(key, _)| !key.is_empty() && key.bytes().all(|b| b.is_ascii_lowercase()))
+}
+
+/// An old-style fragment like `main` or `commit:abc123`
+fn parse_old_style(fragment: &str) -> Treeish<'_> {
+  match fragment.split_once(':') {
+    Some(("commit", commit)) => Treeish::Commit(Cow::Borrowed(commit)),
+    Some(("head", head)) => Treeish::Head(Cow::Borrowed(head)),
+    Some(("tag", tag)) => Treeish::Tag(Cow::Borrowed(tag)),
+    Some(("semver", range)) => Treeish::Semver(Cow::Borrowed(range)),
+    _ => Treeish::Unqualified(fragment),
+  }
+}
+
+fn is_commit_hash(fragment: &str) -> bool {
+  fragment.len() == 40 && fragment.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_resolution_commit() {
+    let spec = GitSpec::parse(
+      "https://github.com/charsleysa/postgres.git#commit=3b1a01b2da3e2fafb1a79006f838eff11a8de3cb",
+    )
+    .expect("should be a git range");
+    assert_eq!(spec.url, "https://github.com/charsleysa/postgres.git");
+    assert_eq!(spec.host, Some(GitHost::GitHub));
+    assert_eq!(
+      spec.commit(),
+      Some("3b1a01b2da3e2fafb1a79006f838eff11a8de3cb")
+    );
+    assert_eq!(spec.repository_url(), spec.url);
+  }
+
+  #[test]
+  fn test_shorthand_and_shortcuts() {
+    let spec = GitSpec::parse("charsleysa/postgres#fix-errors-compiled").unwrap();
+    assert_eq!(spec.host, Some(GitHost::GitHub));
+    assert_eq!(
+      spec.treeish,
+      Some(Treeish::Unqualified("fix-errors-compiled"))
+    );
+    assert_eq!(spec.commit(), None);
+    assert_eq!(
+      spec.repository_url(),
+      "https://github.com/charsleysa/postgres.git"
+    );
+
+    let spec = GitSpec::parse("gitlab:group/project#tag=v1.2.0").unwrap();
+    assert_eq!(spec.host, Some(GitHost::GitLab));
+    assert_eq!(spec.treeish, Some(Treeish::Tag(Cow::Borrowed("v1.2.0"))));
+    assert_eq!(
+      spec.repository_url(),
+      "https://gitlab.com/group/project.git"
+    );
+
+    let spec = GitSpec::parse("bitbucket:team/repo.git").unwrap();
+    assert_eq!(spec.host, Some(GitHost::Bitbucket));
+    assert_eq!(spec.treeish, None);
+    assert_eq!(spec.repository_url(), "https://bitbucket.org/team/repo.git");
+  }
+
+  #[test]
+  fn test_urls_and_fragments() {
+    let spec =
+      GitSpec::parse("git+ssh://git@github.com/a/b.git#semver=%5E1.0.0&workspace=pkg").unwrap();
+    assert_eq!(spec.url, "git+ssh://git@github.com/a/b.git");
+    assert_eq!(spec.host, Some(GitHost::GitHub));
+    assert_eq!(spec.treeish, Some(Treeish::Semver(Cow::Borrowed("^1.0.0"))));
+    assert_eq!(spec.workspace.as_deref(), Some("pkg"));
+    assert_eq!(spec.repository_url(), "ssh://git@github.com/a/b.git");
+
+    let spec = GitSpec::parse("git+https://example.com/a/b.git#head=main").unwrap();
+    assert_eq!(spec.host, None);
+    assert_eq!(spec.treeish, Some(Treeish::Head(Cow::Borrowed("main"))));
+    assert_eq!(spec.repository_url(), "https://example.com/a/b.git");
+
+    let spec = GitSpec::parse("git@github.com:a/b.git#commit:abc123").unwrap();
+    assert_eq!(spec.commit(), Some("abc123"));
+  }
+
+  #[test]
+  fn test_is_git_url() {
+    for range in [
+      "git://github.com/a/b.git",
+      "ssh://git@github.com/a/b.git",
+      "github:a/b",
+      "https://github.com/a/b",
+      "http://example.com/a/b.git#v1",
+      "a/b#main",
+    ] {
+      assert!(is_git_url(range), "{range}");
+    }
+    for range in [
+      "^1.0.0",
+      "npm:^1.0.0",
+      "./local/pkg",
+      "../a",
+      "https://example.com/a.tgz",
+      "file:../a",
+      "workspace:packages/a",
+    ] {
+      assert!(!is_git_url(range), "{range}");
+    }
+  }
+}
@@ -0,0 +1,275 @@
+//! The dependency graph between the entries of a lockfile
+//!
+//! Nodes are positions in [`Lockfile::entries`], as in [`crate::query`], so a
+//! [`DependencyGraph`] can be kept alongside the lockfile it was built from.
+//! Dependencies are resolved through the graph's [`LockfileIndex`].
+//! Each entry has edges to the entries its `dependencies` resolve to, and to
+//! the entries its `peerDependencies` resolve to when the lockfile has a
+//! matching descriptor. Peer dependencies are provided by whoever depends on
+//! the package, so one that doesn't resolve isn't an error.
+
+use crate::ident::Ident;
+use crate::lockfile::Lockfile;
+use crate::query::LockfileIndex;
+use std::fmt;
+
+/// Whether an edge comes from `dependencies` or `peerDependencies`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+  Dependency,
+  PeerDependency,
+}
+
+/// A dependency of one entry on another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+  /// Position of the dependent entry
+  pub from: usize,
+  /// Position of the entry the dependency resolves to
+  pub to: usize,
+  /// The name the dependency is declared under, which differs from the
+  /// resolved package's name for aliases like `npm:string-width@^4.2.0`
+  pub ident: Ident,
+  pub kind: DependencyKind,
+}
+
+/// A dependency that no entry in the lockfile resolves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingResolution {
+  /// Position of the entry declaring the dependency
+  pub from: usize,
+  /// The dependency, e.g. `ms@^2.1.1`
+  pub descriptor: String,
+}
+
+impl fmt::Display for MissingResolution {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "entry {} depends on `{}`, which no entry resolves",
+      self.from, self.descriptor
+    )
+  }
+}
+
+/// Errors from [`DependencyGraph::new`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+  /// Some dependencies don't resolve to an entry, see [`DependencyGraph::new_lenient`]
+  MissingResolutions(Vec<MissingResolution>),
+}
+
+impl fmt::Display for GraphError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::MissingResolutions(missing) => {
+        write!(f, "{} unresolved dependencies", missing.len())?;
+        if let Some(first) = missing.first() {
+          write!(f, ", e.g. {first}")?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl std::error::Error for GraphError {}
+
+/// Forward and reverse dependency edges between the entries of one [`Lockfile`]
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+  index: LockfileIndex,
+  dependencies: Vec<Vec<Edge>>,
+  dependents: Vec<Vec<Edge>>,
+  missing: Vec<MissingResolution>,
+}
+
+impl DependencyGraph {
+  /// Build the graph, failing if any dependency doesn't resolve to an entry
+  pub fn new(lockfile: &Lockfile) -> Result<Self, GraphError> {
+    let graph = Self::new_lenient(lockfile);
+    if graph.missing.is_empty() {
+      Ok(graph)
+    } else {
+      Err(GraphError::MissingResolutions(graph.missing))
+    }
+  }
+
+  /// Build the graph, leaving out dependencies that don't resolve to an
+  /// entry and listing them in [`DependencyGraph::missing`]
+  pub fn new_lenient(lockfile: &Lockfile) -> Self {
+    let entries = &lockfile.entries;
+    let mut graph = Self {
+      index: LockfileIndex::new(lockfile),
+      dependencies: vec![Vec::new(); entries.len()],
+      dependents: vec![Vec::new(); entries.len()],
+      missing: Vec::new(),
+    };
+
+    for (from, entry) in entries.iter().enumerate() {
+      let package = &entry.package;
+      let mut declared: Vec<_> = package
+        .dependencies
+        .values()
+        .map(|descriptor| (descriptor, DependencyKind::Dependency))
+        .chain(
+          package
+            .peer_dependencies
+            .values()
+            .map(|descriptor| (descriptor, DependencyKind::PeerDependency)),
+        )
+        .collect();
+      // Dependencies are stored in hash maps, so sort for a stable order
+      declared.sort_by_cached_key(|(descriptor, kind)| {
+        (
+          *kind,
+          descriptor.ident().display(&lockfile.interner).to_string(),
+        )
+      });
+
+      for (descriptor, kind) in declared {
+        let Some(to) = graph.index.resolve(descriptor) else {
+          if kind == DependencyKind::Dependency {
+            graph.missing.push(MissingResolution {
+              from,
+              descriptor: descriptor.display(&lockfile.interner).to_string(),
+            });
+          }
+          continue;
+        };
+        let edge = Edge {
+          from,
+          to,
+          ident: *descriptor.ident(),
+          kind,
+        };
+        graph.dependencies[from].push(edge);
+        graph.dependents[to].push(edge);
+      }
+    }
+
+    graph
+  }
+
+  /// The descriptor and locator index the graph resolves dependencies with
+  pub fn index(&self) -> &LockfileIndex {
+    &self.index
+  }
+
+  /// Edges to the entries that `position` depends on, dependencies first and
+  /// then peer dependencies, each ordered by name
+  pub fn dependencies(&self, position: usize) -> &[Edge] {
+    &self.dependencies[position]
+  }
+
+  /// Edges from the entries that depend on `position`
+  pub fn dependents(&self, position: usize) -> &[Edge] {
+    &self.dependents[position]
+  }
+
+  /// Dependencies that didn't resolve, when built with [`DependencyGraph::new_lenient`]
+  pub fn missing(&self) -> &[MissingResolution] {
+    &self.missing
+  }
+
+  /// Number of entries in the graph
+  pub fn len(&self) -> usize {
+    self.dependencies.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.dependencies.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ident::Descriptor;
+  use crate::parse::parse;
+
+  const LOCKFILE: &str = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"debug@npm:^4.3.1":
+  version: 4.3.4
+  resolution: "debug@npm:4.3.4"
+  dependencies:
+    ms: "npm:2.1.2"
+  peerDependencies:
+    supports-color: "*"
+  peerDependenciesMeta:
+    supports-color:
+      optional: true
+  languageName: node
+  linkType: hard
+
+"ms@npm:2.1.2":
+  version: 2.1.2
+  resolution: "ms@npm:2.1.2"
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  dependencies:
+    debug: "npm:^4.3.1"
+    left-pad: "npm:^1.3.0"
+    ms-alias: "npm:ms@2.1.2"
+  languageName: unknown
+  linkType: soft
+"#;
+
+  #[test]
+  fn test_dependency_graph() {
+    let lockfile = parse(LOCKFILE).expect("should parse");
+    let graph = DependencyGraph::new_lenient(&lockfile);
+    let interner = &lockfile.interner;
+    let names = |edges: &[Edge]| -> Vec<String> {
+      edges
+        .iter()
+        .map(|edge| edge.ident.display(interner).to_string())
+        .collect()
+    };
+
+    assert_eq!(graph.len(), 3);
+    let debug = graph
+      .index()
+      .resolve(&Descriptor::new(
+        interner.get_ident("debug").unwrap(),
+        "^4.3.1".to_string(),
+      ))
+      .expect("should resolve without the npm: protocol");
+    assert_eq!(debug, 0);
+    let root = lockfile.entries[2]
+      .package
+      .resolution_locator
+      .as_ref()
+      .unwrap();
+    assert_eq!(graph.index().resolve_locator(root), Some(2));
+
+    // `ms-alias` isn't in the lockfile, so it's missing along with `left-pad`;
+    // the optional `supports-color` peer isn't an error
+    assert_eq!(names(graph.dependencies(2)), ["debug"]);
+    assert_eq!(names(graph.dependencies(0)), ["ms"]);
+    assert_eq!(graph.dependencies(0)[0].to, 1);
+    assert_eq!(graph.dependents(1)[0].from, 0);
+    assert_eq!(graph.dependents(0)[0].from, 2);
+    assert!(graph.dependents(2).is_empty());
+
+    let missing: Vec<_> = graph
+      .missing()
+      .iter()
+      .map(|missing| missing.descriptor.as_str())
+      .collect();
+    assert_eq!(missing, ["left-pad@npm:^1.3.0", "ms-alias@npm:ms@2.1.2"]);
+
+    let error = DependencyGraph::new(&lockfile).unwrap_err();
+    assert!(matches!(error, GraphError::MissingResolutions(missing) if missing.len() == 2));
+  }
+}
@@ -0,0 +1,83 @@
+//! Yarn's `identHash`, `descriptorHash` and `locatorHash`
+//!
+//! Yarn keys its cache, `.pnp.cjs` and install state by these hashes, so
+//! computing them the same way lets us match lockfile entries up with those
+//! files. Each is the hex sha512 of its parts concatenated, as in yarn's
+//! `hashUtils.makeHash`:
+//!
+//! - `identHash` hashes the scope (without its `@`) and the name
+//! - `descriptorHash` hashes the `identHash` and the range
+//! - `locatorHash` hashes the `identHash` and the reference
+
+use sha2::{Digest, Sha512};
+use std::fmt::Write;
+
+/// The lowercase hex sha512 of `parts` concatenated, like yarn's `makeHash`
+pub fn make_hash(parts: &[&str]) -> String {
+  let mut hasher = Sha512::new();
+  for part in parts {
+    hasher.update(part.as_bytes());
+  }
+  to_hex(&hasher.finalize())
+}
+
+/// Lowercase hex, as node's `digest('hex')` writes it
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+  let mut hex = String::with_capacity(bytes.len() * 2);
+  for byte in bytes {
+    let _ = write!(hex, "{byte:02x}");
+  }
+  hex
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ident::Descriptor;
+  use crate::intern::Interner;
+  use crate::locator::Locator;
+
+  // Yarn names patch files after the locator, ending in the first 10
+  // characters of its `locatorHash`, e.g. `is-odd-npm-3.0.1-93c3c3f41b.patch`
+  // in the fixtures
+  #[test]
+  fn test_locator_hash_matches_yarn_patch_filenames() {
+    let mut interner = Interner::new();
+    for (name, reference, prefix) in [
+      ("is-odd", "npm:3.0.1", "93c3c3f41b"),
+      ("lodash", "npm:4.17.21", "6382451519"),
+      ("@snowplow/webview-tracker", "npm:0.2.1", "9d395f3487"),
+      ("storybook-design-token", "npm:3.1.0", "5e1cc47002"),
+    ] {
+      let locator = Locator::new(interner.intern_ident(name), reference.to_string());
+      let hash = locator.locator_hash(&interner);
+      assert_eq!(hash.len(), 128);
+      assert!(hash.starts_with(prefix), "{name}@{reference}: {hash}");
+    }
+  }
+
+  #[test]
+  fn test_ident_and_descriptor_hash() {
+    let mut interner = Interner::new();
+    let scoped = interner.intern_ident("@babel/core");
+    // The scope is hashed without its `@`
+    assert_eq!(scoped.ident_hash(&interner), make_hash(&["babel", "core"]));
+
+    let lodash = interner.intern_ident("lodash");
+    let ident_hash = lodash.ident_hash(&interner);
+    assert_eq!(ident_hash, make_hash(&["lodash"]));
+    assert_eq!(
+      Descriptor::new(lodash, "npm:^4.17.0".to_string()).descriptor_hash(&interner),
+      make_hash(&[&ident_hash, "npm:^4.17.0"])
+    );
+  }
+
+  #[test]
+  fn test_make_hash() {
+    assert_eq!(
+      make_hash(&[]),
+      "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+    );
+    assert_eq!(make_hash(&["ab", "c"]), make_hash(&["abc"]));
+  }
+}
@@ -2,34 +2,26 @@
 // https://github.com/yarnpkg/berry/blob/master/packages/yarnpkg-core/sources/types.ts#L19
 // TODO - determine if these should be serde[flatten]ed or not
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct IdentName(String);
-
-impl IdentName {
-  pub fn new(name: String) -> Self {
-    Self(name)
-  }
-
-  pub fn as_str(&self) -> &str {
-    &self.0
-  }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct IdentScope(String);
+use crate::git::{GitSpec, is_git_url};
+use crate::hash::make_hash;
+use crate::intern::{Interner, Resolved, Symbol};
+use crate::patch::{PatchSpec, PatchSpecError};
+use crate::semver::{Version, VersionRange};
+use std::borrow::Cow;
+use std::fmt;
 
-impl IdentScope {
-  pub fn new(scope: String) -> Self {
-    Self(scope)
-  }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct IdentName(Symbol);
 
-  pub fn as_str(&self) -> &str {
-    &self.0
-  }
-}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct IdentScope(Symbol);
 
 /// Scope + name of the package, with hash for comparison
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+///
+/// Both parts are interned in the lockfile's [`Interner`], so an `Ident` is
+/// `Copy` and cheap to hash; resolve it back to text with [`Ident::name`],
+/// [`Ident::scope`] or [`Ident::display`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Ident {
   /// The scope of the package, e.g. for `@scope/package`, this is `@scope`
   scope: Option<IdentScope>,
@@ -38,26 +30,63 @@ pub struct Ident {
 }
 
 impl Ident {
-  pub fn new(scope: Option<String>, name: String) -> Self {
+  pub fn new(scope: Option<Symbol>, name: Symbol) -> Self {
     Self {
-      scope: scope.map(IdentScope::new),
-      name: IdentName::new(name),
+      scope: scope.map(IdentScope),
+      name: IdentName(name),
     }
   }
 
   /// Returns the scope of the package, e.g. for `@scope/package`, this is `@scope`
-  pub fn scope(&self) -> Option<&str> {
-    self.scope.as_ref().map(IdentScope::as_str)
+  pub fn scope<'a>(&self, interner: &'a Interner) -> Option<&'a str> {
+    self.scope.map(|scope| interner.resolve(scope.0))
   }
 
   /// Returns the name of the package, e.g. for `@scope/package`, this is `package`
-  pub fn name(&self) -> &str {
-    self.name.as_str()
+  pub fn name<'a>(&self, interner: &'a Interner) -> &'a str {
+    interner.resolve(self.name.0)
+  }
+
+  /// Yarn's `identHash`, the hex sha512 of the scope (without its `@`) and name
+  pub fn ident_hash(&self, interner: &Interner) -> String {
+    let scope = self
+      .scope(interner)
+      .map_or("", |scope| scope.strip_prefix('@').unwrap_or(scope));
+    make_hash(&[scope, self.name(interner)])
+  }
+
+  /// Formats the ident the way yarn's `stringifyIdent` does, e.g. `@scope/package`
+  pub fn display<'a>(&'a self, interner: &'a Interner) -> Resolved<'a, Self> {
+    Resolved::new(self, interner)
   }
 }
 
+impl fmt::Display for Resolved<'_, Ident> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.value.scope(self.interner) {
+      Some(scope) => write!(f, "{scope}/{}", self.value.name(self.interner)),
+      None => f.write_str(self.value.name(self.interner)),
+    }
+  }
+}
+
+/// Split a descriptor or locator like `@babel/core@npm:^7.0.0` into the
+/// package name and its range or reference, at the first `@` after the
+/// name's own scope `@`
+///
+/// Returns `None` if there is no `@` to split at, as for a bare name like
+/// `@babel/core`.
+pub fn split_name_and_range(input: &str) -> Option<(&str, &str)> {
+  let at = input.get(1..)?.find('@')? + 1;
+  Some((&input[..at], &input[at + 1..]))
+}
+
 /// The range of a descriptor. Stores the raw string and a precomputed
 /// index of the first colon to allow zero-copy access to protocol and selector.
+///
+/// The protocol isn't interned: the raw string has to be kept for the
+/// round-trip anyway, so [`Range::protocol_str`] is a slice of it and
+/// [`Range::protocol`] a `Copy` enum, neither of which allocates.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Range {
   raw: String,
@@ -119,7 +148,8 @@ impl Range {
       Some("portal") => Protocol::Portal,
       Some("exec") => Protocol::Exec,
       Some("link") => Protocol::Link,
-      Some(p) if p.starts_with("git") => Protocol::Git,
+      // Git ranges come in many shapes, including `github:a/b` and plain `a/b`
+      _ if is_git_url(&self.raw) => Protocol::Git,
       Some(_) | None => Protocol::Unknown,
     }
   }
@@ -186,7 +216,15 @@ impl Range {
     }
   }
 
+  /// If protocol is git, decodes the repository, host and fragment, see [`GitSpec`].
+  pub fn as_git_spec(&self) -> Option<GitSpec<'_>> {
+    GitSpec::parse(&self.raw)
+  }
+
   /// If protocol is patch, returns (inner, optional source) split at '#'.
+  ///
+  /// Both are returned as written, still percent-encoded; see
+  /// [`Descriptor::patch_spec`] for a decoded view.
   pub fn as_patch_inner_and_source(&self) -> Option<(&str, Option<&str>)> {
     match self.protocol() {
       Protocol::Patch => {
@@ -235,12 +273,116 @@ impl Descriptor {
   pub fn range_struct(&self) -> &Range {
     &self.range
   }
+
+  /// The range with yarn's default `npm:` protocol, which dependency ranges
+  /// are written without, e.g. `npm:^4.3.1` for `^4.3.1`
+  ///
+  /// Borrowed when the range already has a protocol.
+  pub fn range_with_default_protocol(&self) -> Cow<'_, str> {
+    if self.range.protocol_str().is_some() {
+      Cow::Borrowed(self.range())
+    } else {
+      Cow::Owned(format!("npm:{}", self.range()))
+    }
+  }
+
+  /// Whether `version` satisfies the descriptor's semver range, e.g. `1.2.5` for `npm:^1.2.3`
+  ///
+  /// Ranges without a protocol are npm ranges, as in yarn, and an alias like
+  /// `npm:string-width@^4.2.0` is checked against the range after the `@`.
+  /// Returns `None` if the range isn't semver, such as the dist-tag in
+  /// `npm:latest` or a `workspace:` or `patch:` range.
+  pub fn matches(&self, version: &Version) -> Option<bool> {
+    let selector = match self.range.protocol_str() {
+      None => self.range.raw(),
+      Some("npm") => self.range.selector(),
+      Some(_) => return None,
+    };
+    let range = selector
+      .rsplit_once('@')
+      .map_or(selector, |(_, range)| range);
+
+    VersionRange::parse(range)
+      .ok()
+      .map(|range| range.matches(version))
+  }
+
+  /// Decode a `patch:` range into the patched descriptor and its patches
+  pub fn patch_spec(&self, interner: &mut Interner) -> Result<PatchSpec<Self>, PatchSpecError> {
+    PatchSpec::parse(self.range(), interner)
+  }
+
+  /// Yarn's `descriptorHash`, the hex sha512 of the `identHash` and range
+  pub fn descriptor_hash(&self, interner: &Interner) -> String {
+    make_hash(&[&self.ident.ident_hash(interner), self.range()])
+  }
+
+  /// Formats the descriptor the way yarn's `stringifyDescriptor` does, e.g. `debug@npm:^4.0.0`
+  pub fn display<'a>(&'a self, interner: &'a Interner) -> Resolved<'a, Self> {
+    Resolved::new(self, interner)
+  }
+}
+
+impl fmt::Display for Resolved<'_, Descriptor> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}@{}",
+      self.value.ident.display(self.interner),
+      self.value.range.raw()
+    )
+  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
 
+  #[test]
+  fn test_split_name_and_range() {
+    assert_eq!(
+      split_name_and_range("@babel/core@npm:^7.0.0"),
+      Some(("@babel/core", "npm:^7.0.0"))
+    );
+    assert_eq!(
+      split_name_and_range("a@npm:b@^1.0.0"),
+      Some(("a", "npm:b@^1.0.0"))
+    );
+    assert_eq!(split_name_and_range("@babel/core"), None);
+    assert_eq!(split_name_and_range(""), None);
+  }
+
+  #[test]
+  fn test_display_ident_and_descriptor() {
+    let mut interner = Interner::new();
+    let scoped = interner.intern_ident("@babel/core");
+    assert_eq!(scoped.display(&interner).to_string(), "@babel/core");
+
+    let descriptor = Descriptor::new(scoped, "npm:^7.0.0".to_string());
+    assert_eq!(
+      descriptor.display(&interner).to_string(),
+      "@babel/core@npm:^7.0.0"
+    );
+
+    let plain = interner.intern_ident("debug");
+    assert_eq!(plain.display(&interner).to_string(), "debug");
+  }
+
+  #[test]
+  fn test_descriptor_matches() {
+    let mut interner = Interner::new();
+    let ident = interner.intern_ident("string-width");
+    let version = Version::parse("4.2.3").unwrap();
+    let matches = |range: &str| Descriptor::new(ident, range.to_string()).matches(&version);
+
+    assert_eq!(matches("npm:^4.1.0"), Some(true));
+    assert_eq!(matches("^4.1.0"), Some(true));
+    assert_eq!(matches("npm:^5.0.0"), Some(false));
+    assert_eq!(matches("npm:string-width@^4.2.0"), Some(true));
+    assert_eq!(matches("npm:latest"), None);
+    assert_eq!(matches("workspace:."), None);
+  }
+
   #[test]
   fn test_range_no_protocol() {
     let r = Range::from_raw("*".to_string());
@@ -284,6 +426,20 @@ mod tests {
     let (url, frag) = r.as_git_url_and_fragment().unwrap();
     assert_eq!(url, "git+ssh://host/repo.git");
     assert_eq!(frag, Some("v1"));
+
+    for raw in [
+      "github:a/b",
+      "gitlab:a/b#tag=v1.0.0",
+      "charsleysa/postgres#fix-errors-compiled",
+      "https://github.com/a/b.git#commit=abc123",
+    ] {
+      let r = Range::from_raw(raw.to_string());
+      assert_eq!(r.protocol(), Protocol::Git, "{raw}");
+      assert!(r.as_git_spec().is_some());
+    }
+    let tarball = Range::from_raw("https://example.com/a.tgz".to_string());
+    assert_eq!(tarball.protocol(), Protocol::Unknown);
+    assert_eq!(tarball.as_git_spec(), None);
   }
 
   #[test]
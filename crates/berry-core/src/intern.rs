@@ -0,0 +1,135 @@
+//! String interning for values that repeat across a lockfile
+//!
+//! Monorepo lockfiles mention the same idents thousands of times across
+//! `dependencies` maps, and nearly every package has `languageName: node`.
+//! Each [`crate::lockfile::Lockfile`] owns an [`Interner`] that stores every
+//! distinct string once; idents and language names hold [`Symbol`]s, which are
+//! `Copy` and hash as a single integer.
+//!
+//! Symbols are only meaningful for the interner that created them, so don't
+//! compare symbols (or idents) taken from two different lockfiles.
+
+use crate::borrowed::BorrowedIdent;
+use crate::ident::Ident;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A handle to a string stored in an [`Interner`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// Stores each distinct string once and hands out [`Symbol`]s for them
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+  strings: Vec<Arc<str>>,
+  symbols: HashMap<Arc<str>, Symbol>,
+}
+
+impl Interner {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Returns the symbol for `value`, storing it if it hasn't been seen before
+  pub fn intern(&mut self, value: &str) -> Symbol {
+    if let Some(&symbol) = self.symbols.get(value) {
+      return symbol;
+    }
+
+    let symbol = Symbol(u32::try_from(self.strings.len()).expect("too many interned strings"));
+    let value: Arc<str> = Arc::from(value);
+    self.strings.push(Arc::clone(&value));
+    self.symbols.insert(value, symbol);
+    symbol
+  }
+
+  /// Returns the symbol for `value` if it has already been interned
+  pub fn get(&self, value: &str) -> Option<Symbol> {
+    self.symbols.get(value).copied()
+  }
+
+  /// Returns the string behind `symbol`
+  ///
+  /// Panics if `symbol` came from a different interner.
+  pub fn resolve(&self, symbol: Symbol) -> &str {
+    &self.strings[symbol.0 as usize]
+  }
+
+  /// Intern a package name like `@babel/core` or `debug` as an [`Ident`]
+  pub fn intern_ident(&mut self, full_name: &str) -> Ident {
+    BorrowedIdent::from_name(full_name).into_owned(self)
+  }
+
+  /// Look up the [`Ident`] for a package name like `@babel/core`, without interning it
+  ///
+  /// Returns `None` if no ident with that name appears in the lockfile, which
+  /// makes this handy for lookups like `package.dependencies.get(&ident)`.
+  pub fn get_ident(&self, full_name: &str) -> Option<Ident> {
+    let ident = BorrowedIdent::from_name(full_name);
+    let scope = match ident.scope {
+      Some(scope) => Some(self.get(scope)?),
+      None => None,
+    };
+    Some(Ident::new(scope, self.get(ident.name)?))
+  }
+
+  /// Number of distinct strings stored
+  pub fn len(&self) -> usize {
+    self.strings.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.strings.is_empty()
+  }
+}
+
+/// A value paired with the interner needed to print it, see e.g. [`Ident::display`]
+pub struct Resolved<'a, T> {
+  pub(crate) value: &'a T,
+  pub(crate) interner: &'a Interner,
+}
+
+impl<'a, T> Resolved<'a, T> {
+  pub(crate) fn new(value: &'a T, interner: &'a Interner) -> Self {
+    Self { value, interner }
+  }
+}
+
+impl fmt::Display for Resolved<'_, Symbol> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.interner.resolve(*self.value))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_intern_deduplicates() {
+    let mut interner = Interner::new();
+    let a = interner.intern("node");
+    let b = interner.intern("node");
+    let c = interner.intern("unknown");
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    assert_eq!(interner.len(), 2);
+    assert_eq!(interner.resolve(c), "unknown");
+    assert_eq!(interner.get("node"), Some(a));
+    assert_eq!(interner.get("missing"), None);
+  }
+
+  #[test]
+  fn test_intern_ident() {
+    let mut interner = Interner::new();
+    let ident = interner.intern_ident("@babel/core");
+
+    assert_eq!(ident.scope(&interner), Some("@babel"));
+    assert_eq!(ident.name(&interner), "core");
+    assert_eq!(ident.display(&interner).to_string(), "@babel/core");
+    assert_eq!(interner.get_ident("@babel/core"), Some(ident));
+    assert_eq!(interner.get_ident("@babel/other"), None);
+  }
+}
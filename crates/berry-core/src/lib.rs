@@ -6,9 +6,30 @@
 //! This project is not affiliated with Yarn or the Yarn team, but is a personal project
 //! for my own learning and interest!
 #![deny(clippy::all)]
+pub mod borrowed;
+pub mod cache;
+pub mod checksum;
+pub mod classic;
+pub mod dedupe;
+pub mod diff;
+pub mod error;
+pub mod git;
+pub mod graph;
+pub mod hash;
 pub mod ident;
+pub mod intern;
 pub mod locator;
 pub mod lockfile;
+pub mod merge;
 pub mod metadata;
 pub mod package;
 pub mod parse;
+pub mod patch;
+pub mod query;
+pub mod reader;
+pub mod reference;
+pub mod semver;
+pub mod serialize;
+pub mod why;
+
+pub use error::{Diagnostic, ParseError};
@@ -1,4 +1,10 @@
+use crate::git::GitSpec;
+use crate::hash::make_hash;
 use crate::ident::Ident;
+use crate::intern::{Interner, Resolved};
+use crate::patch::{PatchSpec, PatchSpecError};
+use crate::reference::ReferenceKind;
+use std::fmt;
 
 // Locators are just like idents (including their `identHash`), except that
 // they also contain a reference and an additional comparator hash. They are
@@ -31,4 +37,40 @@ impl Locator {
   pub fn reference(&self) -> &str {
     &self.reference
   }
+
+  /// Yarn's `locatorHash`, the hex sha512 of the `identHash` and reference
+  pub fn locator_hash(&self, interner: &Interner) -> String {
+    make_hash(&[&self.ident.ident_hash(interner), &self.reference])
+  }
+
+  /// Split the reference into its parts according to its protocol, see [`ReferenceKind`]
+  pub fn reference_kind(&self) -> ReferenceKind<'_> {
+    ReferenceKind::parse(&self.reference)
+  }
+
+  /// Decode a git reference, whose [`GitSpec::commit`] is the commit it is pinned to
+  pub fn git_spec(&self) -> Option<GitSpec<'_>> {
+    GitSpec::parse(&self.reference)
+  }
+
+  /// Decode a `patch:` reference into the patched locator, its patches and params
+  pub fn patch_spec(&self, interner: &mut Interner) -> Result<PatchSpec<Self>, PatchSpecError> {
+    PatchSpec::parse(&self.reference, interner)
+  }
+
+  /// Formats the locator the way yarn's `stringifyLocator` does, e.g. `debug@npm:1.0.0`
+  pub fn display<'a>(&'a self, interner: &'a Interner) -> Resolved<'a, Self> {
+    Resolved::new(self, interner)
+  }
+}
+
+impl fmt::Display for Resolved<'_, Locator> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}@{}",
+      self.value.ident.display(self.interner),
+      self.value.reference
+    )
+  }
 }
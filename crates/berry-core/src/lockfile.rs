@@ -1,5 +1,8 @@
+use crate::borrowed::BorrowedMetadata;
 use crate::ident::Descriptor;
+use crate::intern::Interner;
 use crate::package::Package;
+use crate::serialize::write_lockfile;
 use nom::{
   IResult, Parser,
   bytes::complete::{is_not, tag, take_while, take_while1},
@@ -8,6 +11,8 @@ use nom::{
   multi::fold_many0,
   sequence::{pair, preceded, separated_pair, terminated},
 };
+use std::fmt;
+use std::io;
 
 /// A serialized representation of a yarn lockfile.
 #[derive(Debug)]
@@ -20,6 +25,25 @@ pub struct Lockfile {
   pub resolutions: Option<Vec<(String, String)>>,
   /// Optional constraints section (key -> value)
   pub constraints: Option<Vec<(String, String)>>,
+  /// Strings behind the idents and language names of every entry
+  pub interner: Interner,
+}
+
+impl Lockfile {
+  /// Serialize the lockfile in yarn's format into any `io::Write`
+  ///
+  /// The output is byte-identical to the file it was parsed from, provided
+  /// that file was written by yarn.
+  pub fn write_to<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+    write!(writer, "{self}")
+  }
+}
+
+/// Renders the lockfile as yarn.lock text, see [`crate::serialize`]
+impl fmt::Display for Lockfile {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write_lockfile(f, self)
+  }
 }
 
 /// A single lockfile entry is a mapping of one or more descriptors to a single package
@@ -45,7 +69,7 @@ impl Entry {
 ///
 /// # Examples
 ///
-/// ```
+/// ```text
 /// __metadata:
 ///   version: 8
 ///   cacheKey: 9
@@ -53,11 +77,12 @@ impl Entry {
 ///
 /// will often be represented as:
 ///
-/// ```
+/// ```text
 /// Metadata {
 ///   version: "8",
 ///   cache_key: "9",
 /// }
+/// ```
 #[derive(Debug)]
 pub struct Metadata {
   /// The version of the lockfile
@@ -94,12 +119,12 @@ pub(crate) fn parse_metadata_line(input: &str) -> IResult<&str, (&str, &str)> {
 ///
 /// # Examples
 ///
-/// ```
+/// ```text
 /// __metadata:
 ///   version: 8
 ///   cacheKey: 9
 /// ```
-pub(crate) fn parse_metadata(input: &str) -> IResult<&str, Metadata> {
+pub(crate) fn parse_metadata(input: &str) -> IResult<&str, BorrowedMetadata<'_>> {
   let (rest, _) = terminated(tag("__metadata:"), newline).parse(input)?;
   let (rest, version_line) = parse_metadata_line(rest)?;
   let (rest, cache_key_line) = parse_metadata_line(rest)?;
@@ -109,10 +134,7 @@ pub(crate) fn parse_metadata(input: &str) -> IResult<&str, Metadata> {
   let version = version_line.1.trim_matches('"');
   let cache_key = cache_key_line.1.trim_matches('"');
 
-  Ok((
-    rest,
-    Metadata::new(version.to_string(), cache_key.to_string()),
-  ))
+  Ok((rest, BorrowedMetadata { version, cache_key }))
 }
 
 // NOTE: A faster approach **could** be to just consume three lines, and not even use nom
@@ -173,27 +195,22 @@ fn parse_top_level_kv_block<'a>(
   }
 }
 
-pub(crate) fn parse_resolutions(input: &str) -> IResult<&str, Vec<(String, String)>> {
-  let (rest, entries) = parse_top_level_kv_block("resolutions:")(input)?;
-  let owned = entries
-    .into_iter()
-    .map(|(k, v)| {
-      (
-        k.trim_matches('"').trim().to_string(),
-        v.trim_matches('"').to_string(),
-      )
-    })
-    .collect();
-  Ok((rest, owned))
+pub(crate) fn parse_resolutions(input: &str) -> IResult<&str, Vec<(&str, &str)>> {
+  let (rest, mut entries) = parse_top_level_kv_block("resolutions:")(input)?;
+  for (k, v) in &mut entries {
+    *k = k.trim_matches('"').trim();
+    *v = v.trim_matches('"');
+  }
+  Ok((rest, entries))
 }
 
-pub(crate) fn parse_constraints(input: &str) -> IResult<&str, Vec<(String, String)>> {
-  let (rest, entries) = parse_top_level_kv_block("constraints:")(input)?;
-  let owned = entries
-    .into_iter()
-    .map(|(k, v)| (k.trim().to_string(), v.trim_matches('"').to_string()))
-    .collect();
-  Ok((rest, owned))
+pub(crate) fn parse_constraints(input: &str) -> IResult<&str, Vec<(&str, &str)>> {
+  let (rest, mut entries) = parse_top_level_kv_block("constraints:")(input)?;
+  for (k, v) in &mut entries {
+    *k = k.trim();
+    *v = v.trim_matches('"');
+  }
+  Ok((rest, entries))
 }
 
 #[cfg(test)]
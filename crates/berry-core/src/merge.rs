@@ -0,0 +1,591 @@
+//! Merging lockfiles that were changed on two branches
+//!
+//! When both sides of a git merge touch yarn.lock, git leaves conflict
+//! markers that [`crate::parse`] can't read. [`merge_conflicted`] splits the
+//! file back into both sides with [`split_conflict_markers`], and [`merge`]
+//! combines two lockfiles, optionally against their common base:
+//!
+//! - every descriptor from either side is kept, unless one side removed it
+//!   and the other left it as it was in the base
+//! - a descriptor that only one side changed takes that side's resolution
+//! - a descriptor both sides resolve differently is a [`MergeConflict`], and
+//!   takes the higher version so the result doesn't depend on which branch
+//!   is merged into which
+//!
+//! The merged lockfile is written out with [`crate::serialize`] and parsed
+//! back, since its entries come from lockfiles with different interners.
+//! Both the sides and the result are parsed with [`ParseMode::Lenient`], so
+//! properties from a newer yarn survive in [`Package::extra`].
+//! Running `yarn install` afterwards still settles anything the merge can't
+//! know about, like dependencies that are no longer needed.
+
+use crate::error::ParseError;
+use crate::intern::Interner;
+use crate::lockfile::{Lockfile, Metadata};
+use crate::package::Package;
+use crate::parse::{ParseMode, parse_with_mode};
+use crate::semver::Version;
+use crate::serialize::{write_keyed_package, write_package};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+
+/// One of the lockfiles taking part in a merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+  Base,
+  Ours,
+  Theirs,
+}
+
+impl fmt::Display for Side {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::Base => "base",
+      Self::Ours => "ours",
+      Self::Theirs => "theirs",
+    })
+  }
+}
+
+/// Errors from [`merge`] and [`merge_conflicted`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+  /// One side of the conflict markers isn't a valid lockfile
+  Side { side: Side, error: ParseError },
+  /// The merged lockfile didn't parse back, which is a bug in the merge
+  Merged(ParseError),
+}
+
+impl fmt::Display for MergeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Side { side, error } => write!(f, "failed to parse the {side} lockfile: {error}"),
+      Self::Merged(error) => write!(f, "failed to parse the merged lockfile: {error}"),
+    }
+  }
+}
+
+impl std::error::Error for MergeError {}
+
+/// A descriptor both sides resolve differently
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+  /// The descriptor, e.g. `lodash@npm:^4.17.0`
+  pub descriptor: String,
+  /// Our resolution, e.g. `lodash@npm:4.17.20`
+  pub ours: String,
+  /// Their resolution, e.g. `lodash@npm:4.17.21`
+  pub theirs: String,
+  /// The side whose resolution was kept
+  pub chosen: Side,
+}
+
+/// The result of a merge
+#[derive(Debug)]
+pub struct Merge {
+  pub lockfile: Lockfile,
+  /// Descriptors resolved differently on both sides, ordered by descriptor
+  pub conflicts: Vec<MergeConflict>,
+}
+
+/// The text on each side of git's conflict markers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictSides {
+  pub ours: String,
+  /// Only present with `merge.conflictStyle = diff3` (or `zdiff3`)
+  pub base: Option<String>,
+  pub theirs: String,
+}
+
+/// Split text with `<<<<<<<`, `|||||||`, `=======` and `>>>>>>>` markers into
+/// each side, or `None` if there are no markers
+///
+/// Text outside the conflicts is shared by every side.
+pub fn split_conflict_markers(text: &str) -> Option<ConflictSides> {
+  #[derive(PartialEq)]
+  enum State {
+    Common,
+    Ours,
+    Base,
+    Theirs,
+  }
+
+  let is_marker = |line: &str, marker: &str| {
+    line
+      .trim_end_matches(['\n', '\r'])
+      .strip_prefix(marker)
+      .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+  };
+
+  let (mut ours, mut base, mut theirs) = (String::new(), String::new(), String::new());
+  let mut state = State::Common;
+  let (mut found, mut has_base) = (false, false);
+  for line in text.split_inclusive('\n') {
+    match state {
+      State::Common if is_marker(line, "<<<<<<<") => {
+        state = State::Ours;
+        found = true;
+      }
+      State::Ours if is_marker(line, "|||||||") => {
+        state = State::Base;
+        has_base = true;
+      }
+      State::Ours | State::Base if is_marker(line, "=======") => state = State::Theirs,
+      State::Theirs if is_marker(line, ">>>>>>>") => state = State::Common,
+      State::Common => {
+        ours.push_str(line);
+        base.push_str(line);
+        theirs.push_str(line);
+      }
+      State::Ours => ours.push_str(line),
+      State::Base => base.push_str(line),
+      State::Theirs => theirs.push_str(line),
+    }
+  }
+
+  found.then(|| ConflictSides {
+    ours,
+    base: has_base.then_some(base),
+    theirs,
+  })
+}
+
+/// Parse both sides of a lockfile with conflict markers and [`merge`] them
+///
+/// A lockfile without markers is parsed as it is, with no conflicts.
+pub fn merge_conflicted(text: &str) -> Result<Merge, MergeError> {
+  let Some(sides) = split_conflict_markers(text) else {
+    let lockfile = parse_lenient(text).map_err(|error| MergeError::Side {
+      side: Side::Ours,
+      error,
+    })?;
+    return Ok(Merge {
+      lockfile,
+      conflicts: Vec::new(),
+    });
+  };
+
+  let parse_side =
+    |text: &str, side| parse_lenient(text).map_err(|error| MergeError::Side { side, error });
+  let ours = parse_side(&sides.ours, Side::Ours)?;
+  let theirs = parse_side(&sides.theirs, Side::Theirs)?;
+  let base = sides
+    .base
+    .as_deref()
+    .map(|base| parse_side(base, Side::Base))
+    .transpose()?;
+  merge(base.as_ref(), &ours, &theirs)
+}
+
+fn parse_lenient(text: &str) -> Result<Lockfile, ParseError> {
+  parse_with_mode(text, ParseMode::Lenient)
+}
+
+/// A lockfile's entries as text, so lockfiles with different interners compare
+struct Resolutions<'a> {
+  lockfile: &'a Lockfile,
+  /// Descriptor to the resolution of its entry
+  by_descriptor: HashMap<String, &'a str>,
+  /// Resolution to its package
+  packages: HashMap<&'a str, &'a Package>,
+}
+
+impl<'a> Resolutions<'a> {
+  fn new(lockfile: &'a Lockfile) -> Self {
+    let mut resolutions = Self {
+      lockfile,
+      by_descriptor: HashMap::new(),
+      packages: HashMap::new(),
+    };
+    for entry in &lockfile.entries {
+      let Some(resolution) = entry.package.resolution.as_deref() else {
+        continue;
+      };
+      resolutions.packages.insert(resolution, &entry.package);
+      for descriptor in &entry.descriptors {
+        let descriptor = descriptor.display(&lockfile.interner).to_string();
+        resolutions.by_descriptor.insert(descriptor, resolution);
+      }
+    }
+    resolutions
+  }
+
+  fn get(&self, descriptor: &str) -> Option<&'a str> {
+    self.by_descriptor.get(descriptor).copied()
+  }
+
+  /// The package fields as yarn writes them, to compare across lockfiles
+  fn package_text(&self, resolution: &str) -> Option<String> {
+    let package = self.packages.get(resolution)?;
+    let mut text = String::new();
+    write_package(&mut text, package, &self.lockfile.interner).ok()?;
+    Some(text)
+  }
+
+  fn version(&self, resolution: &str) -> Option<Version> {
+    let version = self.packages.get(resolution)?.version.as_deref()?;
+    Version::parse(version).ok()
+  }
+}
+
+/// Merge our and their lockfile, resolving conflicts against `base` when the
+/// common ancestor is known
+///
+/// Without a base every difference is a conflict, except descriptors only
+/// one side has, which are kept.
+pub fn merge(
+  base: Option<&Lockfile>,
+  ours: &Lockfile,
+  theirs: &Lockfile,
+) -> Result<Merge, MergeError> {
+  let base_resolutions = base.map(Resolutions::new);
+  let base_resolution = |descriptor: &str| base_resolutions.as_ref()?.get(descriptor);
+  let ours = Resolutions::new(ours);
+  let theirs = Resolutions::new(theirs);
+
+  let mut descriptors: Vec<&str> = ours
+    .by_descriptor
+    .keys()
+    .chain(theirs.by_descriptor.keys())
+    .map(String::as_str)
+    .collect();
+  descriptors.sort_unstable();
+  descriptors.dedup();
+
+  let mut conflicts = Vec::new();
+  // Resolution to the side its package is taken from and its descriptors
+  let mut merged: BTreeMap<&str, (Side, Vec<&str>)> = BTreeMap::new();
+  for descriptor in descriptors {
+    let base = base_resolution(descriptor);
+    let chosen = match (ours.get(descriptor), theirs.get(descriptor)) {
+      (Some(our), Some(their)) if our == their => Some((Side::Ours, our)),
+      (Some(our), Some(their)) if base == Some(our) => Some((Side::Theirs, their)),
+      (Some(our), Some(their)) if base == Some(their) => Some((Side::Ours, our)),
+      (Some(our), Some(their)) => {
+        let side = higher_resolution(&ours, our, &theirs, their);
+        conflicts.push(MergeConflict {
+          descriptor: descriptor.to_string(),
+          ours: our.to_string(),
+          theirs: their.to_string(),
+          chosen: side,
+        });
+        Some(if side == Side::Ours {
+          (side, our)
+        } else {
+          (side, their)
+        })
+      }
+      // The other side removed a descriptor this side didn't change
+      (Some(our), None) if base == Some(our) => None,
+      (None, Some(their)) if base == Some(their) => None,
+      (Some(our), None) => Some((Side::Ours, our)),
+      (None, Some(their)) => Some((Side::Theirs, their)),
+      (None, None) => None,
+    };
+    if let Some((side, resolution)) = chosen {
+      merged
+        .entry(resolution)
+        .or_insert((side, Vec::new()))
+        .1
+        .push(descriptor);
+    }
+  }
+
+  let mut text = String::new();
+  write_preamble(&mut text, &ours, &theirs);
+  let mut entries: Vec<(String, &str, Side)> = merged
+    .into_iter()
+    .map(|(resolution, (side, descriptors))| {
+      let side = package_side(base_resolutions.as_ref(), &ours, &theirs, resolution, side);
+      (descriptors.join(", "), resolution, side)
+    })
+    .collect();
+  entries.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+  for (key, resolution, side) in entries {
+    let from = if side == Side::Ours { &ours } else { &theirs };
+    text.push('\n');
+    // Writing into a `String` can't fail
+    let _ = write_keyed_package(
+      &mut text,
+      &key,
+      from.packages[resolution],
+      &from.lockfile.interner,
+    );
+  }
+
+  let lockfile = parse_lenient(&text).map_err(MergeError::Merged)?;
+  Ok(Merge {
+    lockfile,
+    conflicts,
+  })
+}
+
+/// The side whose resolution has the higher version, or sorts last when
+/// either isn't semver; ours on a tie
+fn higher_resolution(
+  ours: &Resolutions<'_>,
+  our: &str,
+  theirs: &Resolutions<'_>,
+  their: &str,
+) -> Side {
+  let higher = match (ours.version(our), theirs.version(their)) {
+    (Some(our_version), Some(their_version)) if our_version != their_version => {
+      their_version > our_version
+    }
+    _ => their > our,
+  };
+  if higher { Side::Theirs } else { Side::Ours }
+}
+
+/// Which side a resolution's package fields come from
+///
+/// When both sides have the resolution, a side that changed its fields from
+/// the base (such as a new checksum) wins. When both changed them, or there
+/// is no base, the fields that sort last win so the result doesn't depend on
+/// which branch is merged into which.
+fn package_side(
+  base: Option<&Resolutions<'_>>,
+  ours: &Resolutions<'_>,
+  theirs: &Resolutions<'_>,
+  resolution: &str,
+  chosen: Side,
+) -> Side {
+  let (Some(our_text), Some(their_text)) = (
+    ours.package_text(resolution),
+    theirs.package_text(resolution),
+  ) else {
+    return chosen;
+  };
+  let base_text = base.and_then(|base| base.package_text(resolution));
+  if our_text == their_text {
+    chosen
+  } else if base_text.as_ref() == Some(&our_text) {
+    Side::Theirs
+  } else if base_text.as_ref() == Some(&their_text) || our_text > their_text {
+    Side::Ours
+  } else {
+    Side::Theirs
+  }
+}
+
+/// The header, `__metadata` and `resolutions`/`constraints` blocks
+///
+/// Metadata comes from the side with the newer lockfile version, as yarn
+/// would rewrite the older one; the blocks are merged with ours winning.
+fn write_preamble(out: &mut String, ours: &Resolutions<'_>, theirs: &Resolutions<'_>) {
+  let lockfile_version = |resolutions: &Resolutions<'_>| {
+    resolutions
+      .lockfile
+      .metadata
+      .version
+      .parse::<u32>()
+      .unwrap_or_default()
+  };
+  let metadata = if lockfile_version(theirs) > lockfile_version(ours) {
+    &theirs.lockfile.metadata
+  } else {
+    &ours.lockfile.metadata
+  };
+
+  let preamble = Lockfile {
+    metadata: Metadata::new(metadata.version.clone(), metadata.cache_key.clone()),
+    entries: Vec::new(),
+    resolutions: union_blocks(
+      ours.lockfile.resolutions.as_deref(),
+      theirs.lockfile.resolutions.as_deref(),
+    ),
+    constraints: union_blocks(
+      ours.lockfile.constraints.as_deref(),
+      theirs.lockfile.constraints.as_deref(),
+    ),
+    interner: Interner::new(),
+  };
+  let _ = write!(out, "{preamble}");
+}
+
+/// The pairs of a `resolutions` or `constraints` block from either side,
+/// sorted by key, with ours winning
+fn union_blocks(
+  ours: Option<&[(String, String)]>,
+  theirs: Option<&[(String, String)]>,
+) -> Option<Vec<(String, String)>> {
+  if ours.is_none() && theirs.is_none() {
+    return None;
+  }
+  let pairs: BTreeMap<&String, &String> = theirs
+    .into_iter()
+    .chain(ours)
+    .flatten()
+    .map(|(key, value)| (key, value))
+    .collect();
+  Some(
+    pairs
+      .into_iter()
+      .map(|(key, value)| (key.clone(), value.clone()))
+      .collect(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HEADER: &str = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+"#;
+
+  fn entry(descriptors: &str, resolution: &str, version: &str) -> String {
+    format!(
+      "\n\"{descriptors}\":\n  version: {version}\n  resolution: \"{resolution}\"\n  languageName: node\n  linkType: hard\n"
+    )
+  }
+
+  fn lockfile(entries: &[String]) -> String {
+    format!("{HEADER}{}", entries.concat())
+  }
+
+  fn resolution_of(lockfile: &Lockfile, descriptor: &str) -> Option<String> {
+    Resolutions::new(lockfile)
+      .get(descriptor)
+      .map(str::to_string)
+  }
+
+  #[test]
+  fn test_three_way_merge() {
+    let base = lockfile(&[
+      entry("debug@npm:^4.3.1", "debug@npm:4.3.1", "4.3.1"),
+      entry("left-pad@npm:^1.3.0", "left-pad@npm:1.3.0", "1.3.0"),
+      entry("lodash@npm:^4.17.0", "lodash@npm:4.17.19", "4.17.19"),
+    ]);
+    // Ours upgrades debug, removes left-pad and moves lodash to 4.17.20
+    let ours = lockfile(&[
+      entry("debug@npm:^4.3.1", "debug@npm:4.3.4", "4.3.4"),
+      entry("lodash@npm:^4.17.0", "lodash@npm:4.17.20", "4.17.20"),
+    ]);
+    // Theirs adds ms and moves lodash to 4.17.21
+    let theirs = lockfile(&[
+      entry("debug@npm:^4.3.1", "debug@npm:4.3.1", "4.3.1"),
+      entry("left-pad@npm:^1.3.0", "left-pad@npm:1.3.0", "1.3.0"),
+      entry("lodash@npm:^4.17.0", "lodash@npm:4.17.21", "4.17.21"),
+      entry("ms@npm:2.1.2", "ms@npm:2.1.2", "2.1.2"),
+    ]);
+
+    let [base, ours, theirs] = [base, ours, theirs].map(|text| parse_lenient(&text).unwrap());
+    let Merge {
+      lockfile,
+      conflicts,
+    } = merge(Some(&base), &ours, &theirs).unwrap();
+
+    assert_eq!(
+      resolution_of(&lockfile, "debug@npm:^4.3.1").as_deref(),
+      Some("debug@npm:4.3.4")
+    );
+    assert_eq!(resolution_of(&lockfile, "left-pad@npm:^1.3.0"), None);
+    assert_eq!(
+      resolution_of(&lockfile, "ms@npm:2.1.2").as_deref(),
+      Some("ms@npm:2.1.2")
+    );
+    assert_eq!(
+      resolution_of(&lockfile, "lodash@npm:^4.17.0").as_deref(),
+      Some("lodash@npm:4.17.21")
+    );
+    assert_eq!(
+      conflicts,
+      [MergeConflict {
+        descriptor: "lodash@npm:^4.17.0".to_string(),
+        ours: "lodash@npm:4.17.20".to_string(),
+        theirs: "lodash@npm:4.17.21".to_string(),
+        chosen: Side::Theirs,
+      }]
+    );
+
+    // The same result, whichever side is merged into the other
+    let swapped = merge(Some(&base), &theirs, &ours).unwrap();
+    assert_eq!(swapped.lockfile.to_string(), lockfile.to_string());
+  }
+
+  #[test]
+  fn test_merge_conflict_markers() {
+    let text = format!(
+      "{HEADER}\n<<<<<<< HEAD\n\"debug@npm:^4.3.1\":\n  version: 4.3.4\n  resolution: \"debug@npm:4.3.4\"\n=======\n\"debug@npm:^4.3.1\":\n  version: 4.3.3\n  resolution: \"debug@npm:4.3.3\"\n>>>>>>> feature\n  languageName: node\n  linkType: hard\n{}",
+      entry("ms@npm:2.1.2", "ms@npm:2.1.2", "2.1.2")
+    );
+    let sides = split_conflict_markers(&text).expect("should find markers");
+    assert_eq!(sides.base, None);
+    assert!(sides.ours.contains("debug@npm:4.3.4") && !sides.ours.contains("4.3.3"));
+    assert!(sides.theirs.contains("debug@npm:4.3.3") && !sides.theirs.contains("4.3.4"));
+
+    let Merge {
+      lockfile,
+      conflicts,
+    } = merge_conflicted(&text).unwrap();
+    assert_eq!(lockfile.entries.len(), 2);
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].chosen, Side::Ours);
+    let written = lockfile.to_string();
+    assert!(!written.contains("<<<<<<<"));
+    assert!(written.contains("resolution: \"debug@npm:4.3.4\""));
+  }
+
+  #[test]
+  fn test_merge_keeps_extra_properties() {
+    let text = lockfile(&[entry("ms@npm:2.1.2", "ms@npm:2.1.2", "2.1.2")])
+      .replace("  linkType: hard\n", "  linkType: hard\n  newField: yes\n");
+    let ours = parse_lenient(&text).unwrap();
+    let theirs = parse_lenient(&text).unwrap();
+
+    let merged = merge(None, &ours, &theirs).expect("should merge");
+    assert!(merged.conflicts.is_empty());
+    assert_eq!(merged.lockfile.to_string(), ours.to_string());
+    assert!(
+      merged.lockfile.entries[0]
+        .package
+        .extra
+        .contains_key("newField")
+    );
+
+    let conflicted = text.replace(
+      "  version: 2.1.2\n",
+      "<<<<<<< HEAD\n  version: 2.1.2\n=======\n  version: 2.1.3\n>>>>>>> feature\n",
+    );
+    let merged = merge_conflicted(&conflicted).expect("should merge both sides");
+    assert!(
+      merged.lockfile.entries[0]
+        .package
+        .extra
+        .contains_key("newField")
+    );
+  }
+
+  #[test]
+  fn test_merge_without_base_is_symmetric() {
+    let ms = entry("ms@npm:2.1.2", "ms@npm:2.1.2", "2.1.2");
+    let ours = lockfile(&[ms.replace("  languageName", "  checksum: 10c0/aaaa\n  languageName")]);
+    let theirs = lockfile(&[ms.replace("  languageName", "  checksum: 10c0/bbbb\n  languageName")]);
+    let [ours, theirs] = [ours, theirs].map(|text| parse_lenient(&text).unwrap());
+
+    let merged = merge(None, &ours, &theirs).unwrap();
+    let swapped = merge(None, &theirs, &ours).unwrap();
+    assert!(merged.conflicts.is_empty());
+    assert_eq!(swapped.lockfile.to_string(), merged.lockfile.to_string());
+    assert!(merged.lockfile.to_string().contains("checksum: 10c0/bbbb"));
+  }
+
+  #[test]
+  fn test_split_diff3_markers() {
+    let text = "a\n<<<<<<< ours\nb\n||||||| base\nc\n=======\nd\n>>>>>>> theirs\ne\n";
+    assert_eq!(
+      split_conflict_markers(text),
+      Some(ConflictSides {
+        ours: "a\nb\ne\n".to_string(),
+        base: Some("a\nc\ne\n".to_string()),
+        theirs: "a\nd\ne\n".to_string(),
+      })
+    );
+    assert_eq!(split_conflict_markers("a\nb\n"), None);
+  }
+}
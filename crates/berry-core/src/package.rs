@@ -1,4 +1,6 @@
+use crate::checksum::{Checksum, ChecksumError};
 use crate::ident::{Descriptor, Ident};
+use crate::intern::{Interner, Symbol};
 use crate::locator::Locator;
 use crate::metadata::{DependencyMeta, PeerDependencyMeta};
 use std::collections::HashMap;
@@ -16,6 +18,16 @@ pub enum LinkType {
   Soft,
 }
 
+impl LinkType {
+  /// The value as written in the lockfile, `hard` or `soft`
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Hard => "hard",
+      Self::Soft => "soft",
+    }
+  }
+}
+
 // is there a derive for this?
 impl TryFrom<&str> for LinkType {
   type Error = ();
@@ -49,22 +61,34 @@ struct PortablePath(String);
 #[allow(dead_code)]
 struct PackageVersion(String);
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct LanguageName(String);
+/// The "language" of a package, e.g. `node`, interned since nearly every
+/// package shares the same one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LanguageName(Symbol);
 
 impl LanguageName {
-  pub fn new(name: String) -> Self {
+  pub fn new(name: Symbol) -> Self {
     Self(name)
   }
-}
 
-impl AsRef<str> for LanguageName {
-  fn as_ref(&self) -> &str {
-    &self.0
+  pub fn as_str<'a>(&self, interner: &'a Interner) -> &'a str {
+    interner.resolve(self.0)
   }
 }
 
-// TODO: should the strings here be owned, or just &str for 'a
+/// A package property the parser doesn't model, kept so that it survives a
+/// round-trip when parsing in [`crate::parse::ParseMode::Lenient`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtraProperty {
+  /// A `key: value` property, unquoted and unescaped
+  Scalar(String),
+  /// A `key:` property followed by more deeply indented lines, kept verbatim
+  Block(String),
+}
+
+/// A package as it appears in the lockfile
+///
+/// See [`crate::borrowed::BorrowedPackage`] for a version that borrows from the source text.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Package {
   /// Version of the package, if available
@@ -106,15 +130,18 @@ pub struct Package {
   /// We don't need binaries in resolution, but we do neeed them to keep `yarn run` fast
   /// else we have to parse and read all of the zipfiles
   pub bin: HashMap<String, String>,
+
+  /// Properties the parser doesn't know about (only filled in lenient mode)
+  pub extra: HashMap<String, ExtraProperty>,
 }
 
 impl Package {
-  pub fn new(language_name: String, link_type: LinkType) -> Self {
+  pub fn new(language_name: LanguageName, link_type: LinkType) -> Self {
     Self {
       version: None,
       resolution: None,
       resolution_locator: None,
-      language_name: LanguageName::new(language_name),
+      language_name,
       link_type,
       checksum: None,
       conditions: None,
@@ -123,9 +150,15 @@ impl Package {
       peer_dependencies: HashMap::new(),
       peer_dependencies_meta: HashMap::new(),
       bin: HashMap::new(),
+      extra: HashMap::new(),
     }
   }
 
+  /// The `checksum` parsed into its cache key and digest, if there is one
+  pub fn parsed_checksum(&self) -> Option<Result<Checksum, ChecksumError>> {
+    self.checksum.as_deref().map(Checksum::parse)
+  }
+
   #[must_use]
   pub fn with_version(mut self, version: String) -> Self {
     self.version = Some(version);
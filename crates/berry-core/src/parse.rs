@@ -9,24 +9,222 @@ use nom::{
   sequence::{delimited, preceded, terminated},
 };
 
-use crate::ident::{Descriptor, Ident};
-use crate::locator::Locator;
+use crate::borrowed::{
+  BorrowedDescriptor, BorrowedEntry, BorrowedExtraProperty, BorrowedIdent, BorrowedLocator,
+  BorrowedLockfile, BorrowedMetadata, BorrowedPackage,
+};
+use crate::error::{Diagnostic, ParseError, ParseErrorKind};
+use crate::ident::{Descriptor, split_name_and_range};
+use crate::intern::Interner;
 use crate::lockfile::{
-  Entry, Lockfile, parse_constraints, parse_metadata, parse_resolutions, parse_yarn_header,
+  Lockfile, parse_constraints, parse_metadata, parse_resolutions, parse_yarn_header,
 };
 use crate::metadata::{DependencyMeta, PeerDependencyMeta};
 use crate::package::{LinkType, Package};
 
-/// Parse a package entry into a full lockfile Entry
-fn parse_entry(input: &str) -> IResult<&str, Entry> {
-  map(parse_package_entry, |(descriptors, package)| {
-    Entry::new(descriptors, package)
-  })
+/// How the parser treats package properties it doesn't know about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+  /// Unknown properties are an error
+  #[default]
+  Strict,
+  /// Unknown properties are kept in [`Package::extra`] so they survive round-trips
+  Lenient,
+}
+
+/// Properties that are modelled on [`Package`]
+const KNOWN_PROPERTIES: &[&str] = &[
+  "version",
+  "resolution",
+  "languageName",
+  "linkType",
+  "checksum",
+  "conditions",
+  "dependencies",
+  "peerDependencies",
+  "dependenciesMeta",
+  "peerDependenciesMeta",
+  "bin",
+];
+
+/// Parse a package entry into a full lockfile entry
+pub(crate) fn parse_entry(input: &str, mode: ParseMode) -> IResult<&str, BorrowedEntry<'_>> {
+  map(
+    |i| parse_borrowed_package_entry(i, mode),
+    |(descriptors, package)| BorrowedEntry {
+      descriptors,
+      package,
+    },
+  )
   .parse(input)
 }
 
 /// Entrypoint for parsing a yarn lockfile
 pub fn parse_lockfile(file_contents: &str) -> IResult<&str, Lockfile> {
+  parse_lockfile_with_mode(file_contents, ParseMode::Strict)
+}
+
+/// Parse a yarn lockfile, choosing how unknown package properties are handled
+pub fn parse_lockfile_with_mode(file_contents: &str, mode: ParseMode) -> IResult<&str, Lockfile> {
+  map(
+    |i| parse_lockfile_borrowed_with_mode(i, mode),
+    BorrowedLockfile::into_owned,
+  )
+  .parse(file_contents)
+}
+
+/// Parse a yarn lockfile without copying any strings out of `file_contents`
+pub fn parse_lockfile_borrowed(file_contents: &str) -> IResult<&str, BorrowedLockfile<'_>> {
+  parse_lockfile_borrowed_with_mode(file_contents, ParseMode::Strict)
+}
+
+/// Like [`parse_lockfile_borrowed`], choosing how unknown package properties are handled
+pub fn parse_lockfile_borrowed_with_mode(
+  file_contents: &str,
+  mode: ParseMode,
+) -> IResult<&str, BorrowedLockfile<'_>> {
+  parse_lockfile_borrowed_using(file_contents, |i| parse_entries(i, mode))
+}
+
+/// Parse all package entries, one after another
+fn parse_entries(input: &str, mode: ParseMode) -> IResult<&str, Vec<BorrowedEntry<'_>>> {
+  many0(|i| parse_entry(i, mode)).parse(input)
+}
+
+/// Parse a whole lockfile, using `parse_entries` for everything after the preamble
+fn parse_lockfile_borrowed_using<'a>(
+  file_contents: &'a str,
+  parse_entries: impl FnOnce(&'a str) -> IResult<&'a str, Vec<BorrowedEntry<'a>>>,
+) -> IResult<&'a str, BorrowedLockfile<'a>> {
+  let (rest, (metadata, resolutions, constraints)) = parse_preamble(file_contents)?;
+
+  // Parse all package entries as full Entries
+  let (rest, entries) = parse_entries(rest)?;
+
+  let (rest, ()) = parse_trailing_content(rest)?;
+
+  Ok((
+    rest,
+    BorrowedLockfile {
+      metadata,
+      entries,
+      resolutions,
+      constraints,
+    },
+  ))
+}
+
+/// Entry chunks smaller than this aren't worth handing to another thread
+#[cfg(feature = "parallel")]
+const MIN_PARALLEL_CHUNK_LEN: usize = 4 * 1024;
+
+/// Like [`parse_lockfile`], but parses entries on the rayon thread pool
+///
+/// The result is identical to [`parse_lockfile`], including where parsing stops
+/// and which error is returned.
+#[cfg(feature = "parallel")]
+pub fn parse_lockfile_parallel(file_contents: &str) -> IResult<&str, Lockfile> {
+  parse_lockfile_parallel_with_mode(file_contents, ParseMode::Strict)
+}
+
+/// Like [`parse_lockfile_parallel`], choosing how unknown package properties are handled
+#[cfg(feature = "parallel")]
+pub fn parse_lockfile_parallel_with_mode(
+  file_contents: &str,
+  mode: ParseMode,
+) -> IResult<&str, Lockfile> {
+  map(
+    |i| parse_lockfile_borrowed_parallel_with_mode(i, mode),
+    BorrowedLockfile::into_owned,
+  )
+  .parse(file_contents)
+}
+
+/// Like [`parse_lockfile_borrowed`], but parses entries on the rayon thread pool
+#[cfg(feature = "parallel")]
+pub fn parse_lockfile_borrowed_parallel(
+  file_contents: &str,
+) -> IResult<&str, BorrowedLockfile<'_>> {
+  parse_lockfile_borrowed_parallel_with_mode(file_contents, ParseMode::Strict)
+}
+
+/// Like [`parse_lockfile_borrowed_parallel`], choosing how unknown package properties are handled
+#[cfg(feature = "parallel")]
+pub fn parse_lockfile_borrowed_parallel_with_mode(
+  file_contents: &str,
+  mode: ParseMode,
+) -> IResult<&str, BorrowedLockfile<'_>> {
+  parse_lockfile_borrowed_using(file_contents, |i| {
+    // A few chunks per thread keeps the pool busy when entries vary in size
+    let chunk_len = (i.len() / (rayon::current_num_threads() * 4)).max(MIN_PARALLEL_CHUNK_LEN);
+    parse_entries_parallel(i, mode, chunk_len)
+  })
+}
+
+/// Parse entries in chunks of roughly `chunk_len` bytes on the rayon thread pool
+///
+/// Chunks are parsed independently and merged in source order. As soon as a
+/// chunk doesn't parse cleanly, the sequential parser takes over from the start
+/// of that chunk, so errors and leftovers match [`parse_entries`] exactly.
+#[cfg(feature = "parallel")]
+fn parse_entries_parallel(
+  input: &str,
+  mode: ParseMode,
+  chunk_len: usize,
+) -> IResult<&str, Vec<BorrowedEntry<'_>>> {
+  use rayon::prelude::*;
+
+  let chunks = split_at_entry_boundaries(input, chunk_len);
+  let parsed: Vec<_> = chunks
+    .par_iter()
+    .map(|chunk| parse_entries(chunk, mode))
+    .collect();
+
+  let mut entries = Vec::new();
+  let mut consumed = 0;
+  for (chunk, result) in chunks.iter().zip(parsed) {
+    match result {
+      Ok(("", chunk_entries)) => {
+        entries.extend(chunk_entries);
+        consumed += chunk.len();
+      }
+      // Leftovers or an error: hand this chunk and the rest to the sequential parser
+      _ => break,
+    }
+  }
+
+  let (rest, remaining) = parse_entries(&input[consumed..], mode)?;
+  entries.extend(remaining);
+  Ok((rest, entries))
+}
+
+/// Split `input` into chunks of at least `min_len` bytes, only cutting after a
+/// blank line that is followed by a column-0 line, i.e. between two entries
+#[cfg(feature = "parallel")]
+fn split_at_entry_boundaries(input: &str, min_len: usize) -> Vec<&str> {
+  let mut chunks = Vec::new();
+  let mut start = 0;
+
+  for (index, _) in input.match_indices("\n\n") {
+    let end = index + 2;
+    if end - start >= min_len && input[end..].starts_with(|c: char| !c.is_whitespace()) {
+      chunks.push(&input[start..end]);
+      start = end;
+    }
+  }
+
+  chunks.push(&input[start..]);
+  chunks
+}
+
+/// Resolutions or constraints, as parsed from their top-level block
+type TopLevelBlock<'a> = Option<Vec<(&'a str, &'a str)>>;
+
+/// Parse everything before the first entry: the header, `__metadata`, and
+/// the optional resolutions and constraints blocks
+pub(crate) fn parse_preamble(
+  file_contents: &str,
+) -> IResult<&str, (BorrowedMetadata<'_>, TopLevelBlock<'_>, TopLevelBlock<'_>)> {
   let (rest, (_, _)) = parse_yarn_header(file_contents)?;
   let (rest, metadata) = parse_metadata(rest)?;
 
@@ -34,20 +232,22 @@ pub fn parse_lockfile(file_contents: &str) -> IResult<&str, Lockfile> {
   let (rest, _) = opt(newline).parse(rest)?;
 
   // Optionally parse resolutions and constraints if present
+  // Each block may be followed by a blank line, as written by the serializer
   let (rest, resolutions) = match parse_resolutions(rest) {
-    Ok((rest2, r)) => (rest2, Some(r)),
+    Ok((rest2, r)) => (opt(newline).parse(rest2)?.0, Some(r)),
     Err(_) => (rest, None),
   };
 
   let (rest, constraints) = match parse_constraints(rest) {
-    Ok((rest2, c)) => (rest2, Some(c)),
+    Ok((rest2, c)) => (opt(newline).parse(rest2)?.0, Some(c)),
     Err(_) => (rest, None),
   };
 
-  // Parse all package entries as full Entries
-  let (rest, entries) = many0(parse_entry).parse(rest)?;
+  Ok((rest, (metadata, resolutions, constraints)))
+}
 
-  // Consume any trailing content (backticks, semicolons, whitespace, etc.)
+/// Consume any trailing content (backticks, semicolons, whitespace, etc.)
+fn parse_trailing_content(input: &str) -> IResult<&str, ()> {
   let (rest, _) = many0(alt((
     tag("`"),
     tag(";"),
@@ -56,17 +256,207 @@ pub fn parse_lockfile(file_contents: &str) -> IResult<&str, Lockfile> {
     tag("\t"),
     tag("\r"),
   )))
-  .parse(rest)?;
+  .parse(input)?;
 
-  Ok((
-    rest,
-    Lockfile {
-      metadata,
-      entries,
-      resolutions,
-      constraints,
+  Ok((rest, ()))
+}
+
+/// Parse a complete yarn lockfile, reporting failures as a [`ParseError`]
+///
+/// Unlike [`parse_lockfile`], this fails if anything other than trailing
+/// whitespace is left over, and points at the line where parsing stopped.
+pub fn parse(file_contents: &str) -> Result<Lockfile, ParseError> {
+  parse_with_mode(file_contents, ParseMode::Strict)
+}
+
+/// Like [`parse`], choosing how unknown package properties are handled
+pub fn parse_with_mode(file_contents: &str, mode: ParseMode) -> Result<Lockfile, ParseError> {
+  parse_borrowed_with_mode(file_contents, mode).map(BorrowedLockfile::into_owned)
+}
+
+/// Like [`parse`], but the result borrows its strings from `file_contents`
+///
+/// This skips an allocation per field, which adds up on large lockfiles.
+/// Call [`BorrowedLockfile::into_owned`] if the lockfile needs to outlive
+/// the source text.
+pub fn parse_borrowed(file_contents: &str) -> Result<BorrowedLockfile<'_>, ParseError> {
+  parse_borrowed_with_mode(file_contents, ParseMode::Strict)
+}
+
+/// Like [`parse_borrowed`], choosing how unknown package properties are handled
+pub fn parse_borrowed_with_mode(
+  file_contents: &str,
+  mode: ParseMode,
+) -> Result<BorrowedLockfile<'_>, ParseError> {
+  let (rest, lockfile) = parse_lockfile_borrowed_with_mode(file_contents, mode)
+    .map_err(|err| diagnose_nom(file_contents, &err))?;
+
+  if rest.trim().is_empty() {
+    Ok(lockfile)
+  } else {
+    Err(diagnose(file_contents, rest))
+  }
+}
+
+/// Parse a lockfile, skipping broken entries instead of stopping at the first one
+///
+/// Whenever an entry fails to parse, the parser resyncs at the next column-0
+/// descriptor line and records a [`Diagnostic`] for the region it skipped.
+/// This returns whatever could be salvaged, which makes it possible to repair
+/// lockfiles damaged by bad merges. Only a broken header or `__metadata`
+/// block is fatal.
+pub fn parse_recovering(
+  file_contents: &str,
+  mode: ParseMode,
+) -> Result<(Lockfile, Vec<Diagnostic>), ParseError> {
+  let (mut rest, (metadata, resolutions, constraints)) =
+    parse_preamble(file_contents).map_err(|err| diagnose_nom(file_contents, &err))?;
+
+  let mut entries = Vec::new();
+  let mut diagnostics = Vec::new();
+
+  loop {
+    if let Ok((after, ())) = parse_trailing_content(rest) {
+      rest = after;
+    }
+    if rest.is_empty() {
+      break;
+    }
+
+    let start = file_contents.len() - rest.len();
+    let stopped_at = match parse_entry(rest, mode) {
+      Ok((after, entry)) if ends_at_line_start(file_contents, after) => {
+        entries.push(entry);
+        rest = after;
+        continue;
+      }
+      // The entry stopped part way through a line, so something in it is broken
+      Ok((after, _)) => after,
+      Err(nom::Err::Error(e) | nom::Err::Failure(e)) => e.input,
+      Err(nom::Err::Incomplete(_)) => "",
+    };
+
+    let error = diagnose(file_contents, stopped_at);
+    let resume = next_descriptor_line(file_contents, error.offset);
+    diagnostics.push(Diagnostic {
+      error,
+      skipped: start..resume,
+    });
+    rest = &file_contents[resume..];
+  }
+
+  let lockfile = BorrowedLockfile {
+    metadata,
+    entries,
+    resolutions,
+    constraints,
+  };
+  Ok((lockfile.into_owned(), diagnostics))
+}
+
+/// Returns true if `rest`, a suffix of `source`, starts at the beginning of a line
+fn ends_at_line_start(source: &str, rest: &str) -> bool {
+  let offset = source.len() - rest.len();
+  rest.is_empty() || source[..offset].ends_with('\n')
+}
+
+/// Find the start of the first column-0 descriptor line after the line containing `offset`
+fn next_descriptor_line(source: &str, offset: usize) -> usize {
+  let mut line_start = source[offset..]
+    .find('\n')
+    .map_or(source.len(), |i| offset + i + 1);
+
+  while line_start < source.len() {
+    let line = source[line_start..].split('\n').next().unwrap_or_default();
+    if !line.starts_with([' ', '\t']) && parse_borrowed_descriptor_line(line).is_ok() {
+      return line_start;
+    }
+    line_start += line.len() + 1;
+  }
+
+  source.len()
+}
+
+/// Turn a nom error from anywhere in `source` into a [`ParseError`]
+pub(crate) fn diagnose_nom(source: &str, err: &nom::Err<nom::error::Error<&str>>) -> ParseError {
+  match err {
+    nom::Err::Error(e) | nom::Err::Failure(e) => diagnose(source, e.input),
+    nom::Err::Incomplete(_) => diagnose(source, ""),
+  }
+}
+
+/// Work out why parsing stopped at `rest`, a suffix of `source`
+pub(crate) fn diagnose(source: &str, rest: &str) -> ParseError {
+  let offset = source.len() - rest.len();
+
+  if parse_yarn_header(source).is_err() {
+    return ParseError::new(ParseErrorKind::InvalidHeader, source, 0, None);
+  }
+  if parse_yarn_header(source)
+    .and_then(|(after_header, _)| parse_metadata(after_header))
+    .is_err()
+  {
+    let metadata_offset = source.find("__metadata").unwrap_or(offset);
+    return ParseError::new(
+      ParseErrorKind::InvalidMetadata,
+      source,
+      metadata_offset,
+      None,
+    );
+  }
+
+  diagnose_line(source, offset)
+}
+
+/// Work out what is wrong with the line containing `offset`, given that the
+/// header and metadata are fine (or, as for [`crate::reader`], not part of `source`)
+pub(crate) fn diagnose_line(source: &str, offset: usize) -> ParseError {
+  // Parsers may have eaten the indentation of the line they stopped on
+  let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+  let line = source[line_start..].lines().next().unwrap_or_default();
+  let indent = line.len() - line.trim_start().len();
+  let offset = line_start + indent;
+  let text = line.trim().to_string();
+
+  if indent == 0 {
+    return ParseError::new(
+      ParseErrorKind::InvalidDescriptor { line: text },
+      source,
+      offset,
+      None,
+    );
+  }
+
+  let kind = match text.split_once(':') {
+    Some(("linkType", value)) if indent == 2 => ParseErrorKind::InvalidLinkType {
+      value: value.trim().to_string(),
     },
-  ))
+    Some((key, _))
+      if indent == 2
+        && !key.is_empty()
+        && !KNOWN_PROPERTIES.contains(&key)
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+    {
+      ParseErrorKind::UnknownProperty {
+        key: key.to_string(),
+      }
+    }
+    _ => ParseErrorKind::UnexpectedLine { line: text },
+  };
+
+  ParseError::new(kind, source, offset, enclosing_entry(source, line_start))
+}
+
+/// Find the descriptor line of the entry that contains the line starting at `line_start`
+fn enclosing_entry(source: &str, line_start: usize) -> Option<String> {
+  let header = source[..line_start]
+    .lines()
+    .rev()
+    .find(|line| !line.is_empty() && !line.starts_with([' ', '\t']))?;
+
+  let (_, descriptors) = parse_borrowed_descriptor_line(header).ok()?;
+  let names: Vec<String> = descriptors.iter().map(ToString::to_string).collect();
+  Some(names.join(", "))
 }
 
 /// Parse a single package entry from the lockfile
@@ -83,16 +473,58 @@ pub fn parse_lockfile(file_contents: &str) -> IResult<&str, Lockfile> {
 ///   languageName: node
 ///   linkType: hard
 /// ```
-pub fn parse_package_entry(input: &str) -> IResult<&str, (Vec<Descriptor>, Package)> {
-  let (rest, descriptors) = parse_descriptor_line(input)?;
+///
+/// Idents and language names are interned into `interner`.
+pub fn parse_package_entry<'a>(
+  input: &'a str,
+  interner: &mut Interner,
+) -> IResult<&'a str, (Vec<Descriptor>, Package)> {
+  parse_package_entry_with_mode(input, ParseMode::Strict, interner)
+}
+
+/// Parse a single package entry, choosing how unknown properties are handled
+pub fn parse_package_entry_with_mode<'a>(
+  input: &'a str,
+  mode: ParseMode,
+  interner: &mut Interner,
+) -> IResult<&'a str, (Vec<Descriptor>, Package)> {
+  let (rest, (descriptors, package)) = parse_borrowed_package_entry(input, mode)?;
+  let descriptors = descriptors
+    .into_iter()
+    .map(|descriptor| descriptor.into_owned(interner))
+    .collect();
+
+  Ok((rest, (descriptors, package.into_owned(interner))))
+}
+
+/// Parse a single package entry, borrowing from `input`
+fn parse_borrowed_package_entry(
+  input: &str,
+  mode: ParseMode,
+) -> IResult<&str, (Vec<BorrowedDescriptor<'_>>, BorrowedPackage<'_>)> {
+  let (rest, descriptors) = parse_borrowed_descriptor_line(input)?;
   let (rest, _) = newline.parse(rest)?; // consume newline after descriptor
-  let (rest, package) = parse_package_properties(rest)?;
+  let (rest, package) = parse_borrowed_package_properties(rest, mode)?;
 
   Ok((rest, (descriptors, package)))
 }
 
 /// Parse a package descriptor line like: "debug@npm:1.0.0":, eslint-config-turbo@latest:, or ? "conditional@npm:1.0.0":
-pub fn parse_descriptor_line(input: &str) -> IResult<&str, Vec<Descriptor>> {
+pub fn parse_descriptor_line<'a>(
+  input: &'a str,
+  interner: &mut Interner,
+) -> IResult<&'a str, Vec<Descriptor>> {
+  let (rest, descriptors) = parse_borrowed_descriptor_line(input)?;
+  let descriptors = descriptors
+    .into_iter()
+    .map(|descriptor| descriptor.into_owned(interner))
+    .collect();
+
+  Ok((rest, descriptors))
+}
+
+/// Parse a package descriptor line, borrowing the idents and ranges from `input`
+fn parse_borrowed_descriptor_line(input: &str) -> IResult<&str, Vec<BorrowedDescriptor<'_>>> {
   // Handle optional '? ' prefix for conditional packages
   let (rest, _) = opt(tag("? ")).parse(input)?;
 
@@ -132,17 +564,11 @@ pub fn parse_descriptor_line(input: &str) -> IResult<&str, Vec<Descriptor>> {
     (remaining, all_descriptors)
   };
 
-  // Convert borrowed strings to owned Descriptors (only allocation point)
-  let descriptors: Vec<Descriptor> = descriptor_data
+  let descriptors: Vec<BorrowedDescriptor<'_>> = descriptor_data
     .into_iter()
-    .map(|(name_part, protocol, range)| {
-      let ident = parse_name_to_ident(name_part);
-      let full_range = if protocol.is_empty() {
-        range.to_string()
-      } else {
-        format!("{protocol}:{range}")
-      };
-      Descriptor::new(ident, full_range)
+    .map(|(name_part, range)| BorrowedDescriptor {
+      ident: BorrowedIdent::from_name(name_part),
+      range,
     })
     .collect();
 
@@ -162,67 +588,48 @@ pub fn parse_descriptor_line(input: &str) -> IResult<&str, Vec<Descriptor>> {
 }
 
 /// Parse a single descriptor string like "debug@npm:1.0.0", "c@*", or "is-odd@patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd-npm-3.0.1-93c3c3f41b.patch"
-/// Returns borrowed strings to avoid allocations during parsing
-fn parse_single_descriptor(input: &str) -> IResult<&str, (&str, &str, &str)> {
+/// Returns the package name and the full range (protocol included), borrowed from `input`
+fn parse_single_descriptor(input: &str) -> IResult<&str, (&str, &str)> {
   // Try patch protocol format first (e.g., patch:is-odd@npm%3A3.0.1#~/.yarn/patches/...)
-  if let Ok((remaining, (name_part, _, protocol, _, patch_range))) = (
+  let patch = (
     parse_package_name,
     char('@'),
     parse_protocol,
     char(':'),
     parse_patch_range,
   )
-    .parse(input)
-    && protocol == "patch"
-  {
-    return Ok((remaining, (name_part, protocol, patch_range)));
-  }
-
-  // Try protocol:range format (e.g., npm:1.0.0)
-  if let Ok((remaining, (name_part, _, protocol, _, range))) = (
-    parse_package_name,
-    char('@'),
-    parse_protocol,
-    char(':'),
-    take_while1(|c: char| c != ',' && c != '"'),
-  )
-    .parse(input)
-  {
-    return Ok((remaining, (name_part, protocol, range)));
-  }
-
-  // Try simple range format (e.g., * for c@*)
-  if let Ok((remaining, (name_part, _, range))) = (
-    parse_package_name,
-    char('@'),
-    take_while1(|c: char| c != ',' && c != '"'),
-  )
-    .parse(input)
-  {
-    return Ok((remaining, (name_part, "", range)));
-  }
-
-  Err(nom::Err::Error(nom::error::Error::new(
-    input,
-    nom::error::ErrorKind::Alt,
-  )))
-}
+    .parse(input);
+
+  let (remaining, name_part) = match patch {
+    Ok((remaining, (name_part, _, "patch", _, _))) => (remaining, name_part),
+    _ => alt((
+      // Try protocol:range format (e.g., npm:1.0.0)
+      map(
+        (
+          parse_package_name,
+          char('@'),
+          parse_protocol,
+          char(':'),
+          take_while1(|c: char| c != ',' && c != '"'),
+        ),
+        |(name_part, ..)| name_part,
+      ),
+      // Try simple range format (e.g., * for c@*)
+      map(
+        (
+          parse_package_name,
+          char('@'),
+          take_while1(|c: char| c != ',' && c != '"'),
+        ),
+        |(name_part, ..)| name_part,
+      ),
+    ))
+    .parse(input)?,
+  };
 
-/// Helper function to parse name part into Ident
-fn parse_name_to_ident(name_part: &str) -> Ident {
-  name_part.strip_prefix('@').map_or_else(
-    || Ident::new(None, name_part.to_string()),
-    |stripped| {
-      // Scoped package: @babel/code-frame
-      let parts: Vec<&str> = stripped.splitn(2, '/').collect();
-      if parts.len() == 2 {
-        Ident::new(Some(format!("@{}", parts[0])), parts[1].to_string())
-      } else {
-        // Malformed scoped package, treat as simple name
-        Ident::new(None, name_part.to_string())
-      }
-    },
-  )
+  // The range runs from just after the `@` to wherever parsing stopped
+  let range = &input[name_part.len() + 1..input.len() - remaining.len()];
+  Ok((remaining, (name_part, range)))
 }
 
 /// Parse a package name, which can be scoped (@babel/code-frame) or simple (debug)
@@ -258,92 +665,119 @@ fn parse_patch_range(input: &str) -> IResult<&str, &str> {
 }
 
 /// Parse indented key-value properties for a package
-pub fn parse_package_properties(input: &str) -> IResult<&str, Package> {
+pub fn parse_package_properties<'a>(
+  input: &'a str,
+  interner: &mut Interner,
+) -> IResult<&'a str, Package> {
+  parse_package_properties_with_mode(input, ParseMode::Strict, interner)
+}
+
+/// Parse indented key-value properties for a package, choosing how unknown properties are handled
+///
+/// In [`ParseMode::Strict`] an unknown property fails with a `nom::Err::Failure`
+/// pointing at its key. An invalid `linkType` fails in either mode, as there is
+/// nowhere to keep it.
+pub fn parse_package_properties_with_mode<'a>(
+  input: &'a str,
+  mode: ParseMode,
+  interner: &mut Interner,
+) -> IResult<&'a str, Package> {
+  let (rest, package) = parse_borrowed_package_properties(input, mode)?;
+  Ok((rest, package.into_owned(interner)))
+}
+
+/// Parse indented key-value properties for a package, borrowing every value from `input`
+fn parse_borrowed_package_properties(
+  input: &str,
+  mode: ParseMode,
+) -> IResult<&str, BorrowedPackage<'_>> {
   let (rest, properties) = many0(parse_property_line).parse(input)?;
 
   // Consume any trailing whitespace and blank lines
   let (rest, _) = many0(alt((tag("\n"), tag(" "), tag("\t"), tag("\r")))).parse(rest)?;
 
   // Build the package from the parsed properties
-  let mut package = Package::new("unknown".to_string(), LinkType::Hard);
+  let mut package = BorrowedPackage::default();
 
   for property_value in properties {
     match property_value {
       PropertyValue::Simple(key, value) => match key {
         "version" => {
-          package.version = Some(value.trim_matches('"').to_string());
+          package.version = Some(value.trim_matches('"'));
         }
         "resolution" => {
-          let raw = value.trim_matches('"').to_string();
-          // Best-effort parse of the resolution into a Locator: split on '@' first occurrence
-          // Examples: "debug@npm:1.0.0", "a@workspace:packages/a"
-          if let Some(at_index) = raw.find('@') {
-            let (name_part, reference) = raw.split_at(at_index);
-            let ident = parse_name_to_ident(name_part);
-            // split_at keeps the '@' on the right; remove it
-            let reference = reference.trim_start_matches('@').to_string();
-            package.resolution_locator = Some(Locator::new(ident, reference));
+          let raw = value.trim_matches('"');
+          // Examples: "debug@npm:1.0.0", "@babel/core@npm:7.0.0", "a@workspace:packages/a"
+          if let Some((name, reference)) = split_name_and_range(raw) {
+            package.resolution_locator = Some(BorrowedLocator {
+              ident: BorrowedIdent::from_name(name),
+              reference,
+            });
           }
           package.resolution = Some(raw);
         }
         "languageName" => {
-          package.language_name = crate::package::LanguageName::new(value.to_string());
+          package.language_name = value;
         }
         "linkType" => {
           package.link_type =
-            LinkType::try_from(value).unwrap_or_else(|()| panic!("Invalid link type: {value}"));
+            LinkType::try_from(value).map_err(|()| property_failure(input, key))?;
         }
         "checksum" => {
-          package.checksum = Some(value.to_string());
+          package.checksum = Some(value);
         }
         "conditions" => {
-          package.conditions = Some(value.to_string());
-        }
-        _ => {
-          panic!("Unknown property encountered in package entry: {key}");
+          package.conditions = Some(value);
         }
+        _ => match mode {
+          ParseMode::Strict => return Err(property_failure(input, key)),
+          ParseMode::Lenient => {
+            package
+              .extra
+              .push((key, BorrowedExtraProperty::Scalar(value)));
+          }
+        },
       },
-      PropertyValue::Dependencies(dependencies) => {
-        // Store the parsed dependencies in the package
-        for (dep_name, dep_range) in dependencies {
-          let ident = parse_dependency_name_to_ident(dep_name);
-          let descriptor = Descriptor::new(ident, dep_range.to_string());
+      PropertyValue::Block(key, body) => match mode {
+        ParseMode::Strict => return Err(property_failure(input, key)),
+        ParseMode::Lenient => {
           package
-            .dependencies
-            .insert(descriptor.ident().clone(), descriptor);
+            .extra
+            .push((key, BorrowedExtraProperty::Block(body)));
         }
+      },
+      PropertyValue::Dependencies(dependencies) => {
+        package.dependencies = dependencies
+          .into_iter()
+          .map(|(dep_name, dep_range)| BorrowedDescriptor {
+            ident: BorrowedIdent::from_name(dep_name),
+            range: dep_range,
+          })
+          .collect();
       }
       PropertyValue::PeerDependencies(peer_dependencies) => {
-        // Store the parsed peer dependencies in the package
-        for (dep_name, dep_range) in peer_dependencies {
-          let ident = parse_dependency_name_to_ident(dep_name);
-          let descriptor = Descriptor::new(ident, dep_range.to_string());
-          package
-            .peer_dependencies
-            .insert(descriptor.ident().clone(), descriptor);
-        }
+        package.peer_dependencies = peer_dependencies
+          .into_iter()
+          .map(|(dep_name, dep_range)| BorrowedDescriptor {
+            ident: BorrowedIdent::from_name(dep_name),
+            range: dep_range,
+          })
+          .collect();
       }
       PropertyValue::Bin(binaries) => {
-        // Store the parsed binary executables in the package
-        for (bin_name, bin_path) in binaries {
-          package
-            .bin
-            .insert(bin_name.to_string(), bin_path.to_string());
-        }
+        package.bin = binaries;
       }
       PropertyValue::DependenciesMeta(meta) => {
-        // Store the parsed dependency metadata in the package
-        for (dep_name, dep_meta) in meta {
-          let ident = parse_dependency_name_to_ident(dep_name);
-          package.dependencies_meta.insert(ident, Some(dep_meta));
-        }
+        package.dependencies_meta = meta
+          .into_iter()
+          .map(|(dep_name, dep_meta)| (BorrowedIdent::from_name(dep_name), dep_meta))
+          .collect();
       }
       PropertyValue::PeerDependenciesMeta(meta) => {
-        // Store the parsed peer dependency metadata in the package
-        for (dep_name, dep_meta) in meta {
-          let ident = parse_dependency_name_to_ident(dep_name);
-          package.peer_dependencies_meta.insert(ident, dep_meta);
-        }
+        package.peer_dependencies_meta = meta
+          .into_iter()
+          .map(|(dep_name, dep_meta)| (BorrowedIdent::from_name(dep_name), dep_meta))
+          .collect();
       }
     }
   }
@@ -351,6 +785,15 @@ pub fn parse_package_properties(input: &str) -> IResult<&str, Package> {
   Ok((rest, package))
 }
 
+/// Build a failure pointing at `key`, which must be a slice of `input`
+fn property_failure<'a>(input: &'a str, key: &str) -> nom::Err<nom::error::Error<&'a str>> {
+  let offset = key.as_ptr() as usize - input.as_ptr() as usize;
+  nom::Err::Failure(nom::error::Error::new(
+    &input[offset..],
+    nom::error::ErrorKind::Verify,
+  ))
+}
+
 /// Parse a single property line with 2-space indentation
 /// Examples:
 /// "  version: 1.0.0"
@@ -387,7 +830,11 @@ fn parse_property_line(input: &str) -> IResult<&str, PropertyValue<'_>> {
     return Ok((rest, PropertyValue::PeerDependenciesMeta(meta)));
   }
 
-  // Unknown properties should not be silently skipped
+  // Unknown blocks are handed back so the caller can reject or keep them;
+  // they should not be silently skipped
+  if let Ok((rest, (key, body))) = parse_unknown_block(input) {
+    return Ok((rest, PropertyValue::Block(key, body)));
+  }
 
   // If nothing matches, return an error
   Err(nom::Err::Error(nom::error::Error::new(
@@ -411,14 +858,16 @@ enum PropertyValue<'a> {
   DependenciesMeta(Vec<(&'a str, DependencyMeta)>), // Dependency metadata
   /// A peer dependencies meta block
   PeerDependenciesMeta(Vec<(&'a str, PeerDependencyMeta)>), // Peer dependency metadata
+  /// A block property we don't model: key and its raw, more deeply indented body
+  Block(&'a str, &'a str),
 }
 
 /// Parse a simple key-value property line
 ///
 /// # Examples
-/// ```
-/// use crate::berry_core::parse::parse_simple_property;
 /// ```rust,ignore
+/// use berry::parse::parse_simple_property;
+///
 /// let input = r#"  version: 1.0.0"#;
 /// let result = parse_simple_property(input);
 /// assert!(result.is_ok());
@@ -441,6 +890,25 @@ pub fn parse_simple_property(input: &str) -> IResult<&str, (&str, &str)> {
   Ok((rest, (key, value)))
 }
 
+/// Parse a block property that isn't otherwise known, keeping its body verbatim
+/// Example:
+///   futureField:
+///     some: value
+fn parse_unknown_block(input: &str) -> IResult<&str, (&str, &str)> {
+  let (rest, (_, key, _, _)) = (
+    tag("  "),
+    take_while1(|c: char| c.is_alphanumeric() || c == '_'),
+    char(':'),
+    newline,
+  )
+    .parse(input)?;
+
+  // Everything indented deeper than the property itself belongs to it
+  let (rest, body) = recognize(many0((tag("    "), is_not("\r\n"), opt(newline)))).parse(rest)?;
+
+  Ok((rest, (key, body)))
+}
+
 /// Parse a dependencies block and process dependencies without collecting them
 /// This uses `fold_many0` to avoid Vec allocations
 fn parse_dependencies_block(input: &str) -> IResult<&str, Vec<(&str, &str)>> {
@@ -835,27 +1303,10 @@ fn parse_meta_object(input: &str) -> IResult<&str, DependencyMeta> {
   ))
 }
 
-/// Parse a dependency name into an Ident
-/// This handles both scoped (@scope/name) and non-scoped (name) packages
-fn parse_dependency_name_to_ident(dep_name: &str) -> Ident {
-  dep_name.strip_prefix('@').map_or_else(
-    || Ident::new(None, dep_name.to_string()),
-    |stripped| {
-      // Scoped package: @babel/code-frame
-      let parts: Vec<&str> = stripped.splitn(2, '/').collect();
-      if parts.len() == 2 {
-        Ident::new(Some(format!("@{}", parts[0])), parts[1].to_string())
-      } else {
-        // Malformed scoped package, treat as simple name
-        Ident::new(None, dep_name.to_string())
-      }
-    },
-  )
-}
-
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::package::ExtraProperty;
 
   #[test]
   fn test_parse_dependency_line_simple() {
@@ -983,7 +1434,8 @@ mod tests {
   #[test]
   fn test_parse_descriptor_line_simple() {
     let input = r#""debug@npm:1.0.0":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(result.is_ok(), "Should successfully parse descriptor line");
     let (remaining, descriptors) = result.unwrap();
@@ -992,8 +1444,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "debug");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "debug");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(descriptor.range(), "npm:1.0.0");
     assert_eq!(descriptor.range_struct().protocol_str(), Some("npm"));
     assert_eq!(descriptor.range_struct().selector(), "1.0.0");
@@ -1002,7 +1454,8 @@ mod tests {
   #[test]
   fn test_parse_descriptor_line_scoped_package() {
     let input = r#""@babel/code-frame@npm:7.12.11":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1014,8 +1467,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "code-frame");
-    assert_eq!(descriptor.ident().scope(), Some("@babel"));
+    assert_eq!(descriptor.ident().name(&interner), "code-frame");
+    assert_eq!(descriptor.ident().scope(&interner), Some("@babel"));
     assert_eq!(descriptor.range(), "npm:7.12.11");
     assert_eq!(descriptor.range_struct().protocol_str(), Some("npm"));
     assert_eq!(descriptor.range_struct().selector(), "7.12.11");
@@ -1024,7 +1477,8 @@ mod tests {
   #[test]
   fn test_parse_descriptor_line_workspace() {
     let input = r#""a@workspace:packages/a":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1036,8 +1490,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "a");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "a");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(descriptor.range(), "workspace:packages/a");
     assert_eq!(descriptor.range_struct().protocol_str(), Some("workspace"));
     assert_eq!(descriptor.range_struct().selector(), "packages/a");
@@ -1050,7 +1504,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1062,7 +1517,7 @@ mod tests {
     // Verify the parsed package properties
     assert_eq!(package.version, Some("1.0.0".to_string()));
     assert_eq!(package.resolution, Some("debug@npm:1.0.0".to_string()));
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
     assert_eq!(package.checksum, None);
   }
@@ -1076,7 +1531,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1088,7 +1544,7 @@ mod tests {
     // Verify the parsed package properties
     assert_eq!(package.version, Some("1.0.0".to_string()));
     assert_eq!(package.resolution, Some("debug@npm:1.0.0".to_string()));
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
   }
 
@@ -1100,7 +1556,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1112,7 +1569,7 @@ mod tests {
     // Verify the parsed package properties
     assert_eq!(package.version, Some("1.0.0".to_string()));
     assert_eq!(package.resolution, Some("debug@npm:1.0.0".to_string()));
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
     assert_eq!(package.checksum, Some("edfec8784737afbeea43cc78c3f56c33b88d3e751cc7220ae7a1c5370ff099e7352703275bdb56ea9967f92961231ce0625f8234d82259047303849671153f03".to_string()));
   }
@@ -1129,7 +1586,8 @@ mod tests {
   linkType: hard
 
 "#;
-    let result = parse_package_entry(input);
+    let mut interner = Interner::new();
+    let result = parse_package_entry(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1141,8 +1599,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "debug");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "debug");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(descriptor.range(), "npm:1.0.0");
 
     // Verify the parsed package
@@ -1153,10 +1611,10 @@ mod tests {
       .resolution_locator
       .as_ref()
       .expect("locator present");
-    assert_eq!(locator.ident().name(), "debug");
-    assert_eq!(locator.ident().scope(), None);
+    assert_eq!(locator.ident().name(&interner), "debug");
+    assert_eq!(locator.ident().scope(&interner), None);
     assert_eq!(locator.reference(), "npm:1.0.0");
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
     assert_eq!(package.checksum, Some("edfec8784737afbeea43cc78c3f56c33b88d3e751cc7220ae7a1c5370ff099e7352703275bdb56ea9967f92961231ce0625f8234d82259047303849671153f03".to_string()));
   }
@@ -1170,7 +1628,8 @@ mod tests {
   linkType: soft
 
 "#;
-    let result = parse_package_entry(input);
+    let mut interner = Interner::new();
+    let result = parse_package_entry(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1182,8 +1641,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "a");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "a");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(descriptor.range(), "workspace:packages/a");
 
     // Verify the parsed package
@@ -1196,17 +1655,40 @@ mod tests {
       .resolution_locator
       .as_ref()
       .expect("locator present");
-    assert_eq!(locator.ident().name(), "a");
+    assert_eq!(locator.ident().name(&interner), "a");
     assert_eq!(locator.reference(), "workspace:packages/a");
-    assert_eq!(package.language_name.as_ref(), "unknown");
+    assert_eq!(package.language_name.as_str(&interner), "unknown");
     assert_eq!(package.link_type, LinkType::Soft);
     assert_eq!(package.checksum, None);
   }
 
+  #[test]
+  fn test_parse_scoped_resolution_locator() {
+    let input = r#""@babel/core@npm:^7.0.0":
+  version: 7.19.1
+  resolution: "@babel/core@npm:7.19.1"
+  languageName: node
+  linkType: hard
+
+"#;
+    let mut interner = Interner::new();
+    let (_, (_, package)) =
+      parse_package_entry(input, &mut interner).expect("should parse scoped entry");
+
+    let locator = package
+      .resolution_locator
+      .as_ref()
+      .expect("locator present");
+    assert_eq!(locator.ident().scope(&interner), Some("@babel"));
+    assert_eq!(locator.ident().name(&interner), "core");
+    assert_eq!(locator.reference(), "npm:7.19.1");
+  }
+
   #[test]
   fn test_parse_descriptor_line_multi_descriptor() {
     let input = r#""c@*, c@workspace:packages/c":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1218,21 +1700,22 @@ mod tests {
 
     // Verify the first descriptor: c@*
     let first_descriptor = &descriptors[0];
-    assert_eq!(first_descriptor.ident().name(), "c");
-    assert_eq!(first_descriptor.ident().scope(), None);
+    assert_eq!(first_descriptor.ident().name(&interner), "c");
+    assert_eq!(first_descriptor.ident().scope(&interner), None);
     assert_eq!(first_descriptor.range(), "*");
 
     // Verify the second descriptor: c@workspace:packages/c
     let second_descriptor = &descriptors[1];
-    assert_eq!(second_descriptor.ident().name(), "c");
-    assert_eq!(second_descriptor.ident().scope(), None);
+    assert_eq!(second_descriptor.ident().name(&interner), "c");
+    assert_eq!(second_descriptor.ident().scope(&interner), None);
     assert_eq!(second_descriptor.range(), "workspace:packages/c");
   }
 
   #[test]
   fn test_parse_descriptor_line_complex_multi_descriptor() {
     let input = r#""lodash@npm:^3.0.0 || ^4.0.0, lodash@npm:^4.17.0":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1244,14 +1727,14 @@ mod tests {
 
     // Verify the first descriptor
     let first_descriptor = &descriptors[0];
-    assert_eq!(first_descriptor.ident().name(), "lodash");
-    assert_eq!(first_descriptor.ident().scope(), None);
+    assert_eq!(first_descriptor.ident().name(&interner), "lodash");
+    assert_eq!(first_descriptor.ident().scope(&interner), None);
     assert_eq!(first_descriptor.range(), "npm:^3.0.0 || ^4.0.0");
 
     // Verify the second descriptor
     let second_descriptor = &descriptors[1];
-    assert_eq!(second_descriptor.ident().name(), "lodash");
-    assert_eq!(second_descriptor.ident().scope(), None);
+    assert_eq!(second_descriptor.ident().name(&interner), "lodash");
+    assert_eq!(second_descriptor.ident().scope(&interner), None);
     assert_eq!(second_descriptor.range(), "npm:^4.17.0");
   }
 
@@ -1259,7 +1742,8 @@ mod tests {
   fn test_parse_descriptor_line_patch_protocol() {
     let input =
       r#""is-odd@patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd-npm-3.0.1-93c3c3f41b.patch":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1271,8 +1755,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "is-odd");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "is-odd");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(
       descriptor.range(),
       "patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd-npm-3.0.1-93c3c3f41b.patch"
@@ -1290,7 +1774,8 @@ mod tests {
   fn test_parse_descriptor_line_builtin_patch() {
     let input =
       r#""typescript@patch:typescript@npm%3A^5.8.3#optional!builtin<compat/typescript>":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1302,8 +1787,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "typescript");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "typescript");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(
       descriptor.range(),
       "patch:typescript@npm%3A^5.8.3#optional!builtin<compat/typescript>"
@@ -1329,7 +1814,8 @@ mod tests {
   linkType: hard
 
 "#;
-    let result = parse_package_entry(input);
+    let mut interner = Interner::new();
+    let result = parse_package_entry(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1341,8 +1827,8 @@ mod tests {
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "is-odd");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "is-odd");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(
       descriptor.range(),
       "patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd-npm-3.0.1-93c3c3f41b.patch"
@@ -1354,7 +1840,7 @@ mod tests {
       package.resolution,
       Some("is-odd@patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd-npm-3.0.1-93c3c3f41b.patch::version=3.0.1&hash=9b90ad".to_string())
     );
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
     assert_eq!(package.checksum, Some("4cd944e688e02e147969d6c1784bad1156f6084edbbd4d688f6a37b5fc764671aa99679494fc0bfaf623919bea2779e724fffc31c6ee0432b7c91f174526e5fe".to_string()));
   }
@@ -1371,7 +1857,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1386,7 +1873,7 @@ mod tests {
       package.resolution,
       Some("loose-envify@npm:1.4.0".to_string())
     );
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
     assert_eq!(package.checksum, Some("10/6517e24e0cad87ec9888f500c5b5947032cdfe6ef65e1c1936a0c48a524b81e65542c9c3edc91c97d5bddc806ee2a985dbc79be89215d613b1de5db6d1cfe6f4".to_string()));
 
@@ -1403,7 +1890,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1422,7 +1910,7 @@ mod tests {
       package.conditions,
       Some("os=linux & cpu=x64 & libc=glibc".to_string())
     );
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
   }
 
@@ -1437,7 +1925,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1469,7 +1958,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1483,7 +1973,7 @@ mod tests {
 
     let typescript_meta = package
       .dependencies_meta
-      .get(&Ident::new(None, "typescript".to_string()))
+      .get(&interner.get_ident("typescript").unwrap())
       .unwrap()
       .as_ref()
       .unwrap();
@@ -1493,7 +1983,7 @@ mod tests {
 
     let react_meta = package
       .dependencies_meta
-      .get(&Ident::new(None, "react".to_string()))
+      .get(&interner.get_ident("react").unwrap())
       .unwrap()
       .as_ref()
       .unwrap();
@@ -1512,7 +2002,8 @@ mod tests {
   languageName: node
   linkType: hard
 "#;
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1526,13 +2017,13 @@ mod tests {
 
     let react_meta = package
       .peer_dependencies_meta
-      .get(&Ident::new(None, "react".to_string()))
+      .get(&interner.get_ident("react").unwrap())
       .unwrap();
     assert!(react_meta.optional);
 
     let vue_meta: &PeerDependencyMeta = package
       .peer_dependencies_meta
-      .get(&Ident::new(None, "vue".to_string()))
+      .get(&interner.get_ident("vue").unwrap())
       .unwrap();
     assert!(vue_meta.optional);
   }
@@ -1635,7 +2126,9 @@ __metadata:
   linkType: hard
 "#;
 
-    let result = parse_package_properties(input);
+    let mut interner = Interner::new();
+
+    let result = parse_package_properties(input, &mut interner);
 
     match result {
       Ok((remaining, package)) => {
@@ -1658,7 +2151,8 @@ __metadata:
   #[test]
   fn test_parse_descriptor_line_conditional_package() {
     let input = r#"? "resolve@patch:resolve@npm%3A^1.0.0#optional!builtin<compat/resolve>, resolve@patch:resolve@npm%3A^1.1.4#optional!builtin<compat/resolve>":"#;
-    let result = parse_descriptor_line(input);
+    let mut interner = Interner::new();
+    let result = parse_descriptor_line(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1670,8 +2164,8 @@ __metadata:
 
     // Verify the first descriptor
     let first_descriptor = &descriptors[0];
-    assert_eq!(first_descriptor.ident().name(), "resolve");
-    assert_eq!(first_descriptor.ident().scope(), None);
+    assert_eq!(first_descriptor.ident().name(&interner), "resolve");
+    assert_eq!(first_descriptor.ident().scope(&interner), None);
     assert_eq!(
       first_descriptor.range(),
       "patch:resolve@npm%3A^1.0.0#optional!builtin<compat/resolve>"
@@ -1679,8 +2173,8 @@ __metadata:
 
     // Verify the second descriptor
     let second_descriptor = &descriptors[1];
-    assert_eq!(second_descriptor.ident().name(), "resolve");
-    assert_eq!(second_descriptor.ident().scope(), None);
+    assert_eq!(second_descriptor.ident().name(&interner), "resolve");
+    assert_eq!(second_descriptor.ident().scope(&interner), None);
     assert_eq!(
       second_descriptor.range(),
       "patch:resolve@npm%3A^1.1.4#optional!builtin<compat/resolve>"
@@ -1703,7 +2197,8 @@ __metadata:
   linkType: hard
 
 "#;
-    let result = parse_package_entry(input);
+    let mut interner = Interner::new();
+    let result = parse_package_entry(input, &mut interner);
 
     assert!(
       result.is_ok(),
@@ -1715,8 +2210,8 @@ __metadata:
 
     // Verify the parsed descriptor
     let descriptor = &descriptors[0];
-    assert_eq!(descriptor.ident().name(), "resolve");
-    assert_eq!(descriptor.ident().scope(), None);
+    assert_eq!(descriptor.ident().name(&interner), "resolve");
+    assert_eq!(descriptor.ident().scope(&interner), None);
     assert_eq!(
       descriptor.range(),
       "patch:resolve@npm%3A^1.0.0#optional!builtin<compat/resolve>"
@@ -1728,10 +2223,298 @@ __metadata:
       package.resolution,
       Some("resolve@patch:resolve@npm%3A1.22.10#optional!builtin<compat/resolve>::version=1.22.10&hash=c3c19d".to_string())
     );
-    assert_eq!(package.language_name.as_ref(), "node");
+    assert_eq!(package.language_name.as_str(&interner), "node");
     assert_eq!(package.link_type, LinkType::Hard);
     assert_eq!(package.dependencies.len(), 3);
     assert_eq!(package.bin.len(), 1);
     assert_eq!(package.bin.get("resolve"), Some(&"bin/resolve".to_string()));
   }
+
+  #[test]
+  fn test_parse_reports_unknown_block_property() {
+    let input = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10
+
+"debug@npm:1.0.0":
+  version: 1.0.0
+  resolution: "debug@npm:1.0.0"
+  foo:
+    bar: baz
+  languageName: node
+  linkType: hard
+"#;
+
+    let err = parse(input).expect_err("unknown block property should fail");
+    assert_eq!(
+      err.kind,
+      ParseErrorKind::UnknownProperty {
+        key: "foo".to_string()
+      }
+    );
+    assert_eq!(err.entry.as_deref(), Some("debug@npm:1.0.0"));
+    assert_eq!(err.line, 11);
+    assert_eq!(err.column, 3);
+    assert_eq!(
+      err.to_string(),
+      "unknown property `foo` in entry `debug@npm:1.0.0` at line 11, column 3"
+    );
+    assert!(err.code_frame(input).contains("> 11 |   foo:"));
+  }
+
+  #[test]
+  fn test_parse_reports_invalid_descriptor() {
+    let input = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10
+
+not a descriptor:
+  version: 1.0.0
+"#;
+
+    let err = parse(input).expect_err("bad descriptor should fail");
+    assert_eq!(
+      err.kind,
+      ParseErrorKind::InvalidDescriptor {
+        line: "not a descriptor:".to_string()
+      }
+    );
+    assert_eq!(err.entry, None);
+    assert_eq!((err.line, err.column), (8, 1));
+  }
+
+  #[test]
+  fn test_parse_reports_invalid_header_and_metadata() {
+    let err = parse("__metadata:\n  version: 8\n").expect_err("missing header");
+    assert_eq!(err.kind, ParseErrorKind::InvalidHeader);
+
+    let input = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  cacheKey
+"#;
+    let err = parse(input).expect_err("broken metadata");
+    assert_eq!(err.kind, ParseErrorKind::InvalidMetadata);
+    assert_eq!(err.line, 4);
+  }
+
+  #[test]
+  fn test_parse_package_properties_unknown_property_strict() {
+    let input = r"  version: 1.0.0
+  futureField: yes
+  languageName: node
+  linkType: hard
+";
+    let mut interner = Interner::new();
+    let result = parse_package_properties(input, &mut interner);
+    let Err(nom::Err::Failure(e)) = result else {
+      panic!("Unknown property should fail in strict mode: {result:?}");
+    };
+    assert!(e.input.starts_with("futureField: yes"));
+  }
+
+  #[test]
+  fn test_parse_package_properties_unknown_property_lenient() {
+    let input = r#"  version: 1.0.0
+  futureField: yes
+  futureBlock:
+    nested: "npm:^1.0.0"
+  languageName: node
+  linkType: hard
+"#;
+    let mut interner = Interner::new();
+    let (remaining, package) =
+      parse_package_properties_with_mode(input, ParseMode::Lenient, &mut interner)
+        .expect("lenient parse");
+    assert_eq!(remaining, "");
+    assert_eq!(
+      package.extra.get("futureField"),
+      Some(&ExtraProperty::Scalar("yes".to_string()))
+    );
+    assert_eq!(
+      package.extra.get("futureBlock"),
+      Some(&ExtraProperty::Block(
+        "    nested: \"npm:^1.0.0\"\n".to_string()
+      ))
+    );
+    assert_eq!(package.language_name.as_str(&interner), "node");
+  }
+
+  #[test]
+  fn test_parse_reports_unknown_property_and_invalid_link_type() {
+    let header = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10
+
+"debug@npm:1.0.0":
+  version: 1.0.0
+"#;
+
+    let err = parse(&format!("{header}  foo: bar\n  linkType: hard\n")).expect_err("unknown");
+    assert_eq!(
+      err.to_string(),
+      "unknown property `foo` in entry `debug@npm:1.0.0` at line 10, column 3"
+    );
+
+    for mode in [ParseMode::Strict, ParseMode::Lenient] {
+      let err = parse_with_mode(&format!("{header}  linkType: weird\n"), mode)
+        .expect_err("invalid linkType");
+      assert_eq!(
+        err.kind,
+        ParseErrorKind::InvalidLinkType {
+          value: "weird".to_string()
+        }
+      );
+      assert_eq!(err.entry.as_deref(), Some("debug@npm:1.0.0"));
+    }
+  }
+
+  #[test]
+  fn test_parse_recovering_skips_broken_entries() {
+    let input = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10
+
+"a@npm:1.0.0":
+  version: 1.0.0
+  resolution: "a@npm:1.0.0"
+  languageName: node
+  linkType: hard
+
+"b@npm:1.0.0":
+  version: 1.0.0
+  resolution: "b@npm:1.0.0"
+  linkType: sideways
+  languageName: node
+
+<<<<<<< HEAD
+"c@npm:1.0.0":
+  version: 1.0.0
+  resolution: "c@npm:1.0.0"
+    misplaced: line
+  languageName: node
+  linkType: hard
+
+"d@npm:1.0.0":
+  version: 1.0.0
+  resolution: "d@npm:1.0.0"
+  languageName: node
+  linkType: hard
+`;
+"#;
+
+    assert!(parse(input).is_err());
+
+    let (lockfile, diagnostics) =
+      parse_recovering(input, ParseMode::Strict).expect("header and metadata are fine");
+
+    let names: Vec<&str> = lockfile
+      .entries
+      .iter()
+      .map(|entry| entry.descriptors[0].ident().name(&lockfile.interner))
+      .collect();
+    assert_eq!(names, vec!["a", "d"]);
+
+    // The conflict marker isn't a descriptor line, so it is skipped along with `b`
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(
+      diagnostics[0].error.kind,
+      ParseErrorKind::InvalidLinkType {
+        value: "sideways".to_string()
+      }
+    );
+    assert_eq!(diagnostics[0].error.entry.as_deref(), Some("b@npm:1.0.0"));
+    let skipped = &input[diagnostics[0].skipped.clone()];
+    assert!(skipped.starts_with("\"b@npm:1.0.0\":"));
+    assert!(skipped.ends_with("<<<<<<< HEAD\n"));
+
+    assert_eq!(
+      diagnostics[1].error.kind,
+      ParseErrorKind::UnexpectedLine {
+        line: "misplaced: line".to_string()
+      }
+    );
+    assert_eq!(diagnostics[1].error.entry.as_deref(), Some("c@npm:1.0.0"));
+    let skipped = &input[diagnostics[1].skipped.clone()];
+    assert!(skipped.starts_with("\"c@npm:1.0.0\":"));
+    assert!(skipped.ends_with("  linkType: hard\n\n"));
+  }
+
+  #[test]
+  fn test_parse_recovering_clean_lockfile_has_no_diagnostics() {
+    let input = include_str!("../../../fixtures/yarn4-patch.lock");
+    let (lockfile, diagnostics) = parse_recovering(input, ParseMode::Strict).expect("parse");
+    assert!(diagnostics.is_empty());
+    assert_eq!(lockfile.entries.len(), parse(input).unwrap().entries.len());
+  }
+
+  #[test]
+  fn test_parse_borrowed_points_into_source() {
+    let contents = include_str!("../../../fixtures/yarn4-patch.lock");
+    let lockfile = parse_borrowed(contents).expect("should parse fixture");
+    let source = contents.as_bytes().as_ptr_range();
+
+    let entry = &lockfile.entries[0];
+    let descriptor = entry.descriptors[0];
+    assert!(source.contains(&descriptor.ident.name.as_ptr()));
+    assert!(source.contains(&descriptor.range.as_ptr()));
+    assert!(source.contains(&entry.package.resolution.unwrap().as_ptr()));
+
+    // Converting to the owned form gives the same lockfile as parsing it owned
+    let owned = parse(contents).expect("should parse fixture");
+    assert_eq!(lockfile.into_owned().to_string(), owned.to_string());
+  }
+
+  #[cfg(feature = "parallel")]
+  #[test]
+  fn test_parse_entries_parallel_matches_sequential() {
+    let contents = include_str!("../../../fixtures/berry.lock");
+    let (body, _) = parse_preamble(contents).expect("should parse preamble");
+
+    // Tiny chunks, so that nearly every entry lands in its own chunk
+    let chunks = split_at_entry_boundaries(body, 1);
+    assert!(chunks.len() > 100);
+    assert_eq!(chunks.concat(), body);
+
+    let sequential = parse_entries(body, ParseMode::Strict).expect("sequential parse");
+    let parallel = parse_entries_parallel(body, ParseMode::Strict, 1).expect("parallel parse");
+    assert_eq!(parallel, sequential);
+  }
+
+  #[cfg(feature = "parallel")]
+  #[test]
+  fn test_parse_entries_parallel_stops_where_sequential_does() {
+    let body = r#""a@npm:1.0.0":
+  version: 1.0.0
+  languageName: node
+  linkType: hard
+
+"b@npm:1.0.0":
+  version: 1.0.0
+  languageName: node
+  linkType: sideways
+
+"c@npm:1.0.0":
+  version: 1.0.0
+  languageName: node
+  linkType: hard
+"#;
+
+    let sequential = parse_entries(body, ParseMode::Strict);
+    let parallel = parse_entries_parallel(body, ParseMode::Strict, 1);
+    assert_eq!(parallel, sequential);
+  }
 }
@@ -0,0 +1,374 @@
+//! Patch descriptors and locators, decoded
+//!
+//! A patch range wraps the descriptor (or, in a resolution, the locator) being
+//! patched, with its `:` and `#` percent-encoded, followed by the patches to
+//! apply and the parameters yarn binds once the package is resolved:
+//!
+//! ```text
+//! patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd.patch::version=3.0.1&hash=496de7
+//! ```
+//!
+//! [`PatchSpec`] decodes all of this, and writes it back out byte-for-byte
+//! through [`PatchSpec::display`].
+
+use crate::ident::{Descriptor, Ident, split_name_and_range};
+use crate::intern::{Interner, Resolved};
+use crate::locator::Locator;
+use crate::reference::{RangeParts, encode_param, encode_unsafe, percent_decode};
+use std::fmt;
+
+/// Errors from [`PatchSpec::parse`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchSpecError {
+  /// The range doesn't use the `patch:` protocol
+  NotPatch(String),
+  /// There is no `name@range#` before the patches
+  MissingInner(String),
+}
+
+impl fmt::Display for PatchSpecError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotPatch(range) => write!(f, "`{range}` is not a patch range"),
+      Self::MissingInner(range) => write!(f, "patch range `{range}` has no patched package"),
+    }
+  }
+}
+
+impl std::error::Error for PatchSpecError {}
+
+/// A descriptor or locator that can be patched, see [`PatchSpec`]
+pub trait PatchTarget: Sized {
+  fn from_parts(ident: Ident, range: String) -> Self;
+  fn ident(&self) -> &Ident;
+  /// The range of a descriptor, or the reference of a locator
+  fn range(&self) -> &str;
+}
+
+impl PatchTarget for Descriptor {
+  fn from_parts(ident: Ident, range: String) -> Self {
+    Self::new(ident, range)
+  }
+
+  fn ident(&self) -> &Ident {
+    self.ident()
+  }
+
+  fn range(&self) -> &str {
+    self.range()
+  }
+}
+
+impl PatchTarget for Locator {
+  fn from_parts(ident: Ident, range: String) -> Self {
+    Self::new(ident, range)
+  }
+
+  fn ident(&self) -> &Ident {
+    self.ident()
+  }
+
+  fn range(&self) -> &str {
+    self.reference()
+  }
+}
+
+/// One of the `&`-separated patches of a [`PatchSpec`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PatchSource {
+  /// A patch shipped with yarn, like `builtin<compat/typescript>`
+  Builtin {
+    name: String,
+    /// Written as `~builtin<...>`, as yarn 3 did
+    legacy: bool,
+  },
+  /// A patch that is skipped if it doesn't apply, like `optional!builtin<compat/fsevents>`
+  Optional(Box<Self>),
+  /// A patch file, relative to the project root when it starts with `~/`,
+  /// and otherwise to the workspace in the `locator` param
+  Path(String),
+}
+
+impl PatchSource {
+  fn parse(source: &str) -> Self {
+    if let Some(source) = source.strip_prefix("optional!") {
+      return Self::Optional(Box::new(Self::parse(source)));
+    }
+
+    let (legacy, builtin) = source
+      .strip_prefix('~')
+      .map_or((false, source), |source| (true, source));
+    builtin
+      .strip_prefix("builtin<")
+      .and_then(|name| name.strip_suffix('>'))
+      .map_or_else(
+        || Self::Path(percent_decode(source).into_owned()),
+        |name| Self::Builtin {
+          name: name.to_string(),
+          legacy,
+        },
+      )
+  }
+
+  /// Whether yarn ignores this patch when it fails to apply
+  pub fn is_optional(&self) -> bool {
+    matches!(self, Self::Optional(_))
+  }
+}
+
+impl fmt::Display for PatchSource {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Builtin { name, legacy } => {
+        let prefix = if *legacy { "~" } else { "" };
+        write!(f, "{prefix}builtin<{name}>")
+      }
+      Self::Optional(source) => write!(f, "optional!{source}"),
+      Self::Path(path) => f.write_str(&encode_unsafe(path)),
+    }
+  }
+}
+
+/// The decoded `::key=value&...` parameters of a [`PatchSpec`], in the order written
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PatchParams {
+  pairs: Vec<(String, String)>,
+}
+
+impl PatchParams {
+  /// The decoded value of `key`
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self
+      .pairs
+      .iter()
+      .find(|(name, _)| name == key)
+      .map(|(_, value)| value.as_str())
+  }
+
+  /// The version of the patched package, only present in resolutions
+  pub fn version(&self) -> Option<&str> {
+    self.get("version")
+  }
+
+  /// A hash of the patches, only present in resolutions
+  pub fn hash(&self) -> Option<&str> {
+    self.get("hash")
+  }
+
+  /// The workspace that relative patch paths are relative to, e.g. `root@workspace:.`
+  pub fn locator(&self) -> Option<&str> {
+    self.get("locator")
+  }
+
+  /// Every decoded key and value
+  pub fn pairs(&self) -> &[(String, String)] {
+    &self.pairs
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.pairs.is_empty()
+  }
+}
+
+impl fmt::Display for PatchParams {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, (key, value)) in self.pairs.iter().enumerate() {
+      if i > 0 {
+        f.write_str("&")?;
+      }
+      write!(f, "{}={}", encode_param(key), encode_param(value))?;
+    }
+    Ok(())
+  }
+}
+
+/// A decoded `patch:` range, wrapping a [`Descriptor`] in descriptors and a
+/// [`Locator`] in resolutions
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PatchSpec<T> {
+  /// The package being patched, e.g. `is-odd@npm:3.0.1`
+  pub inner: T,
+  pub sources: Vec<PatchSource>,
+  pub params: PatchParams,
+}
+
+impl<T: PatchTarget> PatchSpec<T> {
+  /// Decode a range like `patch:is-odd@npm%3A3.0.1#./is-odd.patch`
+  ///
+  /// The patched package is interned into `interner`, so ranges from outside
+  /// the lockfile (such as a `resolutions` override) decode too.
+  pub fn parse(range: &str, interner: &mut Interner) -> Result<Self, PatchSpecError> {
+    let parts = RangeParts::parse(range);
+    if parts.protocol != Some("patch") {
+      return Err(PatchSpecError::NotPatch(range.to_string()));
+    }
+
+    let inner = parts
+      .source
+      .map(percent_decode)
+      .ok_or_else(|| PatchSpecError::MissingInner(range.to_string()))?;
+    let (name, inner_range) = split_name_and_range(&inner)
+      .ok_or_else(|| PatchSpecError::MissingInner(range.to_string()))?;
+    let ident = interner.intern_ident(name);
+
+    let params = parts
+      .params
+      .pairs()
+      .iter()
+      .map(|(key, value)| {
+        (
+          percent_decode(key).into_owned(),
+          percent_decode(value).into_owned(),
+        )
+      })
+      .collect();
+
+    Ok(Self {
+      inner: T::from_parts(ident, inner_range.to_string()),
+      sources: parts.selector.split('&').map(PatchSource::parse).collect(),
+      params: PatchParams { pairs: params },
+    })
+  }
+
+  /// Formats the spec back into a `patch:` range
+  pub fn display<'a>(&'a self, interner: &'a Interner) -> Resolved<'a, Self> {
+    Resolved::new(self, interner)
+  }
+}
+
+impl<T: PatchTarget> fmt::Display for Resolved<'_, PatchSpec<T>> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let spec = self.value;
+    write!(
+      f,
+      "patch:{}@{}#",
+      spec.inner.ident().display(self.interner),
+      encode_unsafe(spec.inner.range())
+    )?;
+    for (i, source) in spec.sources.iter().enumerate() {
+      if i > 0 {
+        f.write_str("&")?;
+      }
+      write!(f, "{source}")?;
+    }
+    if !spec.params.is_empty() {
+      write!(f, "::{}", spec.params)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn interner() -> Interner {
+    let mut interner = Interner::new();
+    for name in ["is-odd", "fsevents", "resolve", "@types/react-router"] {
+      interner.intern_ident(name);
+    }
+    interner
+  }
+
+  #[test]
+  fn test_patch_locator() {
+    let mut interner = interner();
+    let range = "patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd-npm-3.0.1-93c3c3f41b.patch::version=3.0.1&hash=496de7";
+    let spec = PatchSpec::<Locator>::parse(range, &mut interner).unwrap();
+
+    assert_eq!(
+      spec.inner.display(&interner).to_string(),
+      "is-odd@npm:3.0.1"
+    );
+    assert_eq!(
+      spec.sources,
+      [PatchSource::Path(
+        "~/.yarn/patches/is-odd-npm-3.0.1-93c3c3f41b.patch".to_string()
+      )]
+    );
+    assert_eq!(spec.params.version(), Some("3.0.1"));
+    assert_eq!(spec.params.hash(), Some("496de7"));
+    assert_eq!(spec.params.locator(), None);
+    assert_eq!(spec.display(&interner).to_string(), range);
+  }
+
+  #[test]
+  fn test_builtin_and_optional_patches() {
+    let mut interner = interner();
+
+    let range = "patch:fsevents@npm%3A^2.1.2#optional!builtin<compat/fsevents>";
+    let spec = PatchSpec::<Descriptor>::parse(range, &mut interner).unwrap();
+    assert_eq!(spec.inner.range(), "npm:^2.1.2");
+    assert_eq!(
+      spec.sources,
+      [PatchSource::Optional(Box::new(PatchSource::Builtin {
+        name: "compat/fsevents".to_string(),
+        legacy: false,
+      }))]
+    );
+    assert!(spec.sources[0].is_optional());
+    assert_eq!(spec.display(&interner).to_string(), range);
+
+    let range = "patch:resolve@^1.20.0#~builtin<compat/resolve>";
+    let spec = PatchSpec::<Descriptor>::parse(range, &mut interner).unwrap();
+    assert_eq!(spec.inner.range(), "^1.20.0");
+    assert_eq!(
+      spec.sources,
+      [PatchSource::Builtin {
+        name: "compat/resolve".to_string(),
+        legacy: true,
+      }]
+    );
+    assert_eq!(spec.display(&interner).to_string(), range);
+  }
+
+  #[test]
+  fn test_multiple_patches_and_locator_param() {
+    let mut interner = interner();
+    let range = "patch:@types/react-router@npm%3A5.1.20#.yarn/patches/a.patch&optional!./b.patch::version=5.1.20&hash=2deb33&locator=web%40workspace%3Aapps%2Fweb";
+    let spec = PatchSpec::<Locator>::parse(range, &mut interner).unwrap();
+
+    assert_eq!(
+      spec.inner.display(&interner).to_string(),
+      "@types/react-router@npm:5.1.20"
+    );
+    assert_eq!(
+      spec.sources,
+      [
+        PatchSource::Path(".yarn/patches/a.patch".to_string()),
+        PatchSource::Optional(Box::new(PatchSource::Path("./b.patch".to_string()))),
+      ]
+    );
+    assert_eq!(spec.params.locator(), Some("web@workspace:apps/web"));
+    assert_eq!(spec.display(&interner).to_string(), range);
+  }
+
+  #[test]
+  fn test_patch_spec_errors() {
+    let mut interner = interner();
+    let mut parse = |range: &str| PatchSpec::<Descriptor>::parse(range, &mut interner).unwrap_err();
+
+    assert_eq!(
+      parse("npm:^1.0.0"),
+      PatchSpecError::NotPatch("npm:^1.0.0".to_string())
+    );
+    assert_eq!(
+      parse("patch:./a.patch"),
+      PatchSpecError::MissingInner("patch:./a.patch".to_string())
+    );
+  }
+
+  #[test]
+  fn test_patch_outside_the_lockfile() {
+    let mut interner = interner();
+    let range = "patch:left-pad@npm%3A1.3.0#./left-pad.patch";
+    let spec = PatchSpec::<Descriptor>::parse(range, &mut interner).unwrap();
+
+    assert_eq!(interner.get_ident("left-pad"), Some(*spec.inner.ident()));
+    assert_eq!(
+      spec.inner.display(&interner).to_string(),
+      "left-pad@npm:1.3.0"
+    );
+    assert_eq!(spec.display(&interner).to_string(), range);
+  }
+}
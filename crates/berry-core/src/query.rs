@@ -0,0 +1,163 @@
+//! Lookups over the entries of a parsed lockfile
+//!
+//! [`LockfileIndex`] maps descriptors and locators to positions in
+//! [`Lockfile::entries`], so it can be kept alongside the lockfile it was built
+//! from (as the node and wasm bindings do) without borrowing it. It is also
+//! what [`crate::graph::DependencyGraph`] resolves dependencies through.
+
+use crate::ident::{Descriptor, split_name_and_range};
+use crate::locator::Locator;
+use crate::lockfile::Lockfile;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Descriptor and locator lookup tables for one [`Lockfile`]
+///
+/// Positions are only meaningful for the lockfile the index was built from,
+/// which the string lookups also need for its interner.
+#[derive(Debug, Clone, Default)]
+pub struct LockfileIndex {
+  /// Every descriptor to the position of its entry
+  by_descriptor: HashMap<Descriptor, usize>,
+  /// The resolution of every entry to its position
+  by_locator: HashMap<Locator, usize>,
+}
+
+impl LockfileIndex {
+  pub fn new(lockfile: &Lockfile) -> Self {
+    let mut index = Self::default();
+
+    for (position, entry) in lockfile.entries.iter().enumerate() {
+      for descriptor in &entry.descriptors {
+        index.by_descriptor.insert(descriptor.clone(), position);
+      }
+      if let Some(locator) = &entry.package.resolution_locator {
+        index.by_locator.insert(locator.clone(), position);
+      }
+    }
+
+    index
+  }
+
+  /// The entry a descriptor resolves to
+  ///
+  /// Dependency ranges are written without yarn's default `npm:` protocol, so
+  /// `debug@^4.3.1` resolves through the `debug@npm:^4.3.1` descriptor.
+  pub fn resolve(&self, descriptor: &Descriptor) -> Option<usize> {
+    if let Some(&position) = self.by_descriptor.get(descriptor) {
+      return Some(position);
+    }
+    let Cow::Owned(range) = descriptor.range_with_default_protocol() else {
+      return None;
+    };
+    let with_protocol = Descriptor::new(*descriptor.ident(), range);
+    self.by_descriptor.get(&with_protocol).copied()
+  }
+
+  /// The entry whose resolution is `locator`
+  pub fn resolve_locator(&self, locator: &Locator) -> Option<usize> {
+    self.by_locator.get(locator).copied()
+  }
+
+  /// The entry a descriptor like `debug@npm:^4.3.1` resolves to, see
+  /// [`LockfileIndex::resolve`]
+  pub fn find_descriptor(&self, lockfile: &Lockfile, descriptor: &str) -> Option<usize> {
+    let (name, range) = split_name_and_range(descriptor)?;
+    let ident = lockfile.interner.get_ident(name)?;
+    self.resolve(&Descriptor::new(ident, range.to_string()))
+  }
+
+  /// The entry whose `resolution` is a locator like `debug@npm:4.3.4`
+  pub fn find_locator(&self, lockfile: &Lockfile, locator: &str) -> Option<usize> {
+    let (name, reference) = split_name_and_range(locator)?;
+    let ident = lockfile.interner.get_ident(name)?;
+    self.resolve_locator(&Locator::new(ident, reference.to_string()))
+  }
+
+  /// The entries that the `dependencies` of `locator` resolve to, ordered by
+  /// dependency name, or `None` if no entry has that resolution
+  ///
+  /// Peer dependencies are not included, as they are provided by the parent.
+  pub fn dependencies_of(&self, lockfile: &Lockfile, locator: &str) -> Option<Vec<usize>> {
+    let position = self.find_locator(lockfile, locator)?;
+
+    let mut descriptors: Vec<_> = lockfile.entries[position]
+      .package
+      .dependencies
+      .values()
+      .collect();
+    descriptors.sort_by_cached_key(|descriptor| descriptor.display(&lockfile.interner).to_string());
+
+    Some(
+      descriptors
+        .into_iter()
+        .filter_map(|descriptor| self.resolve(descriptor))
+        .collect(),
+    )
+  }
+}
+
+/// Positions of every entry for a package name like `@babel/core`, one per resolved version
+pub fn find_by_name(lockfile: &Lockfile, name: &str) -> Vec<usize> {
+  let Some(ident) = lockfile.interner.get_ident(name) else {
+    return Vec::new();
+  };
+
+  lockfile
+    .entries
+    .iter()
+    .enumerate()
+    .filter(|(_, entry)| entry.descriptors.iter().any(|d| *d.ident() == ident))
+    .map(|(position, _)| position)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parse;
+
+  #[test]
+  fn test_lockfile_index() {
+    let lockfile =
+      parse(include_str!("../../../fixtures/berry.lock")).expect("should parse fixture");
+    let index = LockfileIndex::new(&lockfile);
+    let version = |position: usize| lockfile.entries[position].package.version.as_deref();
+
+    let core = index.find_descriptor(&lockfile, "@babel/core@npm:^7.0.0");
+    assert_eq!(core.and_then(version), Some("7.19.1"));
+    assert_eq!(index.find_descriptor(&lockfile, "@babel/core@^7.0.0"), core);
+    assert_eq!(
+      index.find_descriptor(&lockfile, "@babel/core@npm:^99.0.0"),
+      None
+    );
+    assert_eq!(
+      index.find_locator(&lockfile, "@babel/core@npm:7.19.1"),
+      core
+    );
+
+    let versions: Vec<_> = find_by_name(&lockfile, "semver")
+      .into_iter()
+      .map(version)
+      .collect();
+    assert_eq!(versions, [Some("6.3.0"), Some("7.3.7")]);
+    assert!(find_by_name(&lockfile, "not-a-package").is_empty());
+
+    let dependencies: Vec<_> = index
+      .dependencies_of(&lockfile, "@babel/helper-compilation-targets@npm:7.19.1")
+      .expect("locator is in the lockfile")
+      .into_iter()
+      .map(|position| lockfile.entries[position].package.resolution.as_deref())
+      .collect();
+    assert_eq!(
+      dependencies,
+      [
+        Some("@babel/compat-data@npm:7.19.1"),
+        Some("@babel/helper-validator-option@npm:7.18.6"),
+        Some("browserslist@npm:4.21.4"),
+        Some("semver@npm:6.3.0"),
+      ]
+    );
+    assert_eq!(index.dependencies_of(&lockfile, "x@npm:1.0.0"), None);
+  }
+}
@@ -0,0 +1,289 @@
+//! Streaming access to lockfile entries
+//!
+//! [`LockfileReader`] parses the header, `__metadata` and the optional
+//! `resolutions`/`constraints` blocks up front, then reads and parses one entry
+//! at a time. Only the entry being parsed is held in memory, which suits tools
+//! that scan a large lockfile for a handful of packages.
+
+use crate::borrowed::owned_pairs;
+use crate::error::{ParseError, ParseErrorKind};
+use crate::intern::Interner;
+use crate::lockfile::{Entry, Metadata};
+use crate::parse::{ParseMode, diagnose, diagnose_line, diagnose_nom, parse_entry, parse_preamble};
+use std::io::BufRead;
+
+/// Reads lockfile entries lazily from any [`BufRead`]
+///
+/// A memory-mapped file can be read by passing its bytes, as `&[u8]`
+/// implements `BufRead`.
+///
+/// Each item is an owned [`Entry`] whose idents are interned in
+/// [`LockfileReader::interner`]. A broken entry yields an `Err`, and reading
+/// carries on with the next entry, as entries are parsed independently.
+///
+/// ```no_run
+/// use berry::reader::LockfileReader;
+/// use std::{fs::File, io::BufReader};
+///
+/// let file = BufReader::new(File::open("yarn.lock")?);
+/// let mut reader = LockfileReader::new(file)?;
+/// while let Some(entry) = reader.next() {
+///   let entry = entry?;
+///   let interner = reader.interner();
+///   if entry.descriptors.iter().any(|d| d.ident().name(interner) == "lodash") {
+///     println!("{:?}", entry.package.version);
+///   }
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct LockfileReader<R> {
+  reader: R,
+  mode: ParseMode,
+  metadata: Metadata,
+  resolutions: Option<Vec<(String, String)>>,
+  constraints: Option<Vec<(String, String)>>,
+  interner: Interner,
+  /// The first line of the next entry, which has already been read
+  pending: Option<String>,
+  /// Byte offset of `pending` in the whole file
+  offset: usize,
+  /// Number of lines before `pending` in the whole file
+  line: usize,
+}
+
+impl<R: BufRead> LockfileReader<R> {
+  /// Read the header and metadata, rejecting unknown package properties
+  pub fn new(reader: R) -> Result<Self, ParseError> {
+    Self::with_mode(reader, ParseMode::Strict)
+  }
+
+  /// Read the header and metadata, choosing how unknown package properties are handled
+  pub fn with_mode(mut reader: R, mode: ParseMode) -> Result<Self, ParseError> {
+    let mut preamble = String::new();
+    let mut pending = None;
+
+    loop {
+      let mut line = String::new();
+      if read_line(&mut reader, &mut line, &preamble)? == 0 {
+        break;
+      }
+      if starts_entry(&line) && !is_preamble_block(&line) {
+        pending = Some(line);
+        break;
+      }
+      preamble.push_str(&line);
+    }
+
+    let (rest, (metadata, resolutions, constraints)) =
+      parse_preamble(&preamble).map_err(|err| diagnose_nom(&preamble, &err))?;
+    if !rest.trim().is_empty() {
+      return Err(diagnose(&preamble, rest));
+    }
+
+    Ok(Self {
+      reader,
+      mode,
+      metadata: metadata.into_owned(),
+      resolutions: resolutions.map(owned_pairs),
+      constraints: constraints.map(owned_pairs),
+      interner: Interner::new(),
+      pending,
+      offset: preamble.len(),
+      line: preamble.matches('\n').count(),
+    })
+  }
+
+  /// The lockfile's `__metadata` block
+  pub fn metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  /// The optional `resolutions` block
+  pub fn resolutions(&self) -> Option<&[(String, String)]> {
+    self.resolutions.as_deref()
+  }
+
+  /// The optional `constraints` block
+  pub fn constraints(&self) -> Option<&[(String, String)]> {
+    self.constraints.as_deref()
+  }
+
+  /// Resolves the idents and language names of the entries read so far
+  pub fn interner(&self) -> &Interner {
+    &self.interner
+  }
+
+  /// Read the lines of the next entry, skipping any trailing junk at the end of the file
+  fn read_entry(&mut self) -> Result<Option<String>, ParseError> {
+    let Some(mut text) = self.pending.take() else {
+      return Ok(None);
+    };
+
+    loop {
+      let mut line = String::new();
+      if read_line(&mut self.reader, &mut line, "")? == 0 {
+        break;
+      }
+      if starts_entry(&line) {
+        self.pending = Some(line);
+        break;
+      }
+      text.push_str(&line);
+    }
+
+    Ok(Some(text))
+  }
+
+  /// Parse the text of a single entry starting at `offset` / `line` in the file
+  fn parse_entry(&mut self, text: &str, offset: usize, line: usize) -> Result<Entry, ParseError> {
+    let stopped_at = match parse_entry(text, self.mode) {
+      Ok(("", entry)) => return Ok(entry.into_owned(&mut self.interner)),
+      Ok((rest, _)) => rest,
+      Err(nom::Err::Error(e) | nom::Err::Failure(e)) => e.input,
+      Err(nom::Err::Incomplete(_)) => "",
+    };
+
+    // The entry starts at the beginning of a line, so columns are unaffected
+    let mut error = diagnose_line(text, text.len() - stopped_at.len());
+    error.offset += offset;
+    error.line += line;
+    Err(error)
+  }
+}
+
+impl<R: BufRead> Iterator for LockfileReader<R> {
+  type Item = Result<Entry, ParseError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let (offset, line) = (self.offset, self.line);
+      let text = match self.read_entry() {
+        Ok(text) => text?,
+        Err(error) => {
+          self.pending = None;
+          return Some(Err(error));
+        }
+      };
+      self.offset += text.len();
+      self.line += text.matches('\n').count();
+
+      // Stray backticks and semicolons, as left behind by some fixtures
+      if text
+        .trim_matches(['`', ';', ' ', '\t', '\r', '\n'])
+        .is_empty()
+      {
+        continue;
+      }
+
+      return Some(self.parse_entry(&text, offset, line));
+    }
+  }
+}
+
+/// Read one line, turning I/O failures (including invalid UTF-8) into a [`ParseError`]
+fn read_line<R: BufRead>(
+  reader: &mut R,
+  line: &mut String,
+  read: &str,
+) -> Result<usize, ParseError> {
+  reader.read_line(line).map_err(|err| {
+    ParseError::new(
+      ParseErrorKind::Io {
+        message: err.to_string(),
+      },
+      read,
+      read.len(),
+      None,
+    )
+  })
+}
+
+/// Whether `line` starts a new top-level block: anything at column 0 other than
+/// blank lines and the `:` that ends a long `? "descriptor"` key
+fn starts_entry(line: &str) -> bool {
+  !line.starts_with([' ', '\t', '\r', '\n', ':'])
+}
+
+/// Whether `line` opens one of the blocks parsed up front rather than an entry
+fn is_preamble_block(line: &str) -> bool {
+  line.starts_with('#')
+    || ["__metadata:", "resolutions:", "constraints:"].contains(&line.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_reader_matches_parse() {
+    let contents = include_str!("../../../fixtures/berry.lock");
+    let lockfile = crate::parse::parse(contents).expect("should parse fixture");
+
+    let mut reader = LockfileReader::new(contents.as_bytes()).expect("should read preamble");
+    assert_eq!(reader.metadata().version, lockfile.metadata.version);
+
+    let mut count = 0;
+    while let Some(entry) = reader.next() {
+      let entry = entry.expect("should parse entry");
+      let expected = &lockfile.entries[count];
+      assert_eq!(
+        entry.descriptors[0].display(reader.interner()).to_string(),
+        expected.descriptors[0]
+          .display(&lockfile.interner)
+          .to_string()
+      );
+      assert_eq!(entry.package.checksum, expected.package.checksum);
+      count += 1;
+    }
+    assert_eq!(count, lockfile.entries.len());
+  }
+
+  #[test]
+  fn test_reader_reports_errors_with_file_positions() {
+    let contents = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"a@npm:1.0.0":
+  version: 1.0.0
+  languageName: node
+  linkType: hard
+
+"b@npm:1.0.0":
+  version: 1.0.0
+  languageName: node
+  linkType: sideways
+
+"c@npm:1.0.0":
+  version: 1.0.0
+  languageName: node
+  linkType: hard
+"#;
+    let results: Vec<_> = LockfileReader::new(contents.as_bytes())
+      .expect("should read preamble")
+      .collect();
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_ok());
+    assert!(results[2].is_ok());
+
+    // Same position as the whole-file parser reports
+    let error = results[1].as_ref().unwrap_err();
+    assert_eq!(
+      Err(error.clone()),
+      crate::parse::parse(contents).map(|_| ())
+    );
+    assert_eq!((error.line, error.column), (16, 3));
+  }
+
+  #[test]
+  fn test_reader_rejects_bad_metadata() {
+    let contents = "# This file is generated by running \"yarn install\" inside your project.\n# Manual changes might be lost - proceed with caution!\n\n__metadata:\n\n\"a@npm:1.0.0\":\n";
+    let error = LockfileReader::new(contents.as_bytes())
+      .err()
+      .expect("metadata is missing");
+    assert_eq!(error.kind, ParseErrorKind::InvalidMetadata);
+  }
+}
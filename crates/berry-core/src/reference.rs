@@ -0,0 +1,351 @@
+//! Structured views of locator references like `npm:1.2.3` or `patch:...`
+//!
+//! Yarn packs a lot into a reference: patches wrap another (URL-encoded)
+//! locator and list their patch files after a `#`, and several protocols bind
+//! extra parameters after `::`, such as the workspace that a relative
+//! `link:` path is relative to. [`ReferenceKind::parse`] splits these apart
+//! without allocating, except where percent-decoding is needed.
+
+use crate::git::GitSpec;
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// What a locator reference points at, see [`crate::locator::Locator::reference_kind`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceKind<'a> {
+  /// A registry package, `npm:1.2.3`
+  Npm { version: &'a str },
+  /// A workspace in this project, `workspace:packages/a`
+  Workspace { path: &'a str },
+  /// A patched package,
+  /// `patch:is-odd@npm%3A3.0.1#~/.yarn/patches/is-odd.patch::version=3.0.1&hash=496de7`
+  Patch {
+    /// The decoded locator being patched, e.g. `is-odd@npm:3.0.1`
+    inner: Cow<'a, str>,
+    /// The patch files, or builtins like `optional!builtin<compat/fsevents>`
+    patches: Vec<&'a str>,
+    /// `version`, `hash` and, for project-relative patches, `locator`
+    params: BindParams<'a>,
+  },
+  /// A git repository pinned to a commit, `https://github.com/a/b.git#commit=abc123`
+  Git(GitSpec<'a>),
+  /// A folder or tarball copied into the cache, `file:./vendor/a`
+  File {
+    path: &'a str,
+    /// The decoded locator `path` is relative to, e.g. `root@workspace:.`
+    parent: Option<Cow<'a, str>>,
+  },
+  /// A folder linked in with its dependencies, `portal:../a`
+  Portal {
+    path: &'a str,
+    parent: Option<Cow<'a, str>>,
+  },
+  /// A folder linked in without its dependencies, `link:../a`
+  Link {
+    path: &'a str,
+    parent: Option<Cow<'a, str>>,
+  },
+  /// A script that generates the package, `exec:./generate.js`
+  Exec {
+    path: &'a str,
+    parent: Option<Cow<'a, str>>,
+  },
+  /// Any other protocol, such as a plain tarball URL
+  Unknown(&'a str),
+}
+
+impl<'a> ReferenceKind<'a> {
+  /// Classify a reference like `npm:1.2.3`, as found after the name in a locator
+  pub fn parse(reference: &'a str) -> Self {
+    if let Some(spec) = GitSpec::parse(reference) {
+      return Self::Git(spec);
+    }
+
+    let parts = RangeParts::parse(reference);
+    let parent = || parts.params.get("locator");
+
+    match parts.protocol {
+      Some("npm") => Self::Npm {
+        // Aliases resolve to `npm:name@version`
+        version: parts
+          .selector
+          .rsplit_once('@')
+          .map_or(parts.selector, |(_, version)| version),
+      },
+      Some("workspace") => Self::Workspace {
+        path: parts.selector,
+      },
+      Some("patch") => Self::Patch {
+        inner: percent_decode(parts.source.unwrap_or_default()),
+        patches: parts.selector.split('&').collect(),
+        params: parts.params,
+      },
+      Some("file") => Self::File {
+        path: parts.selector,
+        parent: parent(),
+      },
+      Some("portal") => Self::Portal {
+        path: parts.selector,
+        parent: parent(),
+      },
+      Some("link") => Self::Link {
+        path: parts.selector,
+        parent: parent(),
+      },
+      Some("exec") => Self::Exec {
+        path: parts.selector,
+        parent: parent(),
+      },
+      _ => Self::Unknown(reference),
+    }
+  }
+}
+
+/// The `::key=value&...` parameters yarn binds to some references
+///
+/// Values are kept exactly as written, so they can be re-encoded losslessly;
+/// [`BindParams::get`] percent-decodes on access.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BindParams<'a> {
+  pairs: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> BindParams<'a> {
+  /// Parse a query string like `version=3.0.1&hash=496de7`
+  pub fn parse(query: &'a str) -> Self {
+    let pairs = query
+      .split('&')
+      .filter(|pair| !pair.is_empty())
+      .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+      .collect();
+    Self { pairs }
+  }
+
+  /// The decoded value of `key`, e.g. `root@workspace:.` for `locator`
+  pub fn get(&self, key: &str) -> Option<Cow<'a, str>> {
+    self.get_raw(key).map(percent_decode)
+  }
+
+  /// The value of `key` as written, e.g. `root%40workspace%3A.`
+  pub fn get_raw(&self, key: &str) -> Option<&'a str> {
+    self
+      .pairs
+      .iter()
+      .find(|(name, _)| *name == key)
+      .map(|(_, value)| *value)
+  }
+
+  /// Every key and value as written, in order
+  pub fn pairs(&self) -> &[(&'a str, &'a str)] {
+    &self.pairs
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.pairs.is_empty()
+  }
+}
+
+/// A range or reference split the way yarn's `parseRange` does:
+/// `protocol:source#selector::params`, where `source` is only present when
+/// there is a `#`
+pub(crate) struct RangeParts<'a> {
+  pub(crate) protocol: Option<&'a str>,
+  pub(crate) source: Option<&'a str>,
+  pub(crate) selector: &'a str,
+  pub(crate) params: BindParams<'a>,
+}
+
+impl<'a> RangeParts<'a> {
+  pub(crate) fn parse(range: &'a str) -> Self {
+    let (protocol, rest) = match range.find([':', '#']) {
+      Some(i) if range.as_bytes()[i] == b':' => (Some(&range[..i]), &range[i + 1..]),
+      _ => (None, range),
+    };
+    let (body, params) = rest.split_once("::").map_or_else(
+      || (rest, BindParams::default()),
+      |(body, query)| (body, BindParams::parse(query)),
+    );
+    let (source, selector) = body
+      .split_once('#')
+      .map_or((None, body), |(source, selector)| (Some(source), selector));
+
+    Self {
+      protocol,
+      source,
+      selector,
+      params,
+    }
+  }
+}
+
+/// Decode `%XX` escapes, as yarn uses `encodeURIComponent` for nested locators
+///
+/// Malformed escapes are left as they are.
+pub fn percent_decode(input: &str) -> Cow<'_, str> {
+  if !input.contains('%') {
+    return Cow::Borrowed(input);
+  }
+
+  let bytes = input.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let escaped = (bytes[i] == b'%')
+      .then(|| input.get(i + 1..i + 3))
+      .flatten()
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    if let Some(byte) = escaped {
+      decoded.push(byte);
+      i += 3;
+    } else {
+      decoded.push(bytes[i]);
+      i += 1;
+    }
+  }
+
+  String::from_utf8(decoded).map_or(Cow::Borrowed(input), Cow::Owned)
+}
+
+/// Escape the characters yarn's `makeRange` escapes in sources and selectors,
+/// so they can't be mistaken for the `:` and `#` separators
+pub(crate) fn encode_unsafe(input: &str) -> Cow<'_, str> {
+  if !input.contains(['%', ':', '#']) {
+    return Cow::Borrowed(input);
+  }
+  Cow::Owned(
+    input
+      .replace('%', "%25")
+      .replace(':', "%3A")
+      .replace('#', "%23"),
+  )
+}
+
+/// Escape a `::` parameter value the way node's `querystring.stringify` does
+pub(crate) fn encode_param(input: &str) -> Cow<'_, str> {
+  let is_unreserved = |byte: u8| byte.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&byte);
+  if input.bytes().all(is_unreserved) {
+    return Cow::Borrowed(input);
+  }
+
+  let mut encoded = String::with_capacity(input.len() * 3);
+  for byte in input.bytes() {
+    if is_unreserved(byte) {
+      encoded.push(byte as char);
+    } else {
+      let _ = write!(encoded, "%{byte:02X}");
+    }
+  }
+  Cow::Owned(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_npm_and_workspace() {
+    assert_eq!(
+      ReferenceKind::parse("npm:4.17.21"),
+      ReferenceKind::Npm { version: "4.17.21" }
+    );
+    assert_eq!(
+      ReferenceKind::parse("npm:string-width@4.2.3"),
+      ReferenceKind::Npm { version: "4.2.3" }
+    );
+    assert_eq!(
+      ReferenceKind::parse("workspace:packages/a"),
+      ReferenceKind::Workspace { path: "packages/a" }
+    );
+  }
+
+  #[test]
+  fn test_patch() {
+    let reference = "patch:lodash@npm%3A4.17.21#./.yarn/patches/lodash.patch::version=4.17.21&hash=2c6e9e&locator=berry-patch%40workspace%3A.";
+    let ReferenceKind::Patch {
+      inner,
+      patches,
+      params,
+    } = ReferenceKind::parse(reference)
+    else {
+      panic!("should be a patch");
+    };
+    assert_eq!(inner, "lodash@npm:4.17.21");
+    assert_eq!(patches, ["./.yarn/patches/lodash.patch"]);
+    assert_eq!(params.get("version").as_deref(), Some("4.17.21"));
+    assert_eq!(params.get("hash").as_deref(), Some("2c6e9e"));
+    assert_eq!(
+      params.get("locator").as_deref(),
+      Some("berry-patch@workspace:.")
+    );
+    assert_eq!(
+      params.get_raw("locator"),
+      Some("berry-patch%40workspace%3A.")
+    );
+
+    let ReferenceKind::Patch { patches, .. } = ReferenceKind::parse(
+      "patch:fsevents@npm%3A2.3.3#optional!builtin<compat/fsevents>::version=2.3.3&hash=df0bf1",
+    ) else {
+      panic!("should be a patch");
+    };
+    assert_eq!(patches, ["optional!builtin<compat/fsevents>"]);
+  }
+
+  #[test]
+  fn test_git() {
+    let ReferenceKind::Git(spec) = ReferenceKind::parse(
+      "https://github.com/charsleysa/postgres.git#commit=3b1a01b2da3e2fafb1a79006f838eff11a8de3cb",
+    ) else {
+      panic!("should be a git reference");
+    };
+    assert_eq!(spec.url, "https://github.com/charsleysa/postgres.git");
+    assert_eq!(
+      spec.commit(),
+      Some("3b1a01b2da3e2fafb1a79006f838eff11a8de3cb")
+    );
+  }
+
+  #[test]
+  fn test_paths_with_parent() {
+    assert_eq!(
+      ReferenceKind::parse(
+        "link:tests/eslint-plugin::locator=stars-web-client%40workspace%3Aapps%2Fps%2Fweb-client"
+      ),
+      ReferenceKind::Link {
+        path: "tests/eslint-plugin",
+        parent: Some(Cow::Borrowed(
+          "stars-web-client@workspace:apps/ps/web-client"
+        )),
+      }
+    );
+    assert_eq!(
+      ReferenceKind::parse("portal:../a"),
+      ReferenceKind::Portal {
+        path: "../a",
+        parent: None,
+      }
+    );
+    assert!(matches!(
+      ReferenceKind::parse("exec:./gen.js::locator=root%40workspace%3A."),
+      ReferenceKind::Exec { path: "./gen.js", parent: Some(parent) } if parent == "root@workspace:."
+    ));
+    assert_eq!(
+      ReferenceKind::parse("https://example.com/a.tgz"),
+      ReferenceKind::Unknown("https://example.com/a.tgz")
+    );
+  }
+
+  #[test]
+  fn test_percent_decode() {
+    assert_eq!(percent_decode("a%40b%3Ac"), "a@b:c");
+    assert!(matches!(percent_decode("plain"), Cow::Borrowed("plain")));
+    assert_eq!(percent_decode("100%"), "100%");
+    assert_eq!(percent_decode("%zz"), "%zz");
+
+    assert_eq!(encode_unsafe("is-odd@npm:3.0.1"), "is-odd@npm%3A3.0.1");
+    assert_eq!(
+      encode_param("web@workspace:apps/web"),
+      "web%40workspace%3Aapps%2Fweb"
+    );
+    assert!(matches!(encode_param("3.0.1"), Cow::Borrowed("3.0.1")));
+  }
+}
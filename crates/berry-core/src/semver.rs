@@ -0,0 +1,631 @@
+//! Semver versions and npm ranges, following node-semver as used by yarn
+//!
+//! Ranges are desugared into sets of primitive comparators the same way
+//! node-semver does, so `^1.2.3` becomes `>=1.2.3 <2.0.0-0`. Prerelease
+//! versions only satisfy a range when one of its comparators has a prerelease
+//! on the same `major.minor.patch`, which is why `1.3.0-beta.1` doesn't match
+//! `^1.2.3` but `1.2.4-beta.1` does match `^1.2.4-beta.0`.
+//!
+//! Dist-tags like `latest` or `next` can only be resolved against the
+//! registry, so they fail to parse as a [`VersionRange`].
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// An error produced when a version or range can't be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemverError {
+  /// Not a `major.minor.patch` version, e.g. `1.2` or `latest`
+  InvalidVersion(String),
+  /// Not a semver range, e.g. a dist-tag like `latest`
+  InvalidRange(String),
+}
+
+impl fmt::Display for SemverError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidVersion(version) => write!(f, "invalid semver version `{version}`"),
+      Self::InvalidRange(range) => write!(f, "invalid semver range `{range}`"),
+    }
+  }
+}
+
+impl std::error::Error for SemverError {}
+
+/// A dot-separated part of a prerelease, e.g. `beta` or `1` in `1.0.0-beta.1`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Prerelease {
+  Numeric(u64),
+  Alphanumeric(String),
+}
+
+impl Prerelease {
+  fn parse(part: &str) -> Option<Self> {
+    if part.is_empty() || !part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+      return None;
+    }
+    Some(parse_number(part).map_or_else(|| Self::Alphanumeric(part.to_string()), Self::Numeric))
+  }
+}
+
+impl Ord for Prerelease {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (Self::Numeric(a), Self::Numeric(b)) => a.cmp(b),
+      (Self::Numeric(_), Self::Alphanumeric(_)) => Ordering::Less,
+      (Self::Alphanumeric(_), Self::Numeric(_)) => Ordering::Greater,
+      (Self::Alphanumeric(a), Self::Alphanumeric(b)) => a.cmp(b),
+    }
+  }
+}
+
+impl PartialOrd for Prerelease {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl fmt::Display for Prerelease {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Numeric(number) => write!(f, "{number}"),
+      Self::Alphanumeric(part) => f.write_str(part),
+    }
+  }
+}
+
+/// A semver version like `1.2.3-beta.1+build.5`
+///
+/// Build metadata is kept for display but, as the spec requires, ignored when
+/// comparing versions.
+#[derive(Debug, Clone)]
+pub struct Version {
+  pub major: u64,
+  pub minor: u64,
+  pub patch: u64,
+  pub prerelease: Vec<Prerelease>,
+  pub build: Vec<String>,
+}
+
+impl Version {
+  pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+    Self {
+      major,
+      minor,
+      patch,
+      prerelease: Vec::new(),
+      build: Vec::new(),
+    }
+  }
+
+  /// Parse a version, allowing the leading `v` or `=` that npm tolerates
+  pub fn parse(input: &str) -> Result<Self, SemverError> {
+    let error = || SemverError::InvalidVersion(input.to_string());
+    let partial = Partial::parse(input.trim()).ok_or_else(error)?;
+    partial.into_version().ok_or_else(error)
+  }
+
+  fn is_prerelease(&self) -> bool {
+    !self.prerelease.is_empty()
+  }
+
+  fn same_release(&self, other: &Self) -> bool {
+    (self.major, self.minor, self.patch) == (other.major, other.minor, other.patch)
+  }
+
+  /// The lowest prerelease of a version, used for exclusive upper bounds like `<2.0.0-0`
+  fn lowest(major: u64, minor: u64, patch: u64) -> Self {
+    Self {
+      prerelease: vec![Prerelease::Numeric(0)],
+      ..Self::new(major, minor, patch)
+    }
+  }
+}
+
+impl FromStr for Version {
+  type Err = SemverError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::parse(s)
+  }
+}
+
+impl PartialEq for Version {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Version {}
+
+impl Hash for Version {
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    (self.major, self.minor, self.patch, &self.prerelease).hash(state);
+  }
+}
+
+impl Ord for Version {
+  fn cmp(&self, other: &Self) -> Ordering {
+    (self.major, self.minor, self.patch)
+      .cmp(&(other.major, other.minor, other.patch))
+      .then_with(|| {
+        // A release sorts after all of its prereleases
+        match (self.is_prerelease(), other.is_prerelease()) {
+          (false, false) => Ordering::Equal,
+          (false, true) => Ordering::Greater,
+          (true, false) => Ordering::Less,
+          (true, true) => self.prerelease.cmp(&other.prerelease),
+        }
+      })
+  }
+}
+
+impl PartialOrd for Version {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl fmt::Display for Version {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+    for (i, part) in self.prerelease.iter().enumerate() {
+      f.write_str(if i == 0 { "-" } else { "." })?;
+      write!(f, "{part}")?;
+    }
+    if !self.build.is_empty() {
+      write!(f, "+{}", self.build.join("."))?;
+    }
+    Ok(())
+  }
+}
+
+/// Serialized as the version string, e.g. `"1.2.3-beta.1"`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Version {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+/// How a [`Comparator`] compares a version against its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+  Eq,
+  Gt,
+  Gte,
+  Lt,
+  Lte,
+}
+
+/// A primitive comparison like `>=1.2.3`, as ranges are desugared into
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Comparator {
+  pub op: Op,
+  pub version: Version,
+}
+
+impl Comparator {
+  fn new(op: Op, version: Version) -> Self {
+    Self { op, version }
+  }
+
+  pub fn matches(&self, version: &Version) -> bool {
+    let ordering = version.cmp(&self.version);
+    match self.op {
+      Op::Eq => ordering == Ordering::Equal,
+      Op::Gt => ordering == Ordering::Greater,
+      Op::Gte => ordering != Ordering::Less,
+      Op::Lt => ordering == Ordering::Less,
+      Op::Lte => ordering != Ordering::Greater,
+    }
+  }
+}
+
+impl fmt::Display for Comparator {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let op = match self.op {
+      Op::Eq => "",
+      Op::Gt => ">",
+      Op::Gte => ">=",
+      Op::Lt => "<",
+      Op::Lte => "<=",
+    };
+    write!(f, "{op}{}", self.version)
+  }
+}
+
+/// An npm range like `^1.2.3 || >=2.0.0 <3`
+///
+/// Stored as alternatives (split on `||`), each a set of comparators that must
+/// all match. An empty set matches every release.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VersionRange {
+  sets: Vec<Vec<Comparator>>,
+}
+
+impl VersionRange {
+  /// Parse an npm range, failing for dist-tags and anything else that isn't semver
+  pub fn parse(input: &str) -> Result<Self, SemverError> {
+    let sets = input
+      .split("||")
+      .map(parse_comparator_set)
+      .collect::<Option<Vec<_>>>()
+      .ok_or_else(|| SemverError::InvalidRange(input.to_string()))?;
+    Ok(Self { sets })
+  }
+
+  /// The alternatives of the range, each desugared into primitive comparators
+  pub fn comparator_sets(&self) -> &[Vec<Comparator>] {
+    &self.sets
+  }
+
+  /// Whether `version` satisfies the range, with node-semver's prerelease rule
+  pub fn matches(&self, version: &Version) -> bool {
+    self.sets.iter().any(|set| {
+      if !set.iter().all(|comparator| comparator.matches(version)) {
+        return false;
+      }
+
+      // A prerelease is only allowed in if the range opts into prereleases of
+      // that exact release, so `^1.0.0` doesn't pick up `1.5.0-beta`
+      !version.is_prerelease()
+        || set.iter().any(|comparator| {
+          comparator.version.is_prerelease() && comparator.version.same_release(version)
+        })
+    })
+  }
+}
+
+impl FromStr for VersionRange {
+  type Err = SemverError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::parse(s)
+  }
+}
+
+/// Renders the desugared form, e.g. `>=1.2.3 <2.0.0-0`
+impl fmt::Display for VersionRange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, set) in self.sets.iter().enumerate() {
+      if i > 0 {
+        f.write_str(" || ")?;
+      }
+      if set.is_empty() {
+        f.write_str("*")?;
+      }
+      for (j, comparator) in set.iter().enumerate() {
+        if j > 0 {
+          f.write_str(" ")?;
+        }
+        write!(f, "{comparator}")?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// A version that may be missing trailing parts or use `x`/`*` wildcards, as
+/// ranges allow, e.g. `1.2` or `1.x`
+struct Partial {
+  major: Option<u64>,
+  minor: Option<u64>,
+  patch: Option<u64>,
+  prerelease: Vec<Prerelease>,
+  build: Vec<String>,
+}
+
+impl Partial {
+  fn parse(input: &str) -> Option<Self> {
+    let input = input.trim_start_matches(['v', '=']).trim_start();
+    let (input, build) = match input.split_once('+') {
+      Some((input, build)) => (input, build.split('.').map(str::to_string).collect()),
+      None => (input, Vec::new()),
+    };
+    let (core, prerelease) = match input.split_once('-') {
+      Some((core, prerelease)) => (
+        core,
+        prerelease
+          .split('.')
+          .map(Prerelease::parse)
+          .collect::<Option<Vec<_>>>()?,
+      ),
+      None => (input, Vec::new()),
+    };
+
+    let mut parts = core.split('.');
+    let mut next = || match parts.next() {
+      None | Some("x" | "X" | "*") => Some(None),
+      Some(part) => parse_number(part).map(Some),
+    };
+    let (major, minor, patch) = (next()?, next()?, next()?);
+    if parts.next().is_some() {
+      return None;
+    }
+    // Nothing can follow a wildcard, and only full versions have prereleases
+    if major.is_none() && minor.is_some()
+      || minor.is_none() && patch.is_some()
+      || patch.is_none() && !prerelease.is_empty()
+    {
+      return None;
+    }
+
+    Some(Self {
+      major,
+      minor,
+      patch,
+      prerelease,
+      build,
+    })
+  }
+
+  fn into_version(self) -> Option<Version> {
+    Some(Version {
+      major: self.major?,
+      minor: self.minor?,
+      patch: self.patch?,
+      prerelease: self.prerelease,
+      build: self.build,
+    })
+  }
+
+  /// The lowest version the partial covers, filling missing parts with zeros
+  fn floor(&self) -> Version {
+    Version {
+      prerelease: self.prerelease.clone(),
+      ..Version::new(
+        self.major.unwrap_or(0),
+        self.minor.unwrap_or(0),
+        self.patch.unwrap_or(0),
+      )
+    }
+  }
+
+  /// The lowest version above everything the partial covers, e.g. `<1.3.0-0` for `1.2`
+  ///
+  /// Only meaningful when the partial has a major version and is missing a part.
+  fn ceiling(&self) -> Version {
+    match (self.major.unwrap_or(0), self.minor) {
+      (major, None) => Version::lowest(major + 1, 0, 0),
+      (major, Some(minor)) => Version::lowest(major, minor + 1, 0),
+    }
+  }
+
+  fn is_full(&self) -> bool {
+    self.patch.is_some()
+  }
+}
+
+/// Parse a numeric identifier, which npm doesn't allow to have leading zeros
+fn parse_number(part: &str) -> Option<u64> {
+  if part.is_empty()
+    || !part.bytes().all(|b| b.is_ascii_digit())
+    || part.len() > 1 && part.starts_with('0')
+  {
+    return None;
+  }
+  part.parse().ok()
+}
+
+/// Parse one `||` alternative into the comparators it desugars to
+fn parse_comparator_set(input: &str) -> Option<Vec<Comparator>> {
+  let input = input.trim();
+  let mut set = Vec::new();
+
+  // Hyphen ranges need spaces around the `-`, as prereleases also use it
+  if let Some((lower, upper)) = input.split_once(" - ") {
+    let (lower, upper) = (Partial::parse(lower.trim())?, Partial::parse(upper.trim())?);
+    if lower.major.is_some() {
+      set.push(Comparator::new(Op::Gte, lower.floor()));
+    }
+    if upper.is_full() {
+      set.push(Comparator::new(Op::Lte, upper.floor()));
+    } else if upper.major.is_some() {
+      set.push(Comparator::new(Op::Lt, upper.ceiling()));
+    }
+    return Some(set);
+  }
+
+  let mut tokens = input.split_whitespace();
+  while let Some(token) = tokens.next() {
+    // Allow whitespace between an operator and its version, e.g. `>= 1.2.3`
+    let joined;
+    let token = if token.chars().all(|c| "<>=~^".contains(c)) {
+      joined = format!("{token}{}", tokens.next()?);
+      joined.as_str()
+    } else {
+      token
+    };
+    desugar(token, &mut set)?;
+  }
+
+  Some(set)
+}
+
+/// Desugar a single range token like `^1.2` or `>=1.0.0` into primitive comparators
+fn desugar(token: &str, set: &mut Vec<Comparator>) -> Option<()> {
+  let operators = ["<=", ">=", "~>", "<", ">", "=", "^", "~"];
+  let op = operators
+    .iter()
+    .find(|op| token.starts_with(*op))
+    .copied()
+    .unwrap_or("");
+  let partial = Partial::parse(&token[op.len()..])?;
+
+  let Some(major) = partial.major else {
+    // `*` and friends match every release, and `<*` or `>*` match nothing
+    if matches!(op, "<" | ">") {
+      set.push(Comparator::new(Op::Lt, Version::lowest(0, 0, 0)));
+    }
+    return Some(());
+  };
+  let floor = partial.floor();
+
+  match op {
+    "^" => {
+      // Everything up to the next change in the left-most non-zero part
+      let ceiling = match partial.minor {
+        Some(0) if major == 0 && partial.is_full() => Version::lowest(0, 0, floor.patch + 1),
+        Some(minor) if major == 0 => Version::lowest(0, minor + 1, 0),
+        _ => Version::lowest(major + 1, 0, 0),
+      };
+      set.push(Comparator::new(Op::Gte, floor));
+      set.push(Comparator::new(Op::Lt, ceiling));
+    }
+    "~" | "~>" => {
+      let ceiling = partial.minor.map_or_else(
+        || Version::lowest(major + 1, 0, 0),
+        |minor| Version::lowest(major, minor + 1, 0),
+      );
+      set.push(Comparator::new(Op::Gte, floor));
+      set.push(Comparator::new(Op::Lt, ceiling));
+    }
+    "" | "=" if partial.is_full() => set.push(Comparator::new(Op::Eq, floor)),
+    "" | "=" => {
+      set.push(Comparator::new(Op::Gte, floor));
+      set.push(Comparator::new(Op::Lt, partial.ceiling()));
+    }
+    ">" if partial.is_full() => set.push(Comparator::new(Op::Gt, floor)),
+    ">" => {
+      // `>1.2` is everything from the next minor
+      let mut next = partial.ceiling();
+      next.prerelease.clear();
+      set.push(Comparator::new(Op::Gte, next));
+    }
+    ">=" => set.push(Comparator::new(Op::Gte, floor)),
+    "<" if partial.is_full() => set.push(Comparator::new(Op::Lt, floor)),
+    "<" => set.push(Comparator::new(
+      Op::Lt,
+      Version::lowest(major, floor.minor, 0),
+    )),
+    "<=" if partial.is_full() => set.push(Comparator::new(Op::Lte, floor)),
+    "<=" => set.push(Comparator::new(Op::Lt, partial.ceiling())),
+    _ => unreachable!("operator list is exhaustive"),
+  }
+
+  Some(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn satisfies(range: &str, version: &str) -> bool {
+    VersionRange::parse(range)
+      .unwrap_or_else(|e| panic!("{e}"))
+      .matches(&Version::parse(version).unwrap_or_else(|e| panic!("{e}")))
+  }
+
+  #[test]
+  fn test_version_parse_and_order() {
+    let version = Version::parse("v1.2.3-beta.10+build.5").unwrap();
+    assert_eq!((version.major, version.minor, version.patch), (1, 2, 3));
+    assert_eq!(
+      version.prerelease,
+      [
+        Prerelease::Alphanumeric("beta".into()),
+        Prerelease::Numeric(10)
+      ]
+    );
+    assert_eq!(version.to_string(), "1.2.3-beta.10+build.5");
+
+    for invalid in ["1.2", "latest", "01.2.3", "1.2.3-", "1.2.3.4", ""] {
+      assert!(
+        Version::parse(invalid).is_err(),
+        "{invalid} should not parse"
+      );
+    }
+
+    // Ordering from the semver spec
+    let ordered = [
+      "1.0.0-alpha",
+      "1.0.0-alpha.1",
+      "1.0.0-alpha.beta",
+      "1.0.0-beta",
+      "1.0.0-beta.2",
+      "1.0.0-beta.11",
+      "1.0.0-rc.1",
+      "1.0.0",
+      "1.0.1",
+      "1.1.0",
+      "2.0.0",
+    ];
+    for pair in ordered.windows(2) {
+      assert!(
+        Version::parse(pair[0]).unwrap() < Version::parse(pair[1]).unwrap(),
+        "{} < {}",
+        pair[0],
+        pair[1]
+      );
+    }
+    assert_eq!(
+      Version::parse("1.0.0+a").unwrap(),
+      Version::parse("1.0.0+b").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_range_desugaring() {
+    for (range, desugared) in [
+      ("^1.2.3", ">=1.2.3 <2.0.0-0"),
+      ("^0.2.3", ">=0.2.3 <0.3.0-0"),
+      ("^0.0.3", ">=0.0.3 <0.0.4-0"),
+      ("^1.x", ">=1.0.0 <2.0.0-0"),
+      ("^0.0", ">=0.0.0 <0.1.0-0"),
+      ("~1.2.3", ">=1.2.3 <1.3.0-0"),
+      ("~1", ">=1.0.0 <2.0.0-0"),
+      ("1.2", ">=1.2.0 <1.3.0-0"),
+      ("1.2.3 - 2.3", ">=1.2.3 <2.4.0-0"),
+      (">1.2", ">=1.3.0"),
+      ("<1.2", "<1.2.0-0"),
+      ("<=1.2", "<1.3.0-0"),
+      (">= 1.0.0 < 2", ">=1.0.0 <2.0.0-0"),
+      ("*", "*"),
+      ("1 || 2", ">=1.0.0 <2.0.0-0 || >=2.0.0 <3.0.0-0"),
+    ] {
+      assert_eq!(
+        VersionRange::parse(range).unwrap().to_string(),
+        desugared,
+        "{range}"
+      );
+    }
+
+    for tag in ["latest", "next", "beta", "^latest"] {
+      assert!(VersionRange::parse(tag).is_err(), "{tag} should not parse");
+    }
+  }
+
+  #[test]
+  fn test_range_matches() {
+    for (range, version, expected) in [
+      ("^1.2.3", "1.9.9", true),
+      ("^1.2.3", "2.0.0", false),
+      ("^1.2.3", "1.2.2", false),
+      ("^0.2.3", "0.2.9", true),
+      ("^0.2.3", "0.3.0", false),
+      ("~1.2.3", "1.2.9", true),
+      ("~1.2.3", "1.3.0", false),
+      ("1.x", "1.5.0", true),
+      ("*", "3.0.0", true),
+      ("", "3.0.0", true),
+      ("1.2.3 - 2.3.4", "2.3.4", true),
+      ("1.2.3 - 2.3.4", "2.3.5", false),
+      ("<1.0.0 || >=2.0.0", "1.5.0", false),
+      ("<1.0.0 || >=2.0.0", "2.5.0", true),
+      ("=1.2.3", "1.2.3", true),
+      ("v1.2.3", "1.2.3", true),
+      // Prereleases only match ranges that mention that release
+      ("^1.2.3", "1.3.0-beta.1", false),
+      ("^1.2.4-beta.0", "1.2.4-beta.1", true),
+      ("^1.2.4-beta.0", "1.3.0-beta.1", false),
+      ("*", "1.0.0-beta", false),
+      ("<2.0.0", "2.0.0-beta", false),
+      (">=1.0.0-rc.1", "1.0.0-rc.2", true),
+    ] {
+      assert_eq!(satisfies(range, version), expected, "{version} in {range}");
+    }
+  }
+}
@@ -0,0 +1,537 @@
+//! Serialization of a [`Lockfile`] back into yarn.lock text.
+//!
+//! This mirrors yarn's `stringifySyml` closely enough that parsing a lockfile
+//! and writing it back out yields the same bytes:
+//!
+//! - `__metadata` comes first, followed by entries in their stored order
+//! - entry fields use yarn's priority order (`version`, `resolution`,
+//!   `dependencies`, `peerDependencies`, `dependenciesMeta`,
+//!   `peerDependenciesMeta`), then the remaining keys alphabetically
+//! - nested maps are sorted by key
+//! - scalars are only quoted when they can't be written as plain YAML
+//!
+//! <https://github.com/yarnpkg/berry/blob/master/packages/yarnpkg-parsers/sources/syml.ts>
+
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+
+use crate::intern::Interner;
+use crate::lockfile::{Entry, Lockfile, Metadata};
+use crate::package::{ExtraProperty, Package};
+
+/// The comment header yarn writes at the top of every lockfile
+pub const LOCKFILE_HEADER: &str = "# This file is generated by running \"yarn install\" inside your project.\n# Manual changes might be lost - proceed with caution!\n";
+
+/// Keys longer than this are written in the explicit `? key\n:` form
+const MAX_SIMPLE_KEY_LENGTH: usize = 1024;
+
+/// Write a whole lockfile, header included
+pub(crate) fn write_lockfile<W: Write>(out: &mut W, lockfile: &Lockfile) -> fmt::Result {
+  out.write_str(LOCKFILE_HEADER)?;
+  out.write_char('\n')?;
+  write_metadata(out, &lockfile.metadata)?;
+
+  if let Some(resolutions) = &lockfile.resolutions {
+    out.write_char('\n')?;
+    write_top_level_kv_block(out, "resolutions", resolutions)?;
+  }
+
+  if let Some(constraints) = &lockfile.constraints {
+    out.write_char('\n')?;
+    write_top_level_kv_block(out, "constraints", constraints)?;
+  }
+
+  for entry in &lockfile.entries {
+    out.write_char('\n')?;
+    write_entry(out, entry, &lockfile.interner)?;
+  }
+
+  Ok(())
+}
+
+/// Write the `__metadata` block
+fn write_metadata<W: Write>(out: &mut W, metadata: &Metadata) -> fmt::Result {
+  out.write_str("__metadata:\n")?;
+  write_scalar_field(out, 1, "version", &metadata.version)?;
+  write_scalar_field(out, 1, "cacheKey", &metadata.cache_key)
+}
+
+/// Write a `resolutions:` / `constraints:` block
+fn write_top_level_kv_block<W: Write>(
+  out: &mut W,
+  header: &str,
+  pairs: &[(String, String)],
+) -> fmt::Result {
+  writeln!(out, "{header}:")?;
+  for (key, value) in pairs {
+    write_scalar_field(out, 1, key, value)?;
+  }
+  Ok(())
+}
+
+/// Write a single entry: the descriptor line followed by the package fields
+pub(crate) fn write_entry<W: Write>(
+  out: &mut W,
+  entry: &Entry,
+  interner: &Interner,
+) -> fmt::Result {
+  let mut key = String::new();
+  for (i, descriptor) in entry.descriptors.iter().enumerate() {
+    if i > 0 {
+      key.push_str(", ");
+    }
+    write!(key, "{}", descriptor.display(interner))?;
+  }
+
+  write_keyed_package(out, &key, &entry.package, interner)
+}
+
+/// Write a package under a descriptor line given as text, e.g. when the
+/// descriptors were collected from several lockfiles
+pub(crate) fn write_keyed_package<W: Write>(
+  out: &mut W,
+  key: &str,
+  package: &Package,
+  interner: &Interner,
+) -> fmt::Result {
+  let key = stringify_string(key);
+  if key.len() > MAX_SIMPLE_KEY_LENGTH {
+    writeln!(out, "? {key}\n:")?;
+  } else {
+    writeln!(out, "{key}:")?;
+  }
+
+  write_package(out, package, interner)
+}
+
+/// Write the fields of a package at one level of indentation
+pub(crate) fn write_package<W: Write>(
+  out: &mut W,
+  package: &Package,
+  interner: &Interner,
+) -> fmt::Result {
+  if let Some(version) = &package.version {
+    write_scalar_field(out, 1, "version", version)?;
+  }
+  if let Some(resolution) = &package.resolution {
+    write_scalar_field(out, 1, "resolution", resolution)?;
+  }
+
+  write_sorted_block(
+    out,
+    "dependencies",
+    package
+      .dependencies
+      .iter()
+      .map(|(ident, descriptor)| (ident.display(interner).to_string(), descriptor.range())),
+    |out, value| write_scalar(out, value),
+  )?;
+
+  write_sorted_block(
+    out,
+    "peerDependencies",
+    package
+      .peer_dependencies
+      .iter()
+      .map(|(ident, descriptor)| (ident.display(interner).to_string(), descriptor.range())),
+    |out, value| write_scalar(out, value),
+  )?;
+
+  write_sorted_block(
+    out,
+    "dependenciesMeta",
+    package
+      .dependencies_meta
+      .iter()
+      .filter_map(|(ident, meta)| {
+        meta
+          .as_ref()
+          .map(|meta| (ident.display(interner).to_string(), meta))
+      }),
+    |out, meta| {
+      out.write_char('\n')?;
+      for (name, value) in [
+        ("built", meta.built),
+        ("optional", meta.optional),
+        ("unplugged", meta.unplugged),
+      ] {
+        if let Some(value) = value {
+          write_indent(out, 3)?;
+          writeln!(out, "{name}: {value}")?;
+        }
+      }
+      Ok(())
+    },
+  )?;
+
+  write_sorted_block(
+    out,
+    "peerDependenciesMeta",
+    package
+      .peer_dependencies_meta
+      .iter()
+      .map(|(ident, meta)| (ident.display(interner).to_string(), meta)),
+    |out, meta| {
+      out.write_char('\n')?;
+      write_indent(out, 3)?;
+      writeln!(out, "optional: {}", meta.optional)
+    },
+  )?;
+
+  write_package_tail(out, package, interner)
+}
+
+/// Write the fields after the priority keys. These, including any we don't
+/// model, are sorted alphabetically.
+fn write_package_tail<W: Write>(
+  out: &mut W,
+  package: &Package,
+  interner: &Interner,
+) -> fmt::Result {
+  let link_type = package.link_type.as_str();
+  let mut tail: Vec<(&str, TailField<'_>)> = vec![
+    ("bin", TailField::Bin),
+    (
+      "languageName",
+      TailField::Scalar(package.language_name.as_str(interner)),
+    ),
+    ("linkType", TailField::Scalar(link_type)),
+  ];
+  if let Some(checksum) = &package.checksum {
+    tail.push(("checksum", TailField::Scalar(checksum)));
+  }
+  if let Some(conditions) = &package.conditions {
+    tail.push(("conditions", TailField::Scalar(conditions)));
+  }
+  for (key, value) in &package.extra {
+    let field = match value {
+      ExtraProperty::Scalar(value) => TailField::Scalar(value),
+      ExtraProperty::Block(body) => TailField::Block(body),
+    };
+    tail.push((key, field));
+  }
+  tail.sort_unstable_by_key(|(key, _)| *key);
+
+  for (key, field) in tail {
+    match field {
+      TailField::Bin => write_sorted_block(
+        out,
+        key,
+        package
+          .bin
+          .iter()
+          .map(|(name, path)| (name.clone(), path.as_str())),
+        |out, value| write_scalar(out, value),
+      )?,
+      TailField::Scalar(value) => write_scalar_field(out, 1, key, value)?,
+      TailField::Block(body) => {
+        write_indent(out, 1)?;
+        writeln!(out, "{}:", stringify_string(key))?;
+        out.write_str(body)?;
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// A package field written after the priority keys
+enum TailField<'a> {
+  Bin,
+  Scalar(&'a str),
+  Block(&'a str),
+}
+
+/// Write a nested block whose children are sorted by key. Nothing is written
+/// for an empty map, matching yarn which drops empty objects.
+fn write_sorted_block<W, V, I, F>(
+  out: &mut W,
+  header: &str,
+  items: I,
+  write_value: F,
+) -> fmt::Result
+where
+  W: Write,
+  I: Iterator<Item = (String, V)>,
+  F: Fn(&mut W, V) -> fmt::Result,
+{
+  let mut items: Vec<(String, V)> = items.collect();
+  if items.is_empty() {
+    return Ok(());
+  }
+  items.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+  write_indent(out, 1)?;
+  writeln!(out, "{header}:")?;
+  for (key, value) in items {
+    write_indent(out, 2)?;
+    write!(out, "{}:", stringify_string(&key))?;
+    write_value(out, value)?;
+  }
+  Ok(())
+}
+
+/// Write ` value\n`, the tail of a `key: value` line
+fn write_scalar<W: Write>(out: &mut W, value: &str) -> fmt::Result {
+  writeln!(out, " {}", stringify_string(value))
+}
+
+/// Write an indented `key: value` line
+fn write_scalar_field<W: Write>(out: &mut W, depth: usize, key: &str, value: &str) -> fmt::Result {
+  write_indent(out, depth)?;
+  write!(out, "{}:", stringify_string(key))?;
+  write_scalar(out, value)
+}
+
+fn write_indent<W: Write>(out: &mut W, depth: usize) -> fmt::Result {
+  for _ in 0..depth {
+    out.write_str("  ")?;
+  }
+  Ok(())
+}
+
+/// Returns true if yarn would write `value` without quotes.
+///
+/// This is the same check as syml's simple string pattern:
+///
+/// ```text
+/// /^(?![-?:,\][{}#&*!|>'"%@` \t\r\n]).([ \t]*(?![,\][{}:# \t\r\n]).)*$/
+/// ```
+fn is_plain_scalar(value: &str) -> bool {
+  let mut chars = value.chars();
+  let Some(first) = chars.next() else {
+    return false;
+  };
+  if "-?:,][{}#&*!|>'\"%@` \t\r\n".contains(first) {
+    return false;
+  }
+
+  let rest = chars.as_str();
+  !rest.ends_with([' ', '\t']) && !rest.contains([',', ']', '[', '{', '}', ':', '#', '\r', '\n'])
+}
+
+/// Quote a scalar the way yarn does: plain if possible, otherwise as a JSON string
+pub(crate) fn stringify_string(value: &str) -> Cow<'_, str> {
+  if is_plain_scalar(value) {
+    return Cow::Borrowed(value);
+  }
+
+  let mut quoted = String::with_capacity(value.len() + 2);
+  quoted.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => quoted.push_str("\\\""),
+      '\\' => quoted.push_str("\\\\"),
+      '\n' => quoted.push_str("\\n"),
+      '\r' => quoted.push_str("\\r"),
+      '\t' => quoted.push_str("\\t"),
+      '\u{08}' => quoted.push_str("\\b"),
+      '\u{0c}' => quoted.push_str("\\f"),
+      c if c < ' ' => {
+        let _ = write!(quoted, "\\u{:04x}", u32::from(c));
+      }
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('"');
+  Cow::Owned(quoted)
+}
+
+/// Undo [`stringify_string`]: strip one pair of quotes and unescape what's
+/// inside, or return a plain scalar as it is
+pub(crate) fn unquote_string(value: &str) -> Cow<'_, str> {
+  let Some(inner) = value
+    .strip_prefix('"')
+    .and_then(|value| value.strip_suffix('"'))
+  else {
+    return Cow::Borrowed(value);
+  };
+  if !inner.contains('\\') {
+    return Cow::Borrowed(inner);
+  }
+
+  let mut unquoted = String::with_capacity(inner.len());
+  let mut chars = inner.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      unquoted.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('n') => unquoted.push('\n'),
+      Some('r') => unquoted.push('\r'),
+      Some('t') => unquoted.push('\t'),
+      Some('b') => unquoted.push('\u{08}'),
+      Some('f') => unquoted.push('\u{0c}'),
+      Some('u') => {
+        let hex: String = chars.by_ref().take(4).collect();
+        if let Some(c) = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+          unquoted.push(c);
+        } else {
+          unquoted.push_str("\\u");
+          unquoted.push_str(&hex);
+        }
+      }
+      // `\"`, `\\` and `\/`
+      Some(c) => unquoted.push(c),
+      None => unquoted.push('\\'),
+    }
+  }
+  Cow::Owned(unquoted)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parse_lockfile;
+
+  #[test]
+  fn test_stringify_plain_scalars() {
+    assert_eq!(stringify_string("1.0.0"), "1.0.0");
+    assert_eq!(stringify_string("^4.17.21"), "^4.17.21");
+    assert_eq!(stringify_string("0.0.0-use.local"), "0.0.0-use.local");
+    assert_eq!(
+      stringify_string("os=linux & cpu=x64 & libc=glibc"),
+      "os=linux & cpu=x64 & libc=glibc"
+    );
+    assert_eq!(
+      stringify_string("eslint-config-turbo@latest"),
+      "eslint-config-turbo@latest"
+    );
+  }
+
+  #[test]
+  fn test_stringify_quoted_scalars() {
+    assert_eq!(stringify_string("*"), "\"*\"");
+    assert_eq!(stringify_string("npm:^1.0.0"), "\"npm:^1.0.0\"");
+    assert_eq!(stringify_string("@babel/core"), "\"@babel/core\"");
+    assert_eq!(stringify_string(">=3.3.1"), "\">=3.3.1\"");
+    assert_eq!(stringify_string("a, b"), "\"a, b\"");
+    assert_eq!(stringify_string("trailing "), "\"trailing \"");
+    assert_eq!(stringify_string(""), "\"\"");
+    assert_eq!(stringify_string("\"hi\" there"), "\"\\\"hi\\\" there\"");
+    assert_eq!(stringify_string("say \"hi\""), "say \"hi\"");
+  }
+
+  #[test]
+  fn test_roundtrip_small_lockfile() {
+    let input = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"@scope/a@npm:^1.0.0, @scope/a@npm:^1.1.0":
+  version: 1.1.0
+  resolution: "@scope/a@npm:1.1.0"
+  dependencies:
+    "@scope/b": "npm:*"
+    debug: "npm:^4.0.0"
+  peerDependencies:
+    react: "npm:>=17"
+  dependenciesMeta:
+    fsevents:
+      built: false
+      optional: true
+  peerDependenciesMeta:
+    react:
+      optional: true
+  bin:
+    a-cli: bin/cli.js
+  checksum: 10c0/abcdef
+  conditions: os=darwin
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  languageName: unknown
+  linkType: soft
+"#;
+    let (rest, lockfile) = parse_lockfile(input).expect("parse lockfile");
+    assert!(rest.is_empty());
+    assert_eq!(lockfile.to_string(), input);
+  }
+
+  #[test]
+  fn test_write_resolutions_and_constraints() {
+    let input = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10
+
+resolutions:
+  lodash@^4.0.0: "npm:4.17.21"
+
+constraints:
+  react@^18: "npm:^18.2.0"
+
+"lodash@npm:4.17.21":
+  version: 4.17.21
+  resolution: "lodash@npm:4.17.21"
+  languageName: node
+  linkType: hard
+"#;
+    let (_, lockfile) = parse_lockfile(input).expect("parse lockfile");
+    assert_eq!(lockfile.to_string(), input);
+
+    let mut bytes = Vec::new();
+    lockfile.write_to(&mut bytes).expect("write lockfile");
+    assert_eq!(String::from_utf8(bytes).unwrap(), input);
+  }
+
+  #[test]
+  fn test_roundtrip_extra_properties() {
+    let input = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10
+
+"debug@npm:1.0.0":
+  version: 1.0.0
+  resolution: "debug@npm:1.0.0"
+  checksum: 10/abcdef
+  futureBlock:
+    nested: value
+    other: "npm:^1.0.0"
+  languageName: node
+  linkType: hard
+  quoted: "\"b\" c"
+  windowsPath: "C:\\tools\\bin"
+  zeta: "*"
+"#;
+    let lockfile = crate::parse::parse_with_mode(input, crate::parse::ParseMode::Lenient)
+      .expect("lenient parse");
+    let extra = &lockfile.entries[0].package.extra;
+    assert_eq!(
+      extra.get("quoted"),
+      Some(&ExtraProperty::Scalar("\"b\" c".to_string()))
+    );
+    assert_eq!(
+      extra.get("windowsPath"),
+      Some(&ExtraProperty::Scalar("C:\\tools\\bin".to_string()))
+    );
+    assert_eq!(lockfile.to_string(), input);
+  }
+
+  #[test]
+  fn test_unquote_string() {
+    for value in [
+      "plain",
+      "npm:^1.0.0",
+      "a \"b\" c",
+      "back\\slash",
+      "tab\there",
+      "\u{01}",
+    ] {
+      assert_eq!(unquote_string(&stringify_string(value)), value);
+    }
+    assert_eq!(unquote_string("\"\"x\"\""), "\"x\"");
+  }
+}
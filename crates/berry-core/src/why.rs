@@ -0,0 +1,296 @@
+//! Why a package is in the tree
+//!
+//! [`why`] answers questions like "why is `lodash@npm:4.17.15` here?" by
+//! listing every path through a [`DependencyGraph`] from a workspace down to
+//! the entries a [`WhyQuery`] matches, as `yarn why --recursive` does.
+//!
+//! Only `dependencies` are followed: peer dependencies are provided by the
+//! parent, so they never pull a package into the tree on their own.
+
+use crate::graph::{DependencyGraph, DependencyKind, Edge};
+use crate::ident::split_name_and_range;
+use crate::lockfile::Lockfile;
+use crate::package::{LinkType, Package};
+use crate::reference::ReferenceKind;
+
+/// What to explain, either every version of a package or one resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WhyQuery<'a> {
+  /// A package name like `lodash` or `@babel/core`, matching every version
+  Ident(&'a str),
+  /// A resolution like `lodash@npm:4.17.15`
+  Locator(&'a str),
+}
+
+impl<'a> WhyQuery<'a> {
+  /// `lodash` and `@babel/core` are idents, `lodash@npm:4.17.15` is a locator
+  pub fn parse(input: &'a str) -> Self {
+    if split_name_and_range(input).is_some() {
+      Self::Locator(input)
+    } else {
+      Self::Ident(input)
+    }
+  }
+
+  /// Positions of the entries the query matches
+  fn targets(&self, lockfile: &Lockfile) -> Vec<usize> {
+    let ident = match self {
+      Self::Ident(name) => match lockfile.interner.get_ident(name) {
+        Some(ident) => Some(ident),
+        None => return Vec::new(),
+      },
+      Self::Locator(_) => None,
+    };
+
+    let matches = |package: &Package| match self {
+      Self::Ident(_) => package
+        .resolution_locator
+        .as_ref()
+        .is_some_and(|locator| Some(*locator.ident()) == ident),
+      Self::Locator(locator) => package.resolution.as_deref() == Some(*locator),
+    };
+
+    lockfile
+      .entries
+      .iter()
+      .enumerate()
+      .filter(|(_, entry)| matches(&entry.package))
+      .map(|(position, _)| position)
+      .collect()
+  }
+}
+
+/// A chain of dependencies from a workspace down to a matched entry
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DependencyPath {
+  /// Position of the workspace the path starts at
+  pub root: usize,
+  /// The dependencies followed from `root`, empty when the matched entry is
+  /// the workspace itself
+  pub edges: Vec<Edge>,
+}
+
+impl DependencyPath {
+  /// Position of the matched entry
+  pub fn target(&self) -> usize {
+    self.edges.last().map_or(self.root, |edge| edge.to)
+  }
+
+  /// Positions of every entry on the path, from the workspace to the target
+  pub fn positions(&self) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(self.root).chain(self.edges.iter().map(|edge| edge.to))
+  }
+
+  /// Number of dependencies between the workspace and the target
+  pub fn depth(&self) -> usize {
+    self.edges.len()
+  }
+}
+
+/// Whether an entry is a workspace, the roots that [`why`] paths start at
+pub fn is_workspace_root(package: &Package) -> bool {
+  package.link_type == LinkType::Soft
+    && package
+      .resolution_locator
+      .as_ref()
+      .is_some_and(|locator| matches!(locator.reference_kind(), ReferenceKind::Workspace { .. }))
+}
+
+/// Every path from a workspace to the entries `query` matches, ordered by
+/// the positions along them
+///
+/// Paths stop at the first workspace they reach, since every workspace is a
+/// root of its own. Paths longer than `max_depth` dependencies are left out,
+/// and paths that only differ by the name a dependency is declared under
+/// (such as an alias of the same entry) are reported once.
+pub fn why(
+  lockfile: &Lockfile,
+  graph: &DependencyGraph,
+  query: &WhyQuery<'_>,
+  max_depth: Option<usize>,
+) -> Vec<DependencyPath> {
+  let mut search = Search {
+    lockfile,
+    graph,
+    max_depth,
+    on_path: vec![false; graph.len()],
+    chain: Vec::new(),
+    paths: Vec::new(),
+  };
+  for target in query.targets(lockfile) {
+    search.walk_up(target);
+  }
+
+  let mut paths = search.paths;
+  paths.sort_by(|a, b| a.positions().cmp(b.positions()));
+  paths.dedup_by(|a, b| a.positions().eq(b.positions()));
+  paths
+}
+
+/// Depth-first search from a target up through its dependents
+struct Search<'a> {
+  lockfile: &'a Lockfile,
+  graph: &'a DependencyGraph,
+  max_depth: Option<usize>,
+  /// Entries on the current chain, so cycles aren't followed
+  on_path: Vec<bool>,
+  /// Edges from the current position down to the target
+  chain: Vec<Edge>,
+  paths: Vec<DependencyPath>,
+}
+
+impl Search<'_> {
+  fn walk_up(&mut self, position: usize) {
+    if is_workspace_root(&self.lockfile.entries[position].package) {
+      self.paths.push(DependencyPath {
+        root: position,
+        edges: self.chain.iter().rev().copied().collect(),
+      });
+      return;
+    }
+    if self.max_depth.is_some_and(|max| self.chain.len() >= max) {
+      return;
+    }
+
+    self.on_path[position] = true;
+    let graph = self.graph;
+    for edge in graph.dependents(position) {
+      if edge.kind != DependencyKind::Dependency || self.on_path[edge.from] {
+        continue;
+      }
+      self.chain.push(*edge);
+      self.walk_up(edge.from);
+      self.chain.pop();
+    }
+    self.on_path[position] = false;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parse::parse;
+
+  const LOCKFILE: &str = r#"# This file is generated by running "yarn install" inside your project.
+# Manual changes might be lost - proceed with caution!
+
+__metadata:
+  version: 8
+  cacheKey: 10c0
+
+"a@npm:1.0.0":
+  version: 1.0.0
+  resolution: "a@npm:1.0.0"
+  dependencies:
+    b: "npm:1.0.0"
+  languageName: node
+  linkType: hard
+
+"app@workspace:packages/app":
+  version: 0.0.0-use.local
+  resolution: "app@workspace:packages/app"
+  dependencies:
+    debug: "npm:^4.3.1"
+    ms: "npm:2.1.2"
+    ms-alias: "npm:ms@2.1.2"
+  languageName: unknown
+  linkType: soft
+
+"b@npm:1.0.0":
+  version: 1.0.0
+  resolution: "b@npm:1.0.0"
+  dependencies:
+    a: "npm:1.0.0"
+    ms: "npm:2.1.2"
+  languageName: node
+  linkType: hard
+
+"debug@npm:^4.3.1":
+  version: 4.3.4
+  resolution: "debug@npm:4.3.4"
+  dependencies:
+    ms: "npm:2.1.2"
+  languageName: node
+  linkType: hard
+
+"ms-alias@npm:ms@2.1.2, ms@npm:2.1.2":
+  version: 2.1.2
+  resolution: "ms@npm:2.1.2"
+  languageName: node
+  linkType: hard
+
+"root@workspace:.":
+  version: 0.0.0-use.local
+  resolution: "root@workspace:."
+  dependencies:
+    a: "npm:1.0.0"
+    debug: "npm:^4.3.1"
+  languageName: unknown
+  linkType: soft
+"#;
+
+  fn explain(input: &str, max_depth: Option<usize>) -> Vec<String> {
+    let lockfile = parse(LOCKFILE).expect("should parse");
+    let graph = DependencyGraph::new(&lockfile).expect("every dependency should resolve");
+    why(&lockfile, &graph, &WhyQuery::parse(input), max_depth)
+      .iter()
+      .map(|path| {
+        path
+          .positions()
+          .map(|position| {
+            let locator = lockfile.entries[position]
+              .package
+              .resolution_locator
+              .as_ref();
+            locator
+              .unwrap()
+              .ident()
+              .display(&lockfile.interner)
+              .to_string()
+          })
+          .collect::<Vec<_>>()
+          .join(" > ")
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_why_paths() {
+    // `app` reaches `ms` directly under two names, which is reported once,
+    // and the `a` <-> `b` cycle is only walked once per path
+    let expected = [
+      "app > debug > ms",
+      "app > ms",
+      "root > a > b > ms",
+      "root > debug > ms",
+    ];
+    assert_eq!(explain("ms", None), expected);
+    assert_eq!(explain("ms@npm:2.1.2", None), expected);
+    assert_eq!(explain("b", None), ["root > a > b"]);
+  }
+
+  #[test]
+  fn test_why_max_depth_and_roots() {
+    assert_eq!(
+      explain("ms", Some(2)),
+      ["app > debug > ms", "app > ms", "root > debug > ms"]
+    );
+    assert_eq!(explain("ms", Some(0)), Vec::<String>::new());
+    assert_eq!(explain("app", None), ["app"]);
+    assert!(explain("left-pad", None).is_empty());
+    assert!(explain("ms@npm:1.0.0", None).is_empty());
+  }
+
+  #[test]
+  fn test_parse_query() {
+    assert_eq!(WhyQuery::parse("lodash"), WhyQuery::Ident("lodash"));
+    assert_eq!(
+      WhyQuery::parse("@babel/core"),
+      WhyQuery::Ident("@babel/core")
+    );
+    assert_eq!(
+      WhyQuery::parse("@babel/core@npm:7.24.0"),
+      WhyQuery::Locator("@babel/core@npm:7.24.0")
+    );
+  }
+}
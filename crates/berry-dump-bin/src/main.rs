@@ -1,11 +1,32 @@
-use berry::parse::parse_lockfile;
-use clap::Parser;
+use berry::classic::{ClassicLockfile, is_classic, parse_classic};
+use berry::dedupe::{DedupePlan, Duplicate, find_duplicates, plan_dedupe};
+use berry::diff::diff;
+use berry::graph::{DependencyGraph, Edge};
+use berry::lockfile::Lockfile;
+use berry::merge::{Merge, MergeError, merge, merge_conflicted};
+use berry::parse::{ParseMode, parse, parse_recovering, parse_with_mode};
+use berry::why::{DependencyPath, WhyQuery, why};
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(name = "berry-dump")]
 #[command(about = "Parse a Yarn Berry lockfile and dump the Rust struct")]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+  #[command(subcommand)]
+  command: Option<Command>,
+
+  #[command(flatten)]
+  source: Source,
+
+  /// Skip broken entries and report them, instead of stopping at the first error
+  #[arg(short, long)]
+  recover: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct Source {
   /// Path to a lockfile to parse
   #[arg(value_name = "LOCKFILE")]
   lockfile: Option<PathBuf>,
@@ -15,14 +36,75 @@ struct Args {
   fixture: Option<String>,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Show every dependency path from a workspace to a package
+  Why {
+    /// A package name like `lodash`, or a resolution like `lodash@npm:4.17.15`
+    #[arg(value_name = "PACKAGE")]
+    package: String,
+
+    #[command(flatten)]
+    source: Source,
+
+    /// Leave out paths with more than this many dependencies
+    #[arg(short, long, value_name = "N")]
+    depth: Option<usize>,
+
+    /// Print the paths as JSON instead of a tree
+    #[arg(long)]
+    json: bool,
+  },
+  /// List packages locked at several versions and the descriptors that could
+  /// move onto the highest version that satisfies them
+  Dedupe {
+    #[command(flatten)]
+    source: Source,
+
+    /// Print the duplicates and the plan as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Write the deduped lockfile to this path
+    #[arg(short, long, value_name = "PATH")]
+    write: Option<PathBuf>,
+  },
+  /// Summarise the packages, checksums and dependencies that changed between
+  /// two lockfiles, either of which may be a yarn classic (v1) lockfile
+  Diff {
+    #[arg(value_name = "OLD")]
+    old: PathBuf,
+
+    #[arg(value_name = "NEW")]
+    new: PathBuf,
+
+    /// Print the changes as JSON
+    #[arg(long)]
+    json: bool,
+  },
+  /// Resolve conflict markers in a lockfile, or merge BASE OURS THEIRS the way
+  /// git calls a merge driver (`%O %A %B`)
+  Merge {
+    /// A lockfile with conflict markers, or the BASE, OURS and THEIRS lockfiles
+    #[arg(value_name = "FILES", num_args = 1..=3, required = true)]
+    files: Vec<PathBuf>,
+
+    /// Write the result here instead of over the conflicted lockfile or OURS
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Fail when both sides resolve a descriptor differently
+    #[arg(long)]
+    strict: bool,
+  },
+}
+
 fn read_file(p: &PathBuf) -> String {
   std::fs::read_to_string(p).expect("failed to read file")
 }
 
-fn main() {
-  let args = Args::parse();
-
-  let contents = if let Some(fixture) = &args.fixture {
+fn read_source(source: &Source) -> String {
+  if let Some(fixture) = &source.fixture {
     let fixtures_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
       .parent()
       .unwrap()
@@ -31,23 +113,315 @@ fn main() {
       .join("fixtures");
     let path = fixtures_dir.join(fixture);
     read_file(&path)
-  } else if let Some(path) = &args.lockfile {
+  } else if let Some(path) = &source.lockfile {
     read_file(path)
   } else {
     eprintln!("Provide a lockfile path or --fixture <name>");
     std::process::exit(2);
-  };
+  }
+}
 
-  match parse_lockfile(&contents) {
-    Ok((remaining, lockfile)) => {
-      println!("lockfile: {lockfile:#?}");
-      if !remaining.trim().is_empty() {
-        eprintln!("WARNING: {} bytes remaining unparsed", remaining.len());
+fn main() {
+  let args = Args::parse();
+
+  match &args.command {
+    Some(Command::Why {
+      package,
+      source,
+      depth,
+      json,
+    }) => {
+      let contents = read_source(source);
+      run_why(&contents, package, *depth, *json);
+      return;
+    }
+    Some(Command::Dedupe {
+      source,
+      json,
+      write,
+    }) => {
+      let contents = read_source(source);
+      run_dedupe(&contents, *json, write.as_ref());
+      return;
+    }
+    Some(Command::Merge {
+      files,
+      output,
+      strict,
+    }) => {
+      run_merge(files, output.as_ref(), *strict);
+      return;
+    }
+    Some(Command::Diff { old, new, json }) => {
+      let old = parse_any_or_exit(&read_file(old));
+      let new = parse_any_or_exit(&read_file(new));
+      let changes = diff(&old, &new);
+      if *json {
+        println!("{}", serde_json::to_string_pretty(&changes).unwrap());
+      } else {
+        print!("{changes}");
+      }
+      return;
+    }
+    None => {}
+  }
+
+  let contents = read_source(&args.source);
+
+  if args.recover {
+    match parse_recovering(&contents, ParseMode::Strict) {
+      Ok((lockfile, diagnostics)) => {
+        println!("lockfile: {lockfile:#?}");
+        for diagnostic in &diagnostics {
+          eprintln!("Skipped: {diagnostic}");
+          eprintln!();
+          eprint!("{}", diagnostic.error.code_frame(&contents));
+          eprintln!();
+        }
+        if !diagnostics.is_empty() {
+          std::process::exit(1);
+        }
+      }
+      Err(e) => {
+        eprintln!("Parse error: {e}");
+        eprintln!();
+        eprint!("{}", e.code_frame(&contents));
+        std::process::exit(1);
       }
     }
+    return;
+  }
+
+  match parse(&contents) {
+    Ok(lockfile) => {
+      println!("lockfile: {lockfile:#?}");
+    }
     Err(e) => {
-      eprintln!("Parse error: {e:?}");
+      eprintln!("Parse error: {e}");
+      eprintln!();
+      eprint!("{}", e.code_frame(&contents));
       std::process::exit(1);
     }
   }
 }
+
+fn parse_or_exit(contents: &str) -> Lockfile {
+  parse_or_exit_with_mode(contents, ParseMode::Strict)
+}
+
+fn parse_or_exit_with_mode(contents: &str, mode: ParseMode) -> Lockfile {
+  parse_with_mode(contents, mode).unwrap_or_else(|e| {
+    eprintln!("Parse error: {e}");
+    eprintln!();
+    eprint!("{}", e.code_frame(contents));
+    std::process::exit(1);
+  })
+}
+
+/// Like [`parse_or_exit`], but also reads yarn classic lockfiles
+fn parse_any_or_exit(contents: &str) -> Lockfile {
+  if !is_classic(contents) {
+    return parse_or_exit(contents);
+  }
+  parse_classic(contents).map_or_else(
+    |e| {
+      eprintln!("Parse error: {e}");
+      eprintln!();
+      eprint!("{}", e.code_frame(contents));
+      std::process::exit(1);
+    },
+    ClassicLockfile::into_lockfile,
+  )
+}
+
+fn run_why(contents: &str, package: &str, depth: Option<usize>, json: bool) {
+  let lockfile = parse_or_exit(contents);
+  // Dependencies remapped by `resolutions` have no entry, which shouldn't stop
+  // us explaining the rest of the tree
+  let graph = DependencyGraph::new_lenient(&lockfile);
+  let paths = why(&lockfile, &graph, &WhyQuery::parse(package), depth);
+
+  if json {
+    println!("{}", paths_to_json(&lockfile, &paths));
+  } else if paths.is_empty() {
+    eprintln!("No dependency path leads to `{package}`");
+    std::process::exit(1);
+  } else {
+    print!("{}", paths_to_tree(&lockfile, &paths));
+  }
+}
+
+fn run_dedupe(contents: &str, json: bool, write: Option<&PathBuf>) {
+  let lockfile = parse_or_exit(contents);
+  let duplicates = find_duplicates(&lockfile);
+  let plan = plan_dedupe(&lockfile);
+
+  if json {
+    let report = serde_json::json!({ "duplicates": duplicates, "moves": plan.moves });
+    println!("{report}");
+  } else {
+    print!("{}", dedupe_to_text(&duplicates, &plan));
+  }
+
+  if let Some(path) = write {
+    let file = std::fs::File::create(path).expect("failed to create file");
+    plan
+      .apply(lockfile)
+      .write_to(std::io::BufWriter::new(file))
+      .expect("failed to write lockfile");
+  }
+}
+
+fn run_merge(files: &[PathBuf], output: Option<&PathBuf>, strict: bool) {
+  let (result, target) = match files {
+    [conflicted] => (merge_conflicted(&read_file(conflicted)), conflicted),
+    [base, ours, theirs] => {
+      let [base, ours_lockfile, theirs] = [base, ours, theirs]
+        .map(|path| parse_or_exit_with_mode(&read_file(path), ParseMode::Lenient));
+      (merge(Some(&base), &ours_lockfile, &theirs), ours)
+    }
+    _ => {
+      eprintln!("Provide a conflicted lockfile, or the BASE, OURS and THEIRS lockfiles");
+      std::process::exit(2);
+    }
+  };
+
+  let Merge {
+    lockfile,
+    conflicts,
+  } = result.unwrap_or_else(|e: MergeError| {
+    eprintln!("Merge error: {e}");
+    std::process::exit(1);
+  });
+  for conflict in &conflicts {
+    eprintln!(
+      "Conflict: {} is {} in ours and {} in theirs, keeping {}",
+      conflict.descriptor, conflict.ours, conflict.theirs, conflict.chosen
+    );
+  }
+  // Leave the conflict markers, or git's OURS, for the user to resolve
+  if strict && !conflicts.is_empty() {
+    std::process::exit(1);
+  }
+
+  let file = std::fs::File::create(output.unwrap_or(target)).expect("failed to create file");
+  lockfile
+    .write_to(std::io::BufWriter::new(file))
+    .expect("failed to write lockfile");
+}
+
+fn dedupe_to_text(duplicates: &[Duplicate], plan: &DedupePlan) -> String {
+  let mut out = String::new();
+  for duplicate in duplicates {
+    out.push_str(&duplicate.name);
+    out.push('\n');
+    for resolved in &duplicate.versions {
+      out.push_str("  ");
+      out.push_str(&resolved.resolution);
+      out.push('\n');
+      for descriptor in &resolved.descriptors {
+        out.push_str("    ");
+        out.push_str(descriptor);
+        out.push('\n');
+      }
+    }
+  }
+
+  if plan.is_empty() {
+    out.push_str("Nothing to dedupe\n");
+  }
+  for dedupe_move in &plan.moves {
+    out.push_str(&dedupe_move.descriptor);
+    out.push_str(": ");
+    out.push_str(&dedupe_move.from);
+    out.push_str(" -> ");
+    out.push_str(&dedupe_move.to);
+    out.push('\n');
+  }
+  out
+}
+
+/// How an entry on a path is shown: its resolution, and the name it was
+/// depended on under when that's an alias
+fn label(lockfile: &Lockfile, position: usize, edge: Option<&Edge>) -> (String, Option<String>) {
+  let package = &lockfile.entries[position].package;
+  let resolution = package.resolution.clone().unwrap_or_default();
+  let alias = edge
+    .filter(|edge| {
+      package
+        .resolution_locator
+        .as_ref()
+        .is_some_and(|locator| *locator.ident() != edge.ident)
+    })
+    .map(|edge| edge.ident.display(&lockfile.interner).to_string());
+  (resolution, alias)
+}
+
+fn paths_to_json(lockfile: &Lockfile, paths: &[DependencyPath]) -> serde_json::Value {
+  let paths = paths
+    .iter()
+    .map(|path| {
+      let edges = std::iter::once(None).chain(path.edges.iter().map(Some));
+      path
+        .positions()
+        .zip(edges)
+        .map(|(position, edge)| {
+          let (resolution, alias) = label(lockfile, position, edge);
+          serde_json::json!({ "resolution": resolution, "alias": alias })
+        })
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+  serde_json::Value::from(paths)
+}
+
+/// An entry in the rendered `why` tree
+struct TreeNode {
+  position: usize,
+  label: String,
+  children: Vec<Self>,
+}
+
+/// Render paths as a tree, merging the prefixes they share
+fn paths_to_tree(lockfile: &Lockfile, paths: &[DependencyPath]) -> String {
+  let mut roots: Vec<TreeNode> = Vec::new();
+  for path in paths {
+    let edges = std::iter::once(None).chain(path.edges.iter().map(Some));
+    let mut siblings = &mut roots;
+    for (position, edge) in path.positions().zip(edges) {
+      // Paths come sorted by position, so a shared prefix is always the last child
+      if siblings.last().is_none_or(|node| node.position != position) {
+        let (resolution, alias) = label(lockfile, position, edge);
+        siblings.push(TreeNode {
+          position,
+          label: alias.map_or_else(
+            || resolution.clone(),
+            |alias| format!("{resolution} (as {alias})"),
+          ),
+          children: Vec::new(),
+        });
+      }
+      siblings = &mut siblings.last_mut().unwrap().children;
+    }
+  }
+
+  let mut out = String::new();
+  for root in &roots {
+    out.push_str(&root.label);
+    out.push('\n');
+    render_children(&root.children, "", &mut out);
+  }
+  out
+}
+
+fn render_children(children: &[TreeNode], indent: &str, out: &mut String) {
+  for (i, child) in children.iter().enumerate() {
+    let is_last = i + 1 == children.len();
+    out.push_str(indent);
+    out.push_str(if is_last { "└─ " } else { "├─ " });
+    out.push_str(&child.label);
+    out.push('\n');
+    let indent = format!("{indent}{}", if is_last { "   " } else { "│  " });
+    render_children(&child.children, &indent, out);
+  }
+}
@@ -39,7 +39,20 @@ pub fn load_fixture_from_path(fixture_path: &Path) -> String {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use berry::parse::parse_lockfile;
+  use berry::classic::parse_classic;
+  use berry::dedupe::{find_duplicates, plan_dedupe};
+  use berry::diff::diff;
+  use berry::graph::{DependencyGraph, GraphError};
+  use berry::merge::merge;
+  use berry::parse::{
+    ParseMode, parse_lockfile, parse_lockfile_borrowed, parse_lockfile_borrowed_parallel,
+    parse_lockfile_parallel, parse_with_mode,
+  };
+  use berry::patch::PatchSpec;
+  use berry::reader::LockfileReader;
+  use berry::reference::ReferenceKind;
+  use berry::semver::Version;
+  use berry::why::{WhyQuery, is_workspace_root, why};
   use rstest::rstest;
   use std::path::PathBuf;
 
@@ -101,6 +114,315 @@ mod tests {
     );
   }
 
+  #[rstest]
+  fn test_roundtrip_lockfile_fixtures(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let filename = fixture_path
+      .file_name()
+      .and_then(|name| name.to_str())
+      .unwrap_or("unknown");
+
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+
+    // Some fixtures were lifted out of JS template literals and still carry the
+    // closing backtick, or lost their final newline; neither is lockfile content.
+    let expected = format!(
+      "{}\n",
+      contents.trim_end_matches(|c: char| c == '`' || c == ';' || c.is_whitespace())
+    );
+
+    let serialized = lockfile.to_string();
+    if serialized != expected {
+      let line = serialized
+        .lines()
+        .zip(expected.lines())
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| serialized.lines().count().min(expected.lines().count()));
+      panic!(
+        "Round-trip mismatch in {filename} at line {}:\n  expected: {:?}\n  actual:   {:?}",
+        line + 1,
+        expected.lines().nth(line),
+        serialized.lines().nth(line)
+      );
+    }
+  }
+
+  #[rstest]
+  fn test_parallel_matches_sequential_fixtures(
+    #[files("../../fixtures/*.lock")] fixture_path: PathBuf,
+  ) {
+    let contents = load_fixture_from_path(&fixture_path);
+
+    let sequential = parse_lockfile_borrowed(&contents);
+    let parallel = parse_lockfile_borrowed_parallel(&contents);
+    assert_eq!(parallel, sequential);
+
+    // The owned form interns in entry order, so it matches too
+    let (_, sequential) = parse_lockfile(&contents).expect("should parse fixture");
+    let (_, parallel) = parse_lockfile_parallel(&contents).expect("should parse fixture");
+    assert_eq!(parallel.to_string(), sequential.to_string());
+    assert_eq!(parallel.interner.len(), sequential.interner.len());
+  }
+
+  #[rstest]
+  fn test_npm_versions_satisfy_descriptors(
+    #[files("../../fixtures/*.lock")] fixture_path: PathBuf,
+  ) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+
+    // Workspaces can be resolved from npm-looking descriptors like `c@*`, so
+    // only check packages that were resolved from the registry
+    let npm_entries = lockfile.entries.iter().filter(|entry| {
+      entry
+        .package
+        .resolution_locator
+        .as_ref()
+        .is_some_and(|locator| locator.reference().starts_with("npm:"))
+    });
+
+    for entry in npm_entries {
+      let Some(version) = entry.package.version.as_deref() else {
+        continue;
+      };
+      let version = Version::parse(version).expect("npm entries have semver versions");
+
+      for descriptor in &entry.descriptors {
+        assert_ne!(
+          descriptor.matches(&version),
+          Some(false),
+          "{} resolved to {version}",
+          descriptor.display(&lockfile.interner)
+        );
+      }
+    }
+  }
+
+  #[rstest]
+  fn test_resolutions_have_known_reference_kinds(
+    #[files("../../fixtures/*.lock")] fixture_path: PathBuf,
+  ) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+
+    for entry in &lockfile.entries {
+      let Some(locator) = &entry.package.resolution_locator else {
+        continue;
+      };
+      let kind = locator.reference_kind();
+      assert!(
+        !matches!(kind, ReferenceKind::Unknown(_)),
+        "{} has an unknown reference kind",
+        locator.reference()
+      );
+      match kind {
+        ReferenceKind::Patch { inner, patches, .. } => {
+          assert!(!inner.is_empty() && !patches.is_empty());
+        }
+        // Resolutions always pin the commit that was checked out
+        ReferenceKind::Git(spec) => assert!(spec.commit().is_some(), "{}", spec.url),
+        _ => {}
+      }
+    }
+  }
+
+  #[rstest]
+  fn test_patch_specs_roundtrip_fixtures(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, mut lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+    let interner = &mut lockfile.interner;
+
+    for entry in &lockfile.entries {
+      for descriptor in &entry.descriptors {
+        if !descriptor.range().starts_with("patch:") {
+          continue;
+        }
+        let spec = descriptor
+          .patch_spec(interner)
+          .expect("patch descriptors should decode");
+        assert_eq!(spec.inner.ident(), descriptor.ident());
+        assert_eq!(spec.display(interner).to_string(), descriptor.range());
+      }
+
+      let Some(locator) = &entry.package.resolution_locator else {
+        continue;
+      };
+      if !locator.reference().starts_with("patch:") {
+        continue;
+      }
+      let spec: PatchSpec<_> = locator
+        .patch_spec(interner)
+        .expect("patch resolutions should decode");
+      assert!(!spec.inner.reference().contains('%'));
+      assert!(spec.params.version().is_some() && spec.params.hash().is_some());
+      assert_eq!(spec.display(interner).to_string(), locator.reference());
+    }
+  }
+
+  #[rstest]
+  fn test_checksums_parse_fixtures(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+
+    for entry in &lockfile.entries {
+      let Some(checksum) = entry.package.parsed_checksum() else {
+        continue;
+      };
+      let checksum = checksum.expect("fixture checksums should parse");
+      assert_eq!(Some(checksum.to_string()), entry.package.checksum);
+    }
+  }
+
+  #[rstest]
+  fn test_dependency_graph_fixtures(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+
+    // Dependencies remapped by `resolutions` in package.json keep their
+    // original range, which no lockfile key matches, so some fixtures do
+    // have missing resolutions
+    let graph = DependencyGraph::new_lenient(&lockfile);
+    assert_eq!(graph.len(), lockfile.entries.len());
+    assert_eq!(
+      DependencyGraph::new(&lockfile).is_ok(),
+      graph.missing().is_empty()
+    );
+    for position in 0..graph.len() {
+      for edge in graph.dependencies(position) {
+        assert_eq!(edge.from, position);
+        assert!(graph.dependents(edge.to).contains(edge));
+      }
+    }
+  }
+
+  #[rstest]
+  fn test_dedupe_fixtures(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+
+    for duplicate in find_duplicates(&lockfile) {
+      assert!(duplicate.versions.len() > 1);
+      assert!(
+        duplicate
+          .versions
+          .is_sorted_by(|a, b| a.version <= b.version)
+      );
+    }
+
+    let entries = lockfile.entries.len();
+    let plan = plan_dedupe(&lockfile);
+    let deduped = plan.apply(lockfile).to_string();
+    let (_, reparsed) = parse_lockfile(&deduped).expect("should parse the deduped lockfile");
+    assert!(reparsed.entries.len() <= entries);
+    assert!(
+      plan_dedupe(&reparsed).is_empty(),
+      "deduping twice should be a no-op"
+    );
+  }
+
+  #[rstest]
+  fn test_diff_fixture_against_itself(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, old) = parse_lockfile(&contents).expect("should parse fixture");
+    let (_, new) = parse_lockfile(&contents).expect("should parse fixture");
+    assert!(diff(&old, &new).is_empty());
+  }
+
+  #[rstest]
+  fn test_merge_fixture_with_itself(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+    let (_, ours) = parse_lockfile(&contents).expect("should parse fixture");
+    let (_, theirs) = parse_lockfile(&contents).expect("should parse fixture");
+
+    let merged = merge(Some(&ours), &ours, &theirs).expect("should merge");
+    assert!(merged.conflicts.is_empty());
+    assert!(diff(&ours, &merged.lockfile).is_empty());
+    assert_eq!(merged.lockfile.to_string(), ours.to_string());
+  }
+
+  #[test]
+  fn test_classic_fixture() {
+    let contents = load_fixture("classic/yarn.lock");
+    let classic = parse_classic(&contents).expect("should parse fixture");
+    assert_eq!(classic.entries.len(), 6);
+
+    let lockfile = classic.into_lockfile();
+    let graph = DependencyGraph::new(&lockfile).expect("every dependency should resolve");
+    assert_eq!(graph.len(), lockfile.entries.len());
+    assert!(find_duplicates(&lockfile).is_empty());
+
+    // `resolved` and `integrity` are extra properties, which only lenient parsing keeps
+    let reparsed = parse_with_mode(&lockfile.to_string(), ParseMode::Lenient)
+      .expect("should parse the conversion");
+    assert!(diff(&lockfile, &reparsed).is_empty());
+  }
+
+  #[test]
+  fn test_why_minimal_berry() {
+    let contents = load_fixture("minimal-berry.lock");
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+    let graph = DependencyGraph::new(&lockfile).expect("every dependency should resolve");
+
+    let paths = why(&lockfile, &graph, &WhyQuery::parse("lodash"), None);
+    assert!(!paths.is_empty());
+    for path in &paths {
+      assert!(is_workspace_root(&lockfile.entries[path.root].package));
+      assert_eq!(
+        lockfile.entries[path.target()]
+          .package
+          .resolution
+          .as_deref(),
+        Some("lodash@npm:4.17.21")
+      );
+    }
+  }
+
+  #[test]
+  fn test_dependency_graph_reports_overridden_resolution() {
+    let contents = load_fixture("yarn4-resolution.lock");
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+
+    let Err(GraphError::MissingResolutions(missing)) = DependencyGraph::new(&lockfile) else {
+      panic!("`react@npm:^18.2.0` is overridden to 18.1.0 and has no entry");
+    };
+    let descriptors: Vec<_> = missing.iter().map(|m| m.descriptor.as_str()).collect();
+    assert_eq!(descriptors, ["react@npm:^18.2.0"]);
+  }
+
+  #[rstest]
+  fn test_reader_matches_parse_fixtures(#[files("../../fixtures/*.lock")] fixture_path: PathBuf) {
+    let contents = load_fixture_from_path(&fixture_path);
+
+    let (_, lockfile) = parse_lockfile(&contents).expect("should parse fixture");
+    let mut reader = LockfileReader::new(contents.as_bytes()).expect("should read preamble");
+    assert_eq!(reader.metadata().version, lockfile.metadata.version);
+
+    let mut count = 0;
+    while let Some(entry) = reader.next() {
+      let entry = entry.expect("should read every entry");
+      let expected = &lockfile.entries[count];
+      let descriptors = |entry: &berry::lockfile::Entry, interner| {
+        entry
+          .descriptors
+          .iter()
+          .map(|d| d.display(interner).to_string())
+          .collect::<Vec<_>>()
+      };
+      assert_eq!(
+        descriptors(&entry, reader.interner()),
+        descriptors(expected, &lockfile.interner)
+      );
+      assert_eq!(entry.package.resolution, expected.package.resolution);
+      assert_eq!(entry.package.checksum, expected.package.checksum);
+      assert_eq!(
+        entry.package.dependencies.len(),
+        expected.package.dependencies.len()
+      );
+      count += 1;
+    }
+    assert_eq!(count, lockfile.entries.len());
+  }
+
   // TODO: get this test passing, then remove it
   #[test]
   fn test_specific_minimal_berry_lockfile() {
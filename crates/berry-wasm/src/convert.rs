@@ -0,0 +1,157 @@
+//! Plain JS objects for lockfile data
+//!
+//! The shapes match the node bindings' `ParsedLockfile` and `LockfileEntry`,
+//! with maps sorted by key, as yarn writes them.
+
+use berry::ParseError;
+use berry::ident::{Descriptor, Ident};
+use berry::intern::Interner;
+use berry::lockfile::{Entry, Lockfile, Metadata};
+use berry::package::ExtraProperty;
+use js_sys::{Array, Object, Reflect};
+use std::collections::{BTreeMap, HashMap};
+use wasm_bindgen::JsValue;
+
+fn set(object: &Object, key: &str, value: impl Into<JsValue>) {
+  // Only fails for proxies and frozen objects, and these are neither
+  let _ = Reflect::set(object, &JsValue::from_str(key), &value.into());
+}
+
+fn optional(value: Option<&String>) -> JsValue {
+  value.map_or(JsValue::UNDEFINED, |value| JsValue::from_str(value))
+}
+
+fn object<V: Into<JsValue>>(map: BTreeMap<String, V>) -> Object {
+  let object = Object::new();
+  for (key, value) in map {
+    set(&object, &key, value);
+  }
+  object
+}
+
+pub(crate) fn metadata(metadata: &Metadata) -> Object {
+  let object = Object::new();
+  set(&object, "version", metadata.version.as_str());
+  set(&object, "cacheKey", metadata.cache_key.as_str());
+  object
+}
+
+pub(crate) fn entry(entry: &Entry, interner: &Interner) -> Object {
+  let package = &entry.package;
+  let name = |ident: &Ident| ident.display(interner).to_string();
+
+  let descriptors: Array = entry
+    .descriptors
+    .iter()
+    .map(|descriptor| JsValue::from_str(&descriptor.display(interner).to_string()))
+    .collect();
+  let ranges = |map: &HashMap<Ident, Descriptor>| {
+    object(
+      map
+        .iter()
+        .map(|(ident, descriptor)| (name(ident), descriptor.range().to_string()))
+        .collect(),
+    )
+  };
+  let dependencies_meta = object(
+    package
+      .dependencies_meta
+      .iter()
+      .map(|(ident, meta)| {
+        let object = Object::new();
+        let meta = meta.as_ref();
+        for (key, value) in [
+          ("built", meta.and_then(|meta| meta.built)),
+          ("optional", meta.and_then(|meta| meta.optional)),
+          ("unplugged", meta.and_then(|meta| meta.unplugged)),
+        ] {
+          set(
+            &object,
+            key,
+            value.map_or(JsValue::UNDEFINED, JsValue::from_bool),
+          );
+        }
+        (name(ident), object)
+      })
+      .collect(),
+  );
+  let peer_dependencies_meta = object(
+    package
+      .peer_dependencies_meta
+      .iter()
+      .map(|(ident, meta)| {
+        let object = Object::new();
+        set(&object, "optional", meta.optional);
+        (name(ident), object)
+      })
+      .collect(),
+  );
+
+  let object = Object::new();
+  set(&object, "descriptors", descriptors);
+  set(&object, "version", optional(package.version.as_ref()));
+  set(&object, "resolution", optional(package.resolution.as_ref()));
+  set(&object, "checksum", optional(package.checksum.as_ref()));
+  set(&object, "conditions", optional(package.conditions.as_ref()));
+  set(
+    &object,
+    "languageName",
+    package.language_name.as_str(interner),
+  );
+  set(&object, "linkType", package.link_type.as_str());
+  set(&object, "dependencies", ranges(&package.dependencies));
+  set(
+    &object,
+    "peerDependencies",
+    ranges(&package.peer_dependencies),
+  );
+  set(&object, "dependenciesMeta", dependencies_meta);
+  set(&object, "peerDependenciesMeta", peer_dependencies_meta);
+  set(&object, "bin", object_from_pairs(package.bin.iter()));
+  set(
+    &object,
+    "extra",
+    object_from_pairs(package.extra.iter().map(|(key, value)| {
+      let (ExtraProperty::Scalar(value) | ExtraProperty::Block(value)) = value;
+      (key, value)
+    })),
+  );
+  object
+}
+
+fn object_from_pairs<'a>(pairs: impl Iterator<Item = (&'a String, &'a String)>) -> Object {
+  object(
+    pairs
+      .map(|(key, value)| (key.clone(), value.clone()))
+      .collect(),
+  )
+}
+
+pub(crate) fn lockfile(lockfile: &Lockfile) -> Object {
+  let entries: Array = lockfile
+    .entries
+    .iter()
+    .map(|e| JsValue::from(entry(e, &lockfile.interner)))
+    .collect();
+  let block = |pairs: &Option<Vec<(String, String)>>| {
+    pairs.as_ref().map_or(JsValue::UNDEFINED, |pairs| {
+      object_from_pairs(pairs.iter().map(|(key, value)| (key, value))).into()
+    })
+  };
+
+  let object = Object::new();
+  set(&object, "metadata", metadata(&lockfile.metadata));
+  set(&object, "entries", entries);
+  set(&object, "resolutions", block(&lockfile.resolutions));
+  set(&object, "constraints", block(&lockfile.constraints));
+  object
+}
+
+/// A JS `Error` with a code frame in its message and the position as properties
+pub(crate) fn parse_error(error: &ParseError, contents: &str) -> JsValue {
+  let js_error = js_sys::Error::new(&format!("{error}\n\n{}", error.code_frame(contents)));
+  set(&js_error, "line", error.line as u32);
+  set(&js_error, "column", error.column as u32);
+  set(&js_error, "offset", error.offset as u32);
+  js_error.into()
+}
@@ -0,0 +1,146 @@
+//! WebAssembly bindings for berry
+//!
+//! Exposes the same `parse` and `Lockfile` queries as the node bindings
+//! through `wasm-bindgen`, for browsers and edge runtimes that can't load a
+//! native addon. Build with `wasm-pack build crates/berry-wasm`.
+
+use berry::parse::ParseMode;
+use berry::query::{LockfileIndex, find_by_name};
+use js_sys::{Array, Object};
+use wasm_bindgen::prelude::*;
+
+mod convert;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &'static str = r#"
+export interface LockfileMetadata {
+  version: string
+  cacheKey: string
+}
+
+export interface DependencyMeta {
+  built?: boolean
+  optional?: boolean
+  unplugged?: boolean
+}
+
+export interface PeerDependencyMeta {
+  optional: boolean
+}
+
+export interface LockfileEntry {
+  descriptors: Array<string>
+  version?: string
+  resolution?: string
+  checksum?: string
+  conditions?: string
+  languageName: string
+  linkType: 'hard' | 'soft'
+  dependencies: Record<string, string>
+  peerDependencies: Record<string, string>
+  dependenciesMeta: Record<string, DependencyMeta>
+  peerDependenciesMeta: Record<string, PeerDependencyMeta>
+  bin: Record<string, string>
+  extra: Record<string, string>
+}
+
+export interface ParsedLockfile {
+  metadata: LockfileMetadata
+  entries: Array<LockfileEntry>
+  resolutions?: Record<string, string>
+  constraints?: Record<string, string>
+}
+"#;
+
+/// Parse the contents of a yarn.lock file
+///
+/// Properties the parser doesn't know, such as ones added by a newer yarn,
+/// are kept in each entry's `extra` instead of failing the parse.
+///
+/// Throws an `Error` with `line`, `column` and `offset` properties, and a code
+/// frame in its message, if the lockfile can't be parsed.
+#[wasm_bindgen]
+pub fn parse(contents: &str) -> Result<Lockfile, JsValue> {
+  let lockfile = berry::parse::parse_with_mode(contents, ParseMode::Lenient)
+    .map_err(|error| convert::parse_error(&error, contents))?;
+  Ok(Lockfile {
+    index: LockfileIndex::new(&lockfile),
+    lockfile,
+  })
+}
+
+/// A parsed yarn lockfile, kept in wasm memory and queried from JS
+#[wasm_bindgen]
+pub struct Lockfile {
+  lockfile: berry::lockfile::Lockfile,
+  index: LockfileIndex,
+}
+
+impl Lockfile {
+  fn entries_at(&self, positions: Vec<usize>) -> Array {
+    positions
+      .into_iter()
+      .map(|position| JsValue::from(self.entry(position)))
+      .collect()
+  }
+
+  fn entry(&self, position: usize) -> Object {
+    convert::entry(&self.lockfile.entries[position], &self.lockfile.interner)
+  }
+}
+
+#[wasm_bindgen]
+impl Lockfile {
+  /// The `__metadata` block
+  #[wasm_bindgen(getter, unchecked_return_type = "LockfileMetadata")]
+  pub fn metadata(&self) -> Object {
+    convert::metadata(&self.lockfile.metadata)
+  }
+
+  /// Number of entries in the lockfile
+  #[wasm_bindgen(getter)]
+  pub fn length(&self) -> usize {
+    self.lockfile.entries.len()
+  }
+
+  /// The entry a descriptor like `debug@npm:^4.3.1` (or `debug@^4.3.1`) resolves to
+  #[wasm_bindgen(js_name = getEntry, unchecked_return_type = "LockfileEntry | undefined")]
+  pub fn get_entry(&self, descriptor: &str) -> Option<Object> {
+    let position = self.index.find_descriptor(&self.lockfile, descriptor)?;
+    Some(self.entry(position))
+  }
+
+  /// Every entry for a package name like `@babel/core`, one per resolved version
+  #[wasm_bindgen(js_name = findByName, unchecked_return_type = "Array<LockfileEntry>")]
+  pub fn find_by_name(&self, name: &str) -> Array {
+    self.entries_at(find_by_name(&self.lockfile, name))
+  }
+
+  /// The entries that the `dependencies` of a locator like `debug@npm:4.3.4`
+  /// resolve to, or `undefined` if no entry has that resolution
+  #[wasm_bindgen(
+    js_name = dependenciesOf,
+    unchecked_return_type = "Array<LockfileEntry> | undefined"
+  )]
+  pub fn dependencies_of(&self, locator: &str) -> Option<Array> {
+    let positions = self.index.dependencies_of(&self.lockfile, locator)?;
+    Some(self.entries_at(positions))
+  }
+
+  /// Every entry, in file order
+  #[wasm_bindgen(unchecked_return_type = "Array<LockfileEntry>")]
+  pub fn entries(&self) -> Array {
+    self.entries_at((0..self.lockfile.entries.len()).collect())
+  }
+
+  /// Convert the whole lockfile into a plain object
+  #[wasm_bindgen(js_name = toJSON, unchecked_return_type = "ParsedLockfile")]
+  pub fn to_json(&self) -> Object {
+    convert::lockfile(&self.lockfile)
+  }
+
+  /// Write the lockfile back out as yarn.lock text
+  pub fn serialize(&self) -> String {
+    self.lockfile.to_string()
+  }
+}
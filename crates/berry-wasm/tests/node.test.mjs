@@ -0,0 +1,56 @@
+// Run against a Node.js build of the crate:
+//
+//   wasm-pack build --target nodejs crates/berry-wasm
+//   node --test crates/berry-wasm/tests/
+import assert from 'node:assert/strict'
+import fs from 'node:fs'
+import { test } from 'node:test'
+
+import { parse } from '../pkg/berry_wasm.js'
+
+const fixture = fs.readFileSync(new URL('../../../fixtures/berry.lock', import.meta.url), 'utf8')
+
+test('parses a lockfile', () => {
+	const lockfile = parse(fixture)
+	assert.equal(lockfile.length, 337)
+	assert.deepEqual(lockfile.metadata, { version: '6', cacheKey: '8' })
+	assert.equal(lockfile.entries().length, 337)
+	assert.equal(lockfile.toJSON().entries.length, 337)
+})
+
+test('answers queries', () => {
+	const lockfile = parse(fixture)
+	assert.equal(lockfile.getEntry('@babel/core@npm:^7.0.0')?.version, '7.19.1')
+	assert.equal(lockfile.getEntry('@babel/core@^7.0.0')?.version, '7.19.1')
+	assert.equal(lockfile.getEntry('not-a-package@npm:1.0.0'), undefined)
+
+	assert.deepEqual(
+		lockfile.findByName('semver').map((entry) => entry.version),
+		['6.3.0', '7.3.7'],
+	)
+	assert.deepEqual(
+		lockfile.dependenciesOf('@babel/helper-compilation-targets@npm:7.19.1')?.map((entry) => entry.resolution),
+		[
+			'@babel/compat-data@npm:7.19.1',
+			'@babel/helper-validator-option@npm:7.18.6',
+			'browserslist@npm:4.21.4',
+			'semver@npm:6.3.0',
+		],
+	)
+})
+
+test('keeps properties from a newer yarn in extra', () => {
+	const contents = fixture.replace('  linkType: hard\n', '  linkType: hard\n  newField: yes\n')
+	const lockfile = parse(contents)
+	const entry = lockfile.entries().find((entry) => entry.extra.newField !== undefined)
+	assert.equal(entry?.extra.newField, 'yes')
+	assert.equal(lockfile.serialize(), contents)
+})
+
+test('serializes back to the same text', () => {
+	assert.equal(parse(fixture).serialize(), fixture)
+})
+
+test('throws an error with the position of a parse failure', () => {
+	assert.throws(() => parse('__metadata:\n  version: 8\n'), { line: 1, column: 1, offset: 0 })
+})
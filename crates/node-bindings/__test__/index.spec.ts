@@ -1,13 +1,99 @@
 import test from 'ava'
 import fs from 'node:fs'
+import { tmpdir } from 'node:os'
+import { join } from 'node:path'
 
-import { parse } from '../index'
+import { Lockfile, parse, parseFile } from '../index'
 
 test('can parse a file', (t) => {
 	// cwd is actually not the test dir, but the root of the project
-	// needs us to be buffered
 	const fileContents = fs.readFileSync('../../fixtures/berry.lock')
 	const parsed = parse(fileContents)
-	t.log(`result is ${parsed.length} bytes long`)
-	t.true(true)
+	t.deepEqual(parsed.metadata, { version: '6', cacheKey: '8' })
+	t.is(parsed.entries.length, 337)
+
+	const entry = parsed.entries.find((e) => e.descriptors.includes('@babel/helper-compilation-targets@npm:^7.19.1'))
+	t.is(entry?.version, '7.19.1')
+	t.is(entry?.resolution, '@babel/helper-compilation-targets@npm:7.19.1')
+	t.is(entry?.linkType, 'hard')
+	t.is(entry?.dependencies['semver'], '^6.3.0')
+	t.is(entry?.peerDependencies['@babel/core'], '^7.0.0')
+})
+
+test('accepts a string as well as a buffer', (t) => {
+	const fileContents = fs.readFileSync('../../fixtures/berry.lock', 'utf8')
+	t.is(parse(fileContents).entries.length, 337)
+})
+
+test('throws an error with a code frame on invalid input', (t) => {
+	const contents = Buffer.from('__metadata:\n  version: 8\n')
+	const error = t.throws(() => parse(contents))
+	t.regex(error.message, /at line 1, column 1/)
+	t.regex(error.message, /> 1 \| __metadata:/)
+	t.like(error, { line: 1, column: 1, offset: 0 })
+})
+
+test('keeps properties from a newer yarn in extra', (t) => {
+	const contents = fs
+		.readFileSync('../../fixtures/minimal-berry.lock', 'utf8')
+		.replace('  linkType: hard\n', '  linkType: hard\n  newField: yes\n')
+	const entry = parse(contents).entries.find((e) => e.extra['newField'] !== undefined)
+	t.is(entry?.extra['newField'], 'yes')
+	t.is(new Lockfile(contents).getEntry(entry!.descriptors[0])?.extra['newField'], 'yes')
+})
+
+test('Lockfile answers queries without converting every entry', (t) => {
+	const lockfile = new Lockfile(fs.readFileSync('../../fixtures/berry.lock'))
+	t.is(lockfile.length, 337)
+	t.deepEqual(lockfile.metadata, { version: '6', cacheKey: '8' })
+
+	t.is(lockfile.getEntry('@babel/core@npm:^7.0.0')?.version, '7.19.1')
+	// dependency ranges are written without yarn's default npm: protocol
+	t.is(lockfile.getEntry('@babel/core@^7.0.0')?.version, '7.19.1')
+	t.is(lockfile.getEntry('not-a-package@npm:1.0.0'), null)
+
+	t.deepEqual(
+		lockfile.findByName('semver').map((entry) => entry.version),
+		['6.3.0', '7.3.7'],
+	)
+
+	t.deepEqual(
+		lockfile.dependenciesOf('@babel/helper-compilation-targets@npm:7.19.1')?.map((entry) => entry.resolution),
+		[
+			'@babel/compat-data@npm:7.19.1',
+			'@babel/helper-validator-option@npm:7.18.6',
+			'browserslist@npm:4.21.4',
+			'semver@npm:6.3.0',
+		],
+	)
+	t.is(lockfile.dependenciesOf('not-a-package@npm:1.0.0'), null)
+})
+
+test('Lockfile entries() and toJSON() match parse', (t) => {
+	const contents = fs.readFileSync('../../fixtures/berry.lock')
+	const lockfile = new Lockfile(contents)
+	const parsed = parse(contents)
+
+	t.deepEqual([...lockfile.entries()], parsed.entries)
+	t.deepEqual(lockfile.toJSON(), parsed)
+})
+
+test('parseFile resolves to a Lockfile', async (t) => {
+	const lockfile = await parseFile('../../fixtures/berry.lock')
+	t.true(lockfile instanceof Lockfile)
+	t.is(lockfile.length, 337)
+})
+
+test('parseFile rejects with the position of a parse error', async (t) => {
+	const contents = fs.readFileSync('../../fixtures/berry.lock', 'utf8').replace('linkType: hard', 'linkType: sideways')
+	const path = join(tmpdir(), 'berry-invalid-link-type.lock')
+	fs.writeFileSync(path, contents)
+
+	const error = await t.throwsAsync(parseFile(path))
+	t.like(error, { line: 16, column: 3 })
+	t.regex(error.message, /invalid linkType `sideways`/)
+})
+
+test('parseFile rejects when the file cannot be read', async (t) => {
+	await t.throwsAsync(parseFile('../../fixtures/does-not-exist.lock'), { message: /failed to read/ })
 })
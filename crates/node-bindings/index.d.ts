@@ -1,3 +1,114 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
-export declare function parse(fileContents: Buffer): Buffer
+/**
+ * Iterator returned by `Lockfile.entries()`
+ *
+ * This type extends JavaScript's `Iterator`, and so has the iterator helper
+ * methods. It may extend the upcoming TypeScript `Iterator` class in the future.
+ *
+ * @see https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/Iterator#iterator_helper_methods
+ * @see https://www.typescriptlang.org/docs/handbook/release-notes/typescript-5-6.html#iterator-helper-methods
+ */
+export declare class EntryIterator extends Iterator<LockfileEntry, void, void> {
+	next(value?: void): IteratorResult<LockfileEntry, void>
+	return(value?: void): IteratorResult<LockfileEntry, void>
+	throw(err: any): IteratorResult<LockfileEntry, void>
+}
+
+/** A parsed yarn lockfile that is queried from JS without copying it all across */
+export declare class Lockfile {
+	/** Parse the contents of a yarn.lock file, throwing like `parse` on failure */
+	constructor(contents: string | Buffer)
+	/** The `__metadata` block */
+	get metadata(): LockfileMetadata
+	/** Number of entries in the lockfile */
+	get length(): number
+	/** The entry a descriptor like `debug@npm:^4.3.1` (or `debug@^4.3.1`) resolves to */
+	getEntry(descriptor: string): LockfileEntry | null
+	/** Every entry for a package name like `@babel/core`, one per resolved version */
+	findByName(name: string): Array<LockfileEntry>
+	/**
+	 * The entries that the `dependencies` of a locator like `debug@npm:4.3.4`
+	 * resolve to, or `null` if no entry has that resolution
+	 *
+	 * Peer dependencies are not included, as they are provided by the parent.
+	 */
+	dependenciesOf(locator: string): Array<LockfileEntry> | null
+	/** Iterate over the entries in file order, converting each one as it is reached */
+	entries(): EntryIterator
+	/** Convert the whole lockfile into the plain object returned by `parse` */
+	toJSON(): ParsedLockfile
+}
+
+export interface DependencyMeta {
+	built?: boolean
+	optional?: boolean
+	unplugged?: boolean
+}
+
+/** A single package, along with every descriptor that resolves to it */
+export interface LockfileEntry {
+	/** The descriptors on the entry's key line, e.g. `debug@npm:^4.3.1` */
+	descriptors: Array<string>
+	version?: string
+	/** The locator the descriptors resolved to, e.g. `debug@npm:4.3.4` */
+	resolution?: string
+	checksum?: string
+	conditions?: string
+	languageName: string
+	/** `hard` or `soft` */
+	linkType: string
+	/** Dependency name to range */
+	dependencies: Record<string, string>
+	/** Peer dependency name to range */
+	peerDependencies: Record<string, string>
+	dependenciesMeta: Record<string, DependencyMeta>
+	peerDependenciesMeta: Record<string, PeerDependencyMeta>
+	/** Binary name to path */
+	bin: Record<string, string>
+	/**
+	 * Properties the parser doesn't model, to their value, or to the
+	 * indented lines under them for a block
+	 */
+	extra: Record<string, string>
+}
+
+/** The `__metadata` block */
+export interface LockfileMetadata {
+	version: string
+	cacheKey: string
+}
+
+/**
+ * Parse the contents of a yarn.lock file
+ *
+ * Properties the parser doesn't know, such as ones added by a newer yarn,
+ * are kept in each entry's `extra` instead of failing the parse.
+ *
+ * Throws an `Error` with `line`, `column` and `offset` properties, and a code
+ * frame in its message, if the lockfile can't be parsed.
+ */
+export declare function parse(contents: string | Buffer): ParsedLockfile
+
+/**
+ * Read and parse the yarn.lock file at `path` on the libuv threadpool
+ *
+ * The promise rejects with the same `Error` that `parse` throws, including
+ * `line`, `column` and `offset`, if the lockfile can't be parsed.
+ */
+export declare function parseFile(path: string): Promise<Lockfile>
+
+/** A parsed yarn lockfile */
+export interface ParsedLockfile {
+	metadata: LockfileMetadata
+	entries: Array<LockfileEntry>
+	/** The top-level `resolutions` block, if present */
+	resolutions?: Record<string, string>
+	/** The top-level `constraints` block, if present */
+	constraints?: Record<string, string>
+}
+
+export interface PeerDependencyMeta {
+	optional: boolean
+}
+
@@ -646,5 +646,5 @@ if (!nativeBinding) {
 	throw new Error(`Failed to load native binding`)
 }
 
-const { parse } = nativeBinding
-export { parse }
+const { EntryIterator, Lockfile, parse, parseFile } = nativeBinding
+export { EntryIterator, Lockfile, parse, parseFile }
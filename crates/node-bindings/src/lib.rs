@@ -1,13 +1,63 @@
 //! A simple yarn lockfile parser
 //! Note - this is a slim public API file.
 
-use napi::bindgen_prelude::Buffer;
+use berry::ParseError;
+use berry::parse::ParseMode;
+use napi::bindgen_prelude::{Buffer, Either};
+use napi::{Env, JsValue};
 use napi_derive::napi;
 
+mod lockfile;
+mod parse_file;
+mod types;
+
+pub use lockfile::{EntryIterator, Lockfile};
+pub use parse_file::{ParseFileTask, parse_file};
+pub use types::{
+  DependencyMeta, LockfileEntry, LockfileMetadata, ParsedLockfile, PeerDependencyMeta,
+};
+
 // NOTE: this is in lib.rs for now ,but eventually we may want to separate
 // out the bindings, into its own crate
+/// Parse the contents of a yarn.lock file
+///
+/// Properties the parser doesn't know, such as ones added by a newer yarn,
+/// are kept in each entry's `extra` instead of failing the parse.
+///
+/// Throws an `Error` with `line`, `column` and `offset` properties, and a code
+/// frame in its message, if the lockfile can't be parsed.
 #[napi]
-pub fn parse(file_contents: Buffer) -> Buffer {
-  // wow fast!!!!
-  file_contents
+pub fn parse(env: Env, contents: Either<String, Buffer>) -> napi::Result<ParsedLockfile> {
+  let contents = read_contents(&contents)?;
+  let lockfile = parse_lockfile(contents).map_err(|e| parse_error(&env, &e, contents))?;
+  Ok(ParsedLockfile::new(&lockfile))
+}
+
+/// Parse leniently, so a lockfile from a newer yarn still loads
+fn parse_lockfile(contents: &str) -> Result<berry::lockfile::Lockfile, ParseError> {
+  berry::parse::parse_with_mode(contents, ParseMode::Lenient)
+}
+
+/// Borrow the lockfile text, which may be passed as a string or a UTF-8 `Buffer`
+fn read_contents(contents: &Either<String, Buffer>) -> napi::Result<&str> {
+  match contents {
+    Either::A(contents) => Ok(contents),
+    Either::B(buffer) => std::str::from_utf8(buffer)
+      .map_err(|e| napi::Error::from_reason(format!("lockfile is not valid UTF-8: {e}"))),
+  }
+}
+
+/// Surface a parse failure as a JS `Error` with a code frame pointing at the problem
+fn parse_error(env: &Env, error: &ParseError, contents: &str) -> napi::Error {
+  let message = format!("{error}\n\n{}", error.code_frame(contents));
+
+  let with_position = || -> napi::Result<napi::Error> {
+    let mut object = env.create_error(napi::Error::from_reason(message.clone()))?;
+    object.set("line", error.line as u32)?;
+    object.set("column", error.column as u32)?;
+    object.set("offset", error.offset as u32)?;
+    Ok(object.to_unknown().into())
+  };
+
+  with_position().unwrap_or_else(|_| napi::Error::from_reason(message))
 }
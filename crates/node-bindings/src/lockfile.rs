@@ -0,0 +1,141 @@
+//! A handle to a parsed lockfile that stays on the Rust side
+//!
+//! Converting every entry into JS objects costs more than parsing, so the
+//! `Lockfile` class keeps the `berry::lockfile::Lockfile` alive and only
+//! converts the entries that are asked for.
+
+use crate::types::{LockfileEntry, LockfileMetadata, ParsedLockfile};
+use crate::{parse_error, parse_lockfile, read_contents};
+use berry::query::{LockfileIndex, find_by_name};
+use napi::Env;
+use napi::bindgen_prelude::{Buffer, Either, Generator};
+use napi_derive::napi;
+use std::sync::Arc;
+
+/// The parsed lockfile, plus lookup tables built once up front
+struct Indexed {
+  lockfile: berry::lockfile::Lockfile,
+  index: LockfileIndex,
+}
+
+impl Indexed {
+  fn entry(&self, position: usize) -> LockfileEntry {
+    LockfileEntry::new(&self.lockfile.entries[position], &self.lockfile.interner)
+  }
+}
+
+/// A parsed yarn lockfile that is queried from JS without copying it all across
+#[napi]
+pub struct Lockfile {
+  inner: Arc<Indexed>,
+}
+
+impl Lockfile {
+  pub(crate) fn from_lockfile(lockfile: berry::lockfile::Lockfile) -> Self {
+    Self {
+      inner: Arc::new(Indexed {
+        index: LockfileIndex::new(&lockfile),
+        lockfile,
+      }),
+    }
+  }
+}
+
+#[napi]
+impl Lockfile {
+  /// Parse the contents of a yarn.lock file, throwing like `parse` on failure
+  #[napi(constructor)]
+  pub fn new(env: Env, contents: Either<String, Buffer>) -> napi::Result<Self> {
+    let contents = read_contents(&contents)?;
+    let lockfile = parse_lockfile(contents).map_err(|e| parse_error(&env, &e, contents))?;
+    Ok(Self::from_lockfile(lockfile))
+  }
+
+  /// The `__metadata` block
+  #[napi(getter)]
+  pub fn metadata(&self) -> LockfileMetadata {
+    LockfileMetadata::new(&self.inner.lockfile.metadata)
+  }
+
+  /// Number of entries in the lockfile
+  #[napi(getter)]
+  pub fn length(&self) -> u32 {
+    self.inner.lockfile.entries.len() as u32
+  }
+
+  /// The entry a descriptor like `debug@npm:^4.3.1` (or `debug@^4.3.1`) resolves to
+  #[napi]
+  pub fn get_entry(&self, descriptor: String) -> Option<LockfileEntry> {
+    let position = self
+      .inner
+      .index
+      .find_descriptor(&self.inner.lockfile, &descriptor)?;
+    Some(self.inner.entry(position))
+  }
+
+  /// Every entry for a package name like `@babel/core`, one per resolved version
+  #[napi]
+  pub fn find_by_name(&self, name: String) -> Vec<LockfileEntry> {
+    find_by_name(&self.inner.lockfile, &name)
+      .into_iter()
+      .map(|position| self.inner.entry(position))
+      .collect()
+  }
+
+  /// The entries that the `dependencies` of a locator like `debug@npm:4.3.4`
+  /// resolve to, or `null` if no entry has that resolution
+  ///
+  /// Peer dependencies are not included, as they are provided by the parent.
+  #[napi]
+  pub fn dependencies_of(&self, locator: String) -> Option<Vec<LockfileEntry>> {
+    let positions = self
+      .inner
+      .index
+      .dependencies_of(&self.inner.lockfile, &locator)?;
+    Some(
+      positions
+        .into_iter()
+        .map(|position| self.inner.entry(position))
+        .collect(),
+    )
+  }
+
+  /// Iterate over the entries in file order, converting each one as it is reached
+  #[napi]
+  pub fn entries(&self) -> EntryIterator {
+    EntryIterator {
+      inner: Arc::clone(&self.inner),
+      next: 0,
+    }
+  }
+
+  /// Convert the whole lockfile into the plain object returned by `parse`
+  #[napi(js_name = "toJSON")]
+  pub fn to_json(&self) -> ParsedLockfile {
+    ParsedLockfile::new(&self.inner.lockfile)
+  }
+}
+
+/// Iterator returned by `Lockfile.entries()`
+#[napi(iterator)]
+pub struct EntryIterator {
+  inner: Arc<Indexed>,
+  next: usize,
+}
+
+#[napi]
+impl Generator for EntryIterator {
+  type Yield = LockfileEntry;
+  type Next = ();
+  type Return = ();
+
+  fn next(&mut self, _value: Option<Self::Next>) -> Option<Self::Yield> {
+    if self.next >= self.inner.lockfile.entries.len() {
+      return None;
+    }
+
+    let entry = self.inner.entry(self.next);
+    self.next += 1;
+    Some(entry)
+  }
+}
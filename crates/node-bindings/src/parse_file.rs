@@ -0,0 +1,55 @@
+//! Reading and parsing a lockfile on the libuv threadpool
+
+use crate::lockfile::Lockfile;
+use crate::{parse_error, parse_lockfile};
+use berry::ParseError;
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Task};
+use napi_derive::napi;
+
+/// Reads and parses the lockfile at `path` off the main thread
+pub struct ParseFileTask {
+  path: String,
+  /// The error and lockfile text of a failed parse, kept for `reject`, which
+  /// has the `Env` needed to attach the position to the JS error
+  failure: Option<(ParseError, String)>,
+}
+
+impl Task for ParseFileTask {
+  type Output = berry::lockfile::Lockfile;
+  type JsValue = Lockfile;
+
+  fn compute(&mut self) -> napi::Result<Self::Output> {
+    let contents = std::fs::read_to_string(&self.path)
+      .map_err(|e| napi::Error::from_reason(format!("failed to read {}: {e}", self.path)))?;
+
+    parse_lockfile(&contents).map_err(|error| {
+      let reason = error.to_string();
+      self.failure = Some((error, contents));
+      napi::Error::from_reason(reason)
+    })
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+    Ok(Lockfile::from_lockfile(output))
+  }
+
+  fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+    match self.failure.take() {
+      Some((error, contents)) => Err(parse_error(&env, &error, &contents)),
+      None => Err(err),
+    }
+  }
+}
+
+/// Read and parse the yarn.lock file at `path` on the libuv threadpool
+///
+/// The promise rejects with the same `Error` that `parse` throws, including
+/// `line`, `column` and `offset`, if the lockfile can't be parsed.
+#[napi(ts_return_type = "Promise<Lockfile>")]
+pub fn parse_file(path: String) -> AsyncTask<ParseFileTask> {
+  AsyncTask::new(ParseFileTask {
+    path,
+    failure: None,
+  })
+}
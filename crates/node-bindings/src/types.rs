@@ -0,0 +1,165 @@
+//! Plain JS objects returned by `parse`
+//!
+//! These mirror `berry::lockfile::Lockfile`, with idents and descriptors
+//! flattened to strings and maps sorted by key, as yarn writes them.
+
+use berry::intern::Interner;
+use berry::lockfile::{Entry, Lockfile, Metadata};
+use berry::metadata;
+use berry::package::{ExtraProperty, Package};
+use napi_derive::napi;
+use std::collections::BTreeMap;
+
+/// A parsed yarn lockfile
+#[napi(object)]
+pub struct ParsedLockfile {
+  pub metadata: LockfileMetadata,
+  pub entries: Vec<LockfileEntry>,
+  /// The top-level `resolutions` block, if present
+  pub resolutions: Option<BTreeMap<String, String>>,
+  /// The top-level `constraints` block, if present
+  pub constraints: Option<BTreeMap<String, String>>,
+}
+
+/// The `__metadata` block
+#[napi(object)]
+pub struct LockfileMetadata {
+  pub version: String,
+  pub cache_key: String,
+}
+
+/// A single package, along with every descriptor that resolves to it
+#[napi(object)]
+pub struct LockfileEntry {
+  /// The descriptors on the entry's key line, e.g. `debug@npm:^4.3.1`
+  pub descriptors: Vec<String>,
+  pub version: Option<String>,
+  /// The locator the descriptors resolved to, e.g. `debug@npm:4.3.4`
+  pub resolution: Option<String>,
+  pub checksum: Option<String>,
+  pub conditions: Option<String>,
+  pub language_name: String,
+  /// `hard` or `soft`
+  pub link_type: String,
+  /// Dependency name to range
+  pub dependencies: BTreeMap<String, String>,
+  /// Peer dependency name to range
+  pub peer_dependencies: BTreeMap<String, String>,
+  pub dependencies_meta: BTreeMap<String, DependencyMeta>,
+  pub peer_dependencies_meta: BTreeMap<String, PeerDependencyMeta>,
+  /// Binary name to path
+  pub bin: BTreeMap<String, String>,
+  /// Properties the parser doesn't model, to their value, or to the
+  /// indented lines under them for a block
+  pub extra: BTreeMap<String, String>,
+}
+
+#[napi(object)]
+pub struct DependencyMeta {
+  pub built: Option<bool>,
+  pub optional: Option<bool>,
+  pub unplugged: Option<bool>,
+}
+
+#[napi(object)]
+pub struct PeerDependencyMeta {
+  pub optional: bool,
+}
+
+impl ParsedLockfile {
+  pub fn new(lockfile: &Lockfile) -> Self {
+    let pairs = |pairs: &Vec<(String, String)>| pairs.iter().cloned().collect();
+
+    Self {
+      metadata: LockfileMetadata::new(&lockfile.metadata),
+      entries: lockfile
+        .entries
+        .iter()
+        .map(|entry| LockfileEntry::new(entry, &lockfile.interner))
+        .collect(),
+      resolutions: lockfile.resolutions.as_ref().map(pairs),
+      constraints: lockfile.constraints.as_ref().map(pairs),
+    }
+  }
+}
+
+impl LockfileMetadata {
+  pub fn new(metadata: &Metadata) -> Self {
+    Self {
+      version: metadata.version.clone(),
+      cache_key: metadata.cache_key.clone(),
+    }
+  }
+}
+
+impl LockfileEntry {
+  pub fn new(entry: &Entry, interner: &Interner) -> Self {
+    let package: &Package = &entry.package;
+    let name = |ident: &berry::ident::Ident| ident.display(interner).to_string();
+
+    Self {
+      descriptors: entry
+        .descriptors
+        .iter()
+        .map(|descriptor| descriptor.display(interner).to_string())
+        .collect(),
+      version: package.version.clone(),
+      resolution: package.resolution.clone(),
+      checksum: package.checksum.clone(),
+      conditions: package.conditions.clone(),
+      language_name: package.language_name.as_str(interner).to_string(),
+      link_type: package.link_type.as_str().to_string(),
+      dependencies: package
+        .dependencies
+        .iter()
+        .map(|(ident, descriptor)| (name(ident), descriptor.range().to_string()))
+        .collect(),
+      peer_dependencies: package
+        .peer_dependencies
+        .iter()
+        .map(|(ident, descriptor)| (name(ident), descriptor.range().to_string()))
+        .collect(),
+      dependencies_meta: package
+        .dependencies_meta
+        .iter()
+        .map(|(ident, meta)| (name(ident), DependencyMeta::new(meta.as_ref())))
+        .collect(),
+      peer_dependencies_meta: package
+        .peer_dependencies_meta
+        .iter()
+        .map(|(ident, meta)| (name(ident), PeerDependencyMeta::new(meta)))
+        .collect(),
+      bin: package
+        .bin
+        .iter()
+        .map(|(name, path)| (name.clone(), path.clone()))
+        .collect(),
+      extra: package
+        .extra
+        .iter()
+        .map(|(key, value)| {
+          let (ExtraProperty::Scalar(value) | ExtraProperty::Block(value)) = value;
+          (key.clone(), value.clone())
+        })
+        .collect(),
+    }
+  }
+}
+
+impl DependencyMeta {
+  fn new(meta: Option<&metadata::DependencyMeta>) -> Self {
+    Self {
+      built: meta.and_then(|meta| meta.built),
+      optional: meta.and_then(|meta| meta.optional),
+      unplugged: meta.and_then(|meta| meta.unplugged),
+    }
+  }
+}
+
+impl PeerDependencyMeta {
+  fn new(meta: &metadata::PeerDependencyMeta) -> Self {
+    Self {
+      optional: meta.optional,
+    }
+  }
+}